@@ -0,0 +1,3 @@
+#!/bin/sh
+cargo rustc --release --bin fabchess -- -C target-cpu=native
+./target/release/fabchess
@@ -1,3 +1,3 @@
 cargo rustc --release --bin referee -- -C target-cpu=skylake
-"./target/release/referee.exe" config REFEREE_CONFIG.json
+"./target/release/referee.exe" selfplay config REFEREE_CONFIG.json
 pause
\ No newline at end of file
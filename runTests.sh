@@ -0,0 +1,3 @@
+#!/bin/sh
+cargo rustc --release --bin referee -- -C target-cpu=native
+./target/release/referee ${1:-selfplay} config REFEREE_CONFIG.json
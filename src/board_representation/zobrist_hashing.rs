@@ -6,6 +6,8 @@ pub fn rand_u64(rng: &mut StdRng) -> u64 {
     let res: u64 = rng.gen();
     res
 }
+//Transposition tables saved to disk are only valid with the keys generated from the same seed
+pub const ZOBRIST_SEED: [u8; 32] = [42; 32];
 lazy_static! {
     pub static ref ZOBRIST_KEYS: Zobrist = init_zobrist();
 }
@@ -31,7 +33,7 @@ pub fn rand_array_8(rng: &mut StdRng) -> [u64; 8] {
 }
 
 pub fn init_zobrist() -> Zobrist {
-    let mut generator: StdRng = SeedableRng::from_seed([42; 32]);
+    let mut generator: StdRng = SeedableRng::from_seed(ZOBRIST_SEED);
 
     Zobrist {
         w_pawns: rand_array_64(&mut generator),
@@ -0,0 +1,273 @@
+use super::get_distance;
+use super::kpk::probe_kpk;
+use crate::bitboards::FILES;
+use crate::board_representation::game_state::{
+    GameState, BISHOP, BLACK, KING, KNIGHT, PAWN, QUEEN, ROOK, WHITE,
+};
+
+//Scale factors are applied to the endgame score and are out of SCALE_NORMAL
+pub const SCALE_NORMAL: i16 = 128;
+pub const SCALE_DRAW: i16 = 0;
+pub const SCALE_OPPOSITE_BISHOPS: i16 = 48;
+pub const SCALE_OPPOSITE_BISHOPS_WITH_ROOKS: i16 = 96;
+pub const SCALE_ROOK_VS_MINOR: i16 = 16;
+
+pub const KNOWN_WIN: i16 = 1000;
+pub const KPK_WIN: i16 = 600;
+pub const KPK_PAWN_ADVANCE: i16 = 20;
+pub const KBNK_CORNER: i16 = 40;
+pub const PUSH_TO_EDGE: i16 = 20;
+pub const PUSH_CLOSE: i16 = 10;
+
+pub const LIGHT_SQUARES: u64 = 0x55AA_55AA_55AA_55AA;
+pub const DARK_SQUARES: u64 = !LIGHT_SQUARES;
+
+//Piece counts of one side: pawns, knights, bishops, rooks, queens
+fn material(g: &GameState, side: usize) -> [u32; 5] {
+    [
+        g.pieces[PAWN][side].count_ones(),
+        g.pieces[KNIGHT][side].count_ones(),
+        g.pieces[BISHOP][side].count_ones(),
+        g.pieces[ROOK][side].count_ones(),
+        g.pieces[QUEEN][side].count_ones(),
+    ]
+}
+
+const BARE_KING: [u32; 5] = [0, 0, 0, 0, 0];
+const KP: [u32; 5] = [1, 0, 0, 0, 0];
+const KBN: [u32; 5] = [0, 1, 1, 0, 0];
+const KQ: [u32; 5] = [0, 0, 0, 0, 1];
+const KR: [u32; 5] = [0, 0, 0, 1, 0];
+const KB: [u32; 5] = [0, 0, 1, 0, 0];
+const KN: [u32; 5] = [0, 1, 0, 0, 0];
+
+//Manhattan distance of a square to the center, 0 for the center squares and 6 for the corners
+fn center_distance(sq: usize) -> i16 {
+    let file = (sq % 8) as i16;
+    let rank = (sq / 8) as i16;
+    (if file < 4 { 3 - file } else { file - 4 }) + (if rank < 4 { 3 - rank } else { rank - 4 })
+}
+
+fn king_distance(g: &GameState) -> i16 {
+    get_distance(g.king_square(WHITE) as isize, g.king_square(BLACK) as isize) as i16
+}
+
+//Returns the evaluation from white's point of view if the position is a known endgame which is evaluated by a specialized evaluator
+pub fn evaluate_specialized(g: &GameState) -> Option<i16> {
+    let mut all_pieces = 0u64;
+    for piece in g.pieces.iter() {
+        all_pieces |= piece[WHITE] | piece[BLACK];
+    }
+    if (all_pieces & !(g.pieces[KING][WHITE] | g.pieces[KING][BLACK])).count_ones() > 2 {
+        return None;
+    }
+    let (white, black) = (material(g, WHITE), material(g, BLACK));
+    for &(strong, strong_material, weak_material) in
+        [(WHITE, white, black), (BLACK, black, white)].iter()
+    {
+        let score = if strong_material == KP && weak_material == BARE_KING {
+            kpk(g, strong)
+        } else if strong_material == KBN && weak_material == BARE_KING {
+            kbnk(g, strong)
+        } else if strong_material == KQ && weak_material == KR {
+            kqkr(g, strong)
+        } else {
+            continue;
+        };
+        return Some(if strong == WHITE { score } else { -score });
+    }
+    None
+}
+
+//King and pawn vs king, evaluated by the bitbase
+pub fn kpk(g: &GameState, strong: usize) -> i16 {
+    if kpk_is_win(g, strong) {
+        let pawn = g.pieces[PAWN][strong].trailing_zeros() as usize;
+        let relative_rank = if strong == WHITE {
+            pawn / 8
+        } else {
+            7 - pawn / 8
+        };
+        KPK_WIN + KPK_PAWN_ADVANCE * relative_rank as i16
+    } else {
+        0
+    }
+}
+
+//Probes the bitbase for a position with exactly one pawn of the strong side and no other pieces
+pub fn kpk_is_win(g: &GameState, strong: usize) -> bool {
+    let mut strong_king = g.king_square(strong);
+    let mut weak_king = g.king_square(1 - strong);
+    let mut pawn = g.pieces[PAWN][strong].trailing_zeros() as usize;
+    if strong == BLACK {
+        strong_king ^= 56;
+        weak_king ^= 56;
+        pawn ^= 56;
+    }
+    if pawn % 8 >= 4 {
+        strong_king ^= 7;
+        weak_king ^= 7;
+        pawn ^= 7;
+    }
+    let stm = if g.color_to_move == strong {
+        WHITE
+    } else {
+        BLACK
+    };
+    probe_kpk(stm, strong_king, weak_king, pawn)
+}
+
+//King, bishop and knight vs king. The weak king has to be driven into a corner of the bishop's color
+pub fn kbnk(g: &GameState, strong: usize) -> i16 {
+    let weak_king = g.king_square(1 - strong) as isize;
+    let corners: [isize; 2] = if g.pieces[BISHOP][strong] & DARK_SQUARES != 0 {
+        [0, 63]
+    } else {
+        [7, 56]
+    };
+    let corner_distance =
+        get_distance(weak_king, corners[0]).min(get_distance(weak_king, corners[1])) as i16;
+    KNOWN_WIN + KBNK_CORNER * (7 - corner_distance) + PUSH_CLOSE * (7 - king_distance(g))
+}
+
+//King and queen vs king and rook. The weak king has to be driven to the edge
+pub fn kqkr(g: &GameState, strong: usize) -> i16 {
+    let weak_king = g.king_square(1 - strong);
+    KNOWN_WIN + PUSH_TO_EDGE * center_distance(weak_king) + PUSH_CLOSE * (7 - king_distance(g))
+}
+
+//Returns the factor by which the endgame score is scaled, out of SCALE_NORMAL
+pub fn scale_factor(g: &GameState, eg_score: i16) -> i16 {
+    let strong = if eg_score > 0 { WHITE } else { BLACK };
+    let weak = 1 - strong;
+
+    //Step 1. Rook pawns with the wrong bishop
+    let strong_pawns = g.pieces[PAWN][strong];
+    if strong_pawns != 0
+        && g.pieces[BISHOP][strong] != 0
+        && g.pieces[KNIGHT][strong] | g.pieces[ROOK][strong] | g.pieces[QUEEN][strong] == 0
+    {
+        let file = if strong_pawns & !FILES[0] == 0 {
+            Some(0)
+        } else if strong_pawns & !FILES[7] == 0 {
+            Some(7)
+        } else {
+            None
+        };
+        if let Some(file) = file {
+            let promotion_square = if strong == WHITE { 56 + file } else { file };
+            let wrong_bishops = if (1u64 << promotion_square) & LIGHT_SQUARES != 0 {
+                DARK_SQUARES
+            } else {
+                LIGHT_SQUARES
+            };
+            if g.pieces[BISHOP][strong] & !wrong_bishops == 0
+                && get_distance(g.king_square(weak) as isize, promotion_square as isize) <= 1
+            {
+                return SCALE_DRAW;
+            }
+        }
+    }
+
+    //Step 2. Opposite colored bishops
+    let (white_bishops, black_bishops) = (g.pieces[BISHOP][WHITE], g.pieces[BISHOP][BLACK]);
+    if white_bishops.count_ones() == 1
+        && black_bishops.count_ones() == 1
+        && (white_bishops & LIGHT_SQUARES == 0) != (black_bishops & LIGHT_SQUARES == 0)
+    {
+        let (white, black) = (material(g, WHITE), material(g, BLACK));
+        if white[1..] == KB[1..] && black[1..] == KB[1..] {
+            return SCALE_OPPOSITE_BISHOPS;
+        }
+        let bishop_and_rook = [0, 0, 1, 1, 0];
+        if white[1..] == bishop_and_rook[1..] && black[1..] == bishop_and_rook[1..] {
+            return SCALE_OPPOSITE_BISHOPS_WITH_ROOKS;
+        }
+    }
+
+    //Step 3. Rook vs minor piece without pawns
+    let (strong_material, weak_material) = (material(g, strong), material(g, weak));
+    if strong_material == KR && (weak_material == KB || weak_material == KN) {
+        return SCALE_ROOK_VS_MINOR;
+    }
+
+    SCALE_NORMAL
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluation::eval_game_state_from_null;
+
+    fn eval(fen: &str) -> i16 {
+        eval_game_state_from_null(&GameState::from_fen(fen)).final_eval
+    }
+
+    #[test]
+    fn kpk_test() {
+        //King on the sixth rank in front of the pawn wins with either side to move
+        assert!(eval("3k4/8/3K4/3P4/8/8/8/8 w - - 0 1") >= KPK_WIN);
+        assert!(eval("3k4/8/3K4/3P4/8/8/8/8 b - - 0 1") >= KPK_WIN);
+        assert!(eval("8/8/8/8/4p3/4k3/8/4K3 w - - 0 1") <= -KPK_WIN);
+        //Stalemate with black to move
+        assert!(eval("4k3/4P3/4K3/8/8/8/8/8 w - - 0 1") >= KPK_WIN);
+        assert_eq!(eval("4k3/4P3/4K3/8/8/8/8/8 b - - 0 1"), 0);
+        //Defending king in front of the pawn with the opposition
+        assert_eq!(eval("8/8/8/4k3/4P3/4K3/8/8 w - - 0 1"), 0);
+        //Defending king in front of the rook pawn
+        assert_eq!(eval("k7/8/8/8/P7/8/8/K7 w - - 0 1"), 0);
+        assert_eq!(eval("8/8/8/8/7p/8/k7/7K b - - 0 1"), 0);
+        //Pawn runs away from the defending king
+        assert!(eval("8/8/8/8/8/8/4P3/4K2k w - - 0 1") >= KPK_WIN);
+        assert!(eval("4k2K/4p3/8/8/8/8/8/8 b - - 0 1") <= -KPK_WIN);
+        //Promoting is better than keeping the pawn
+        assert!(eval("4Q3/8/8/8/8/8/k7/4K3 b - - 0 1") > eval("8/4P3/8/8/8/8/k7/4K3 w - - 0 1"));
+    }
+
+    #[test]
+    fn kbnk_test() {
+        //Dark-squared bishop, so the king has to be driven to a1 or h8
+        let right_corner = eval("7k/8/5K2/8/8/8/8/2B1N3 w - - 0 1");
+        let wrong_corner = eval("k7/8/2K5/8/8/8/8/2B1N3 w - - 0 1");
+        let center = eval("8/8/8/3k4/8/8/8/2B1NK2 w - - 0 1");
+        assert!(right_corner >= KNOWN_WIN);
+        assert!(right_corner > wrong_corner);
+        assert!(center > wrong_corner);
+        assert!(eval("2b1n3/8/8/8/8/8/8/K1k5 w - - 0 1") < -KNOWN_WIN);
+    }
+
+    #[test]
+    fn kqkr_test() {
+        let edge = eval("3k4/8/3K4/8/8/8/8/4Q2r w - - 0 1");
+        let center = eval("8/8/8/3k4/8/8/8/4Q1Kr w - - 0 1");
+        assert!(edge >= KNOWN_WIN);
+        assert!(edge > center);
+        assert!(eval("q2R4/8/8/8/8/3K4/8/3k4 b - - 0 1") <= -KNOWN_WIN);
+    }
+
+    #[test]
+    fn scale_factor_test() {
+        //Opposite colored bishops with an extra pawn
+        let g = GameState::from_fen("8/5k2/2p5/1pPb4/1P6/4B3/3K2P1/8 w - - 0 1");
+        assert_eq!(scale_factor(&g, 100), SCALE_OPPOSITE_BISHOPS);
+        let g = GameState::from_fen("3r4/5k2/2p5/1pPb4/1P6/4B3/3K2P1/3R4 w - - 0 1");
+        assert_eq!(scale_factor(&g, 100), SCALE_OPPOSITE_BISHOPS_WITH_ROOKS);
+        //Same colored bishops are not scaled
+        let g = GameState::from_fen("8/5k2/2p5/1pP5/1P1b4/4B3/3K2P1/8 w - - 0 1");
+        assert_eq!(scale_factor(&g, 100), SCALE_NORMAL);
+        //Rook pawn with the wrong bishop
+        assert_eq!(eval("k7/8/8/8/8/8/P7/2B1K3 w - - 0 1"), 0);
+        assert_eq!(eval("k7/8/8/8/P7/P7/P7/2B1K3 w - - 0 1"), 0);
+        assert_eq!(eval("8/7p/8/8/8/8/8/3kb2K w - - 0 1"), 0);
+        //Right bishop or the defending king far away
+        assert!(eval("k7/8/8/8/8/8/P7/1B2K3 w - - 0 1") > 0);
+        assert!(eval("8/8/8/4k3/8/8/P7/2B1K3 w - - 0 1") > 0);
+        //Rook vs minor piece
+        let g = GameState::from_fen("8/8/3k4/3b4/8/8/3K4/7R w - - 0 1");
+        assert_eq!(scale_factor(&g, 100), SCALE_ROOK_VS_MINOR);
+        assert!(eval("8/8/3k4/3b4/8/8/3K4/7R w - - 0 1").abs() < 150);
+        assert!(eval("8/8/3k4/3n4/8/8/3K4/7R w - - 0 1").abs() < 150);
+        let g = GameState::from_fen("8/8/3k4/3n4/8/8/3K1P2/7R w - - 0 1");
+        assert_eq!(scale_factor(&g, 100), SCALE_NORMAL);
+    }
+}
@@ -0,0 +1,189 @@
+use crate::bitboards::KING_ATTACKS;
+use crate::board_representation::game_state::{BLACK, WHITE};
+
+//King and pawn vs king bitbase, generated by retrograde analysis at program start.
+//Positions are normalized so that the strong side is white and the pawn is on files a-d.
+//Index layout: white king (6 bits), black king (6 bits), side to move (1 bit), pawn file (2 bits), 6 - pawn rank (3 bits)
+pub const KPK_MAX_INDEX: usize = 2 * 24 * 64 * 64;
+
+const INVALID: u8 = 0;
+const UNKNOWN: u8 = 1;
+const DRAW: u8 = 2;
+const WIN: u8 = 4;
+
+lazy_static! {
+    pub static ref KPK_BITBASE: Vec<u64> = init_kpk_bitbase();
+}
+
+pub fn init_kpk() {
+    lazy_static::initialize(&KPK_BITBASE);
+}
+
+#[inline(always)]
+pub fn kpk_index(stm: usize, white_king: usize, black_king: usize, pawn: usize) -> usize {
+    white_king | (black_king << 6) | (stm << 12) | ((pawn % 8) << 13) | ((6 - pawn / 8) << 15)
+}
+
+#[inline(always)]
+fn pawn_attacks(pawn: usize) -> u64 {
+    let mut res = 0u64;
+    let file = pawn % 8;
+    if file > 0 {
+        res |= 1u64 << (pawn + 7);
+    }
+    if file < 7 {
+        res |= 1u64 << (pawn + 9);
+    }
+    res
+}
+
+fn initial_classification(stm: usize, white_king: usize, black_king: usize, pawn: usize) -> u8 {
+    let distance = ((white_king / 8) as isize - (black_king / 8) as isize)
+        .abs()
+        .max(((white_king % 8) as isize - (black_king % 8) as isize).abs());
+    if distance <= 1
+        || white_king == pawn
+        || black_king == pawn
+        || (stm == WHITE && pawn_attacks(pawn) & (1u64 << black_king) != 0)
+    {
+        return INVALID;
+    }
+    if stm == WHITE
+        && pawn / 8 == 6
+        && white_king != pawn + 8
+        && black_king != pawn + 8
+        && (KING_ATTACKS[black_king] & (1u64 << (pawn + 8)) == 0
+            || KING_ATTACKS[white_king] & (1u64 << (pawn + 8)) != 0)
+    {
+        //Pawn promotes without being captured
+        return WIN;
+    }
+    if stm == BLACK
+        && (KING_ATTACKS[black_king] & !(KING_ATTACKS[white_king] | pawn_attacks(pawn)) == 0
+            || KING_ATTACKS[black_king] & !KING_ATTACKS[white_king] & (1u64 << pawn) != 0)
+    {
+        //Stalemate or the pawn can be captured
+        return DRAW;
+    }
+    UNKNOWN
+}
+
+fn classify(db: &[u8], stm: usize, white_king: usize, black_king: usize, pawn: usize) -> u8 {
+    let (good, bad) = if stm == WHITE {
+        (WIN, DRAW)
+    } else {
+        (DRAW, WIN)
+    };
+    let mut res = INVALID;
+    let mut king_moves = if stm == WHITE {
+        KING_ATTACKS[white_king]
+    } else {
+        KING_ATTACKS[black_king]
+    };
+    while king_moves != 0 {
+        let to = king_moves.trailing_zeros() as usize;
+        king_moves &= king_moves - 1;
+        res |= if stm == WHITE {
+            db[kpk_index(BLACK, to, black_king, pawn)]
+        } else {
+            db[kpk_index(WHITE, white_king, to, pawn)]
+        };
+    }
+    if stm == WHITE {
+        if pawn / 8 < 6 {
+            res |= db[kpk_index(BLACK, white_king, black_king, pawn + 8)];
+        }
+        if pawn / 8 == 1 && pawn + 8 != white_king && pawn + 8 != black_king {
+            res |= db[kpk_index(BLACK, white_king, black_king, pawn + 16)];
+        }
+    }
+    if res & good != 0 {
+        good
+    } else if res & UNKNOWN != 0 {
+        UNKNOWN
+    } else {
+        bad
+    }
+}
+
+pub fn init_kpk_bitbase() -> Vec<u64> {
+    let mut db = vec![INVALID; KPK_MAX_INDEX];
+    //Step 1. Classify all positions which can be decided without looking at any moves
+    for stm in 0..2 {
+        for white_king in 0..64 {
+            for black_king in 0..64 {
+                for pawn_rank in 1..7 {
+                    for pawn_file in 0..4 {
+                        let pawn = pawn_rank * 8 + pawn_file;
+                        db[kpk_index(stm, white_king, black_king, pawn)] =
+                            initial_classification(stm, white_king, black_king, pawn);
+                    }
+                }
+            }
+        }
+    }
+    //Step 2. Iterate until no unknown position can be resolved anymore
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for stm in 0..2 {
+            for white_king in 0..64 {
+                for black_king in 0..64 {
+                    for pawn_rank in 1..7 {
+                        for pawn_file in 0..4 {
+                            let pawn = pawn_rank * 8 + pawn_file;
+                            let index = kpk_index(stm, white_king, black_king, pawn);
+                            if db[index] == UNKNOWN {
+                                let res = classify(&db, stm, white_king, black_king, pawn);
+                                if res != UNKNOWN {
+                                    db[index] = res;
+                                    changed = true;
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+    //Step 3. Everything still unknown can't be forced to a win
+    let mut bitbase = vec![0u64; KPK_MAX_INDEX / 64];
+    for (index, result) in db.iter().enumerate() {
+        if *result == WIN {
+            bitbase[index / 64] |= 1u64 << (index % 64);
+        }
+    }
+    bitbase
+}
+
+//Squares have to be normalized already: strong side is white, pawn on files a-d and rank 2-7
+#[inline(always)]
+pub fn probe_kpk(stm: usize, white_king: usize, black_king: usize, pawn: usize) -> bool {
+    let index = kpk_index(stm, white_king, black_king, pawn);
+    KPK_BITBASE[index / 64] & (1u64 << (index % 64)) != 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::probe_kpk;
+    use crate::board_representation::game_state::{BLACK, WHITE};
+
+    #[test]
+    fn kpk_bitbase_test() {
+        //White king on the sixth rank in front of the pawn always wins
+        //Kd6, Pd5 vs Kd8
+        assert!(probe_kpk(WHITE, 43, 59, 35));
+        assert!(probe_kpk(BLACK, 43, 59, 35));
+        //Kd6, Pd7 vs Kd8 is stalemate with black to move, but Kc6 wins with white to move
+        assert!(probe_kpk(WHITE, 43, 59, 51));
+        assert!(!probe_kpk(BLACK, 43, 59, 51));
+        //Kd3, Pd4 vs Kd5: the defending king has the opposition in front of the pawn
+        assert!(!probe_kpk(WHITE, 19, 35, 27));
+        //Rook pawn with the defending king in the corner
+        //Ka1, Pa4 vs Ka8
+        assert!(!probe_kpk(WHITE, 0, 56, 24));
+        assert!(!probe_kpk(BLACK, 0, 56, 24));
+        //Kd1, Pd2 vs Kh1: the defending king is outside of the pawn's square
+        assert!(probe_kpk(WHITE, 3, 7, 11));
+    }
+}
@@ -1,3 +1,5 @@
+pub mod endgame;
+pub mod kpk;
 pub mod params;
 pub mod phase;
 pub mod psqt_evaluation;
@@ -6,8 +8,6 @@ use super::bitboards;
 use super::board_representation::game_state::{
     GameState, BISHOP, BLACK, KING, KNIGHT, PAWN, QUEEN, ROOK, WHITE,
 };
-#[cfg(feature = "display-eval")]
-use super::logging::log;
 use super::move_generation::movegen;
 use crate::board_representation::game_state_attack_container::{
     GameStateAttackContainer, MGSA_BISHOP, MGSA_KNIGHT, MGSA_QUEEN, MGSA_ROOKS,
@@ -101,6 +101,88 @@ pub struct EvaluationResult {
     pub trace: Trace,
 }
 
+//All terms of the evaluation, indexed by side. Used for displaying the evaluation
+pub struct EvaluationBreakdown {
+    pub tempo: [EvaluationScore; 2],
+    pub psqt: [EvaluationScore; 2],
+    pub material: [EvaluationScore; 2],
+    pub pawns: [EvaluationScore; 2],
+    pub knights: [EvaluationScore; 2],
+    pub piecewise: [EvaluationScore; 2],
+    pub king: [EvaluationScore; 2],
+    pub phase: f64,
+    pub scale_factor: i16,
+    pub specialized: Option<i16>,
+    pub final_eval: i16,
+    #[cfg(feature = "texel-tuning")]
+    pub trace: Trace,
+}
+
+impl EvaluationBreakdown {
+    pub fn terms(&self) -> [(&'static str, [EvaluationScore; 2]); 7] {
+        [
+            ("Tempo", self.tempo),
+            ("PSQT", self.psqt),
+            ("Material", self.material),
+            ("Pawns", self.pawns),
+            ("Knights", self.knights),
+            ("Piecewise", self.piecewise),
+            ("King safety", self.king),
+        ]
+    }
+
+    pub fn sum(&self) -> EvaluationScore {
+        let mut res = EvaluationScore::default();
+        for (_, term) in self.terms().iter() {
+            res += term[WHITE] - term[BLACK];
+        }
+        res
+    }
+}
+
+impl Display for EvaluationBreakdown {
+    fn fmt(&self, formatter: &mut Formatter) -> Result {
+        let mut res_str = String::new();
+        let separator = "+-------------+-------------+-------------+-------------+\n";
+        res_str.push_str(separator);
+        res_str.push_str("|        Term |    White    |    Black    |    Total    |\n");
+        res_str.push_str("|             |   MG    EG  |   MG    EG  |   MG    EG  |\n");
+        res_str.push_str(separator);
+        for (name, term) in self.terms().iter() {
+            let total = term[WHITE] - term[BLACK];
+            res_str.push_str(&format!(
+                "| {:>11} | {:>5} {:>5} | {:>5} {:>5} | {:>5} {:>5} |\n",
+                name, term[WHITE].0, term[WHITE].1, term[BLACK].0, term[BLACK].1, total.0, total.1
+            ));
+        }
+        res_str.push_str(separator);
+        let sum = self.sum();
+        res_str.push_str(&format!(
+            "| {:>11} |             |             | {:>5} {:>5} |\n",
+            "Total", sum.0, sum.1
+        ));
+        res_str.push_str(separator);
+        res_str.push_str(&format!("Phase: {:.2}\n", self.phase));
+        res_str.push_str(&format!(
+            "Scale factor: {}/{}\n",
+            self.scale_factor,
+            endgame::SCALE_NORMAL
+        ));
+        if self.specialized.is_some() {
+            res_str.push_str(&format!(
+                "Final evaluation: {} (specialized endgame evaluation)\n",
+                self.final_eval
+            ));
+        } else {
+            res_str.push_str(&format!(
+                "Final evaluation: {} (EG/1.5 and scaled, interpolated by phase)\n",
+                self.final_eval
+            ));
+        }
+        write!(formatter, "{}", res_str)
+    }
+}
+
 pub fn eval_game_state_from_null(g: &GameState) -> EvaluationResult {
     let mgsac = GameStateAttackContainer::from_state(g);
     eval_game_state(g, &mgsac, -16000, 16000)
@@ -112,178 +194,100 @@ pub fn eval_game_state(
     _alpha: i16, //Lazy Eval components, unneeded currently
     _beta: i16,
 ) -> EvaluationResult {
-    #[cfg(feature = "display-eval")]
-    {
-        log(&format!("Evaluating GameState fen: {}\n", g.to_fen()));
-    }
     let mut result = EvaluationResult {
         final_eval: 0,
         #[cfg(feature = "texel-tuning")]
         trace: Trace::default(),
     };
-    let phase = g.phase.phase;
-    #[cfg(feature = "texel-tuning")]
-    {
-        result.trace.phase = phase;
+    if let Some(score) = endgame::evaluate_specialized(g) {
+        result.final_eval = score;
+        return result;
     }
-    let mut res = EvaluationScore::default();
+    result.final_eval =
+        evaluate_terms(g, attacks, &mut result, !cfg!(feature = "texel-tuning")).final_eval;
+    result
+}
 
-    if g.color_to_move == WHITE {
-        res += TEMPO_BONUS;
-    } else {
-        res -= TEMPO_BONUS;
-    }
-    #[cfg(feature = "display-eval")]
-    {
-        let tempo = if g.color_to_move == WHITE {
-            TEMPO_BONUS
+//Every term of the evaluation is computed here, for the search as well as for the breakdown.
+//With incremental_psqt the PSQT score kept by the game state is used, but only as white's term
+#[inline(always)]
+fn evaluate_terms(
+    g: &GameState,
+    attacks: &GameStateAttackContainer,
+    result: &mut EvaluationResult,
+    incremental_psqt: bool,
+) -> EvaluationBreakdown {
+    let mut breakdown = EvaluationBreakdown {
+        tempo: [EvaluationScore::default(); 2],
+        psqt: if incremental_psqt {
+            [g.psqt, EvaluationScore::default()]
         } else {
-            TEMPO_BONUS * -1
-        };
-        log(&format!("\nTempo:{}\n", tempo));
-    }
+            [
+                psqt(true, &g.pieces, result),
+                psqt(false, &g.pieces, result),
+            ]
+        },
+        material: [
+            piece_values(true, g, result),
+            piece_values(false, g, result),
+        ],
+        pawns: [
+            pawns(true, g, result, attacks),
+            pawns(false, g, result, attacks),
+        ],
+        knights: [
+            knights(true, g, result, attacks),
+            knights(false, g, result, attacks),
+        ],
+        piecewise: [
+            piecewise(true, g, result, attacks),
+            piecewise(false, g, result, attacks),
+        ],
+        king: [king(true, g, result), king(false, g, result)],
+        phase: g.phase.phase,
+        scale_factor: endgame::SCALE_NORMAL,
+        specialized: None,
+        final_eval: 0,
+        #[cfg(feature = "texel-tuning")]
+        trace: Trace::default(),
+    };
+    breakdown.tempo[g.color_to_move] = TEMPO_BONUS;
+    let sum = breakdown.sum();
+    breakdown.scale_factor = endgame::scale_factor(g, sum.1);
     #[cfg(feature = "texel-tuning")]
     {
+        result.trace.phase = breakdown.phase;
         result.trace.tempo_bonus = if g.color_to_move == WHITE { 1 } else { -1 };
+        result.trace.scale_factor = f64::from(breakdown.scale_factor);
     }
+    breakdown.final_eval = final_evaluation(sum, breakdown.phase, breakdown.scale_factor);
+    breakdown
+}
 
-    let psqt_score: EvaluationScore =
-        if cfg!(feature = "display-eval") || cfg!(feature = "texel-tuning") {
-            let (psqt_w, psqt_b) = (
-                psqt(true, &g.pieces, &mut result),
-                psqt(false, &g.pieces, &mut result),
-            );
-            (psqt_w - psqt_b)
-        } else {
-            g.psqt
-        };
-    #[cfg(feature = "display-eval")]
-    {
-        log(&format!("\nPSQT Sum: {}\n", psqt_score));
-    }
-    res += psqt_score;
-
-    let (pieces_w, pieces_b) = (
-        piece_values(true, g, &mut result),
-        piece_values(false, g, &mut result),
-    );
-    #[cfg(feature = "display-eval")]
-    {
-        log(&format!(
-            "\nPiece value Sum: {} - {} -> {}\n",
-            pieces_w,
-            pieces_b,
-            pieces_w - pieces_b
-        ));
-    }
-    res += pieces_w - pieces_b;
-
-    /*let lazy_eval = EvaluationScore(res.0, (f64::from(res.1) / 1.5) as i16);
-    let lazy_eval = lazy_eval.interpolate(phase);
-
-    if lazy_eval + FIRST_LAZY_MARGIN < alpha {
-        result.final_eval = lazy_eval + FIRST_LAZY_MARGIN;
-        return result;
-    } else if lazy_eval - FIRST_LAZY_MARGIN > beta {
-        result.final_eval = lazy_eval - FIRST_LAZY_MARGIN;
-        return result;
-    }*/
-    let (pawns_w, pawns_b) = (
-        pawns(true, g, &mut result, attacks),
-        pawns(false, g, &mut result, attacks),
-    );
-    #[cfg(feature = "display-eval")]
-    {
-        log(&format!(
-            "\nPawn Sum: {} - {} -> {}\n",
-            pawns_w,
-            pawns_b,
-            pawns_w - pawns_b
-        ));
-    }
-    res += pawns_w - pawns_b;
-
-    /*let lazy_eval = EvaluationScore(res.0, (f64::from(res.1) / 1.5) as i16);
-    let lazy_eval = lazy_eval.interpolate(phase);
-
-    if lazy_eval + SECOND_LAZY_MARGIN < alpha {
-        result.final_eval = lazy_eval + SECOND_LAZY_MARGIN;
-        return result;
-    } else if lazy_eval - SECOND_LAZY_MARGIN > beta {
-        result.final_eval = lazy_eval - SECOND_LAZY_MARGIN;
-        return result;
-    }*/
-
-    let (knights_w, knights_b) = (
-        knights(true, g, &mut result, attacks),
-        knights(false, g, &mut result, attacks),
-    );
-    #[cfg(feature = "display-eval")]
-    {
-        log(&format!(
-            "\nKnights Sum: {} - {} -> {}\n",
-            knights_w,
-            knights_b,
-            knights_w - knights_b
-        ));
-    }
-    res += knights_w - knights_b;
-
-    let (piecewise_w, piecewise_b) = (
-        piecewise(true, g, &mut result, attacks),
-        piecewise(false, g, &mut result, attacks),
-    );
-    #[cfg(feature = "display-eval")]
-    {
-        log(&format!(
-            "\nPiecewise Sum: {} - {} -> {}\n",
-            piecewise_w,
-            piecewise_b,
-            piecewise_w - piecewise_b
-        ));
-    }
-    res += piecewise_w - piecewise_b;
-
-    let (king_w, king_b) = (king(true, g, &mut result), king(false, g, &mut result));
-    #[cfg(feature = "display-eval")]
-    {
-        log(&format!(
-            "\nKing Sum: {} - {} -> {}\n",
-            king_w,
-            king_b,
-            king_w - king_b
-        ));
-    }
-    res += king_w - king_b;
-
+pub fn final_evaluation(mut res: EvaluationScore, phase: f64, scale_factor: i16) -> i16 {
     res.1 = (f64::from(res.1) / 1.5) as i16;
+    res.1 = (i32::from(res.1) * i32::from(scale_factor) / i32::from(endgame::SCALE_NORMAL)) as i16;
     //Phasing is done the same way stockfish does it
-    let final_res = res.interpolate(phase);
-    #[cfg(feature = "display-eval")]
+    res.interpolate(phase)
+}
+
+pub fn eval_breakdown(g: &GameState) -> EvaluationBreakdown {
+    let attacks = GameStateAttackContainer::from_state(g);
+    let mut result = EvaluationResult {
+        final_eval: 0,
+        #[cfg(feature = "texel-tuning")]
+        trace: Trace::default(),
+    };
+    let mut breakdown = evaluate_terms(g, &attacks, &mut result, false);
+    #[cfg(feature = "texel-tuning")]
     {
-        log(&format!(
-            "\nSum: {} + {} + {} + {} + {} + {} + {} -> {} (EG/=1.5)\n",
-            psqt_score,
-            knights_w - knights_b,
-            piecewise_w - piecewise_b,
-            king_w - king_b,
-            pawns_w - pawns_b,
-            pieces_w - pieces_b,
-            if g.color_to_move == 0 {
-                TEMPO_BONUS
-            } else {
-                TEMPO_BONUS * -1
-            },
-            res
-        ));
-        log(&format!("Phase: {}\n", phase));
-        log(&format!(
-            "\nFinal Result: ({} * {} + {} * (128.0 - {}))/128.0 -> {}",
-            res.0, phase, res.1, phase, final_res,
-        ));
+        breakdown.trace = result.trace;
     }
-    result.final_eval = final_res;
-    result
+    breakdown.specialized = endgame::evaluate_specialized(g);
+    if let Some(score) = breakdown.specialized {
+        breakdown.final_eval = score;
+    }
+    breakdown
 }
 
 pub fn knights(
@@ -331,20 +335,6 @@ pub fn knights(
         }
     }
     res += outpost;
-    #[cfg(feature = "display-eval")]
-    {
-        log(&format!(
-            "\nKnights for {}:\n",
-            if white { "White" } else { "Black" }
-        ));
-        log(&format!(
-            "\tSupported by pawns: {} -> {}\n",
-            supported_knights_amount,
-            KNIGHT_SUPPORTED_BY_PAWN * supported_knights_amount,
-        ));
-        log(&format!("\tOutposts: {} -> {}\n", _outposts, outpost));
-        log(&format!("Sum: {}\n", res));
-    }
 
     res
 }
@@ -636,109 +626,6 @@ pub fn piecewise(
         + QUEEN_XRAY_KING * queen_xray_king
         + attack;
 
-    #[cfg(feature = "display-eval")]
-    {
-        log(&format!(
-            "\nPiecewise for {}:\n",
-            if white { "White" } else { "Black" }
-        ));
-        log(&format!("\tMobility Knight: {}\n", mk));
-        log(&format!("\tMobility Bishop: {}\n", mb));
-        log(&format!("\tBishop Diagonally Adj: {}\n", mb_diag));
-        log(&format!("\tMobility Rook  : {}\n", mr));
-        log(&format!("\tMobility Queen : {}\n", mq));
-        log(&format!(
-            "\tBishopXrayKing : {} -> {}\n",
-            bishop_xray_king,
-            BISHOP_XRAY_KING * bishop_xray_king,
-        ));
-        log(&format!(
-            "\tRookXrayKing : {} -> {}\n",
-            rook_xray_king,
-            ROOK_XRAY_KING * rook_xray_king,
-        ));
-        log(&format!(
-            "\tQueenXrayKing : {} -> {}\n",
-            queen_xray_king,
-            QUEEN_XRAY_KING * queen_xray_king,
-        ));
-        log(&format!(
-            "\tRooks on open  : {} -> {}\n",
-            rooks_onopen,
-            ROOK_ON_OPEN_FILE_BONUS * rooks_onopen,
-        ));
-        log(&format!(
-            "\tRooks on semi-open  : {} -> {}\n",
-            rooks_on_semi_open,
-            ROOK_ON_SEMI_OPEN_FILE_BONUS * rooks_on_semi_open,
-        ));
-        log(&format!(
-            "\tQueens on open  : {} -> {}\n",
-            queens_onopen,
-            QUEEN_ON_OPEN_FILE_BONUS * queens_onopen,
-        ));
-        log(&format!(
-            "\tQueens on semi-open  : {} -> {}\n",
-            queens_on_semi_open,
-            QUEEN_ON_SEMI_OPEN_FILE_BONUS * queens_on_semi_open,
-        ));
-        log(&format!(
-            "\tRooks on seventh: {} -> {}\n",
-            rooks_onseventh,
-            ROOK_ON_SEVENTH * rooks_onseventh
-        ));
-        log(&format!(
-            "\tKnight Attackers: Num: {} , Val: {}\n",
-            knight_attackers, knight_attacker_values
-        ));
-        log(&format!(
-            "\tBishop Attackers: Num: {} , Val: {}\n",
-            bishop_attackers, bishop_attacker_values
-        ));
-        log(&format!(
-            "\tRook Attackers: Num: {} , Val: {}\n",
-            rook_attackers, rook_attacker_values
-        ));
-        log(&format!(
-            "\tQueen Attackers: Num: {} , Val: {}\n",
-            queen_attackers, queen_attacker_values
-        ));
-        log(&format!(
-            "\tSum Attackers: (Num: {} , Val: {}\n",
-            knight_attackers + bishop_attackers + rook_attackers + queen_attackers,
-            knight_attacker_values
-                + bishop_attacker_values
-                + rook_attacker_values
-                + queen_attacker_values
-        ));
-        log(&format!(
-            "\tAttack MG value: {} * {} / 100.0 -> {}\n",
-            SAFETY_TABLE[(knight_attacker_values.0
-                + bishop_attacker_values.0
-                + rook_attacker_values.0
-                + queen_attacker_values.0)
-                .min(99) as usize]
-                .0,
-            ATTACK_WEIGHT[(knight_attackers + bishop_attackers + rook_attackers + queen_attackers)
-                .min(7) as usize]
-                .0,
-            attack_mg
-        ));
-        log(&format!(
-            "\tAttack EG value: {} * {} / 100.0 -> {}\n",
-            SAFETY_TABLE[(knight_attacker_values.1
-                + bishop_attacker_values.1
-                + rook_attacker_values.1
-                + queen_attacker_values.1)
-                .min(99) as usize]
-                .1,
-            ATTACK_WEIGHT[(knight_attackers + bishop_attackers + rook_attackers + queen_attackers)
-                .min(7) as usize]
-                .1,
-            attack_eg
-        ));
-        log(&format!("Sum: {}\n", res));
-    }
     res
 }
 
@@ -784,22 +671,6 @@ pub fn king(white: bool, g: &GameState, _eval: &mut EvaluationResult) -> Evaluat
     let res = SHIELDING_PAWN_MISSING[shields_missing]
         + SHIELDING_PAWN_MISSING_ON_OPEN_FILE[shields_on_open_missing];
 
-    #[cfg(feature = "display-eval")]
-    {
-        log(&format!(
-            "\nKing for {}:\n",
-            if white { "White" } else { "Black" }
-        ));
-        log(&format!(
-            "\tShield pawn missing: {} -> {}\n",
-            shields_missing, SHIELDING_PAWN_MISSING[shields_missing],
-        ));
-        log(&format!(
-            "\tShield pawn on open file missing: {} -> {}\n",
-            shields_on_open_missing, SHIELDING_PAWN_MISSING_ON_OPEN_FILE[shields_on_open_missing],
-        ));
-        log(&format!("Sum: {}\n", res));
-    }
     res
 }
 
@@ -998,60 +869,6 @@ pub fn pawns(
         _eval.trace.pawn_passed_weak += weak_passers as i8 * if side == WHITE { 1 } else { -1 };
     }
     res += passer_score + PAWN_PASSED_WEAK * weak_passers + passer_dist;
-    #[cfg(feature = "display-eval")]
-    {
-        log(&format!(
-            "\nPawns for {}:\n",
-            if white { "White" } else { "Black" }
-        ));
-        log(&format!(
-            "\tDoubled: {} -> {}\n",
-            doubled_pawns,
-            PAWN_DOUBLED_VALUE * doubled_pawns
-        ));
-        log(&format!(
-            "\tIsolated: {} -> {}\n",
-            isolated_pawns,
-            PAWN_ISOLATED_VALUE * isolated_pawns,
-        ));
-        log(&format!(
-            "\tBackward: {} -> {}\n",
-            backward_pawns,
-            PAWN_BACKWARD_VALUE * backward_pawns,
-        ));
-        log(&format!("\tSupported: {} -> {}\n", _supported_amt, supp));
-        log(&format!(
-            "\tAttack Center: {} -> {}\n",
-            center_attack_pawns,
-            PAWN_ATTACK_CENTER * center_attack_pawns,
-        ));
-        log(&format!(
-            "\tMobility: {} -> {}\n",
-            pawn_mobility,
-            PAWN_MOBILITY * pawn_mobility,
-        ));
-        log(&format!(
-            "\tPasser Blocked/Not Blocked: {} , {} -> {}\n",
-            _passer_normal, _passer_notblocked, passer_score
-        ));
-        log(&format!(
-            "\tRook behind passer: {} -> {}\n",
-            rooks_support_passer,
-            ROOK_BEHIND_SUPPORT_PASSER * rooks_support_passer,
-        ));
-        log(&format!(
-            "\tEnemy Rook behind passer: {} -> {}\n",
-            enemy_rooks_attack_passer,
-            ROOK_BEHIND_ENEMY_PASSER * enemy_rooks_attack_passer,
-        ));
-        log(&format!(
-            "\tWeak passer: {} -> {}\n",
-            weak_passers,
-            PAWN_PASSED_WEAK * weak_passers,
-        ));
-        log(&format!("\tPassers distance to kings -> {}\n", passer_dist));
-        log(&format!("Sum: {}\n", res));
-    }
     res
 }
 
@@ -1095,41 +912,5 @@ pub fn piece_values(white: bool, g: &GameState, _eval: &mut EvaluationResult) ->
         _eval.trace.rooks += my_rooks as i8 * if side == WHITE { 1 } else { -1 };
         _eval.trace.queens += my_queens as i8 * if side == WHITE { 1 } else { -1 };
     }
-    #[cfg(feature = "display-eval")]
-    {
-        log(&format!(
-            "\nPiece values for {}\n",
-            if white { "White" } else { "Black" }
-        ));
-        log(&format!(
-            "\tPawns: {} -> {}\n",
-            my_pawns,
-            PAWN_PIECE_VALUE * my_pawns,
-        ));
-        log(&format!(
-            "\tKnights: {} -> {}\n",
-            my_knights,
-            (KNIGHT_PIECE_VALUE + KNIGHT_VALUE_WITH_PAWNS[pawns_on_board]) * my_knights,
-        ));
-        log(&format!(
-            "\tBishops: {} -> {}\n",
-            my_bishops,
-            BISHOP_PIECE_VALUE * my_bishops,
-        ));
-        if my_bishops > 1 {
-            log(&format!("\tBishop-Pair: {} -> {}\n", 1, BISHOP_PAIR_BONUS));
-        }
-        log(&format!(
-            "\tRooks: {} -> {}\n",
-            my_rooks,
-            ROOK_PIECE_VALUE * my_rooks,
-        ));
-        log(&format!(
-            "\tQueens: {} -> {}\n",
-            my_queens,
-            QUEEN_PIECE_VALUE * my_queens,
-        ));
-        log(&format!("Sum: {}\n", res));
-    }
     res
 }
@@ -4,8 +4,6 @@ use super::EvaluationScore;
 use crate::board_representation::game_state::{
     PieceType, BISHOP, BLACK, KING, KNIGHT, PAWN, QUEEN, ROOK, WHITE,
 };
-#[cfg(feature = "display-eval")]
-use crate::logging::log;
 
 pub const BLACK_INDEX: [usize; 64] = [
     56, 57, 58, 59, 60, 61, 62, 63, 48, 49, 50, 51, 52, 53, 54, 55, 40, 41, 42, 43, 44, 45, 46, 47,
@@ -102,20 +100,6 @@ pub fn psqt(white: bool, pieces: &[[u64; 2]; 6], _eval: &mut EvaluationResult) -
         _eval.trace.psqt_king[king_idx / 8][king_idx % 8] += if side == WHITE { 1 } else { -1 };
     }
     let sum = pawn + knight + bishop + rook + queen + king;
-    #[cfg(feature = "display-eval")]
-    {
-        log(&format!(
-            "\nPSQT for {}:\n",
-            if white { "White" } else { "Black" }
-        ));
-        log(&format!("\tPawns  : {}\n", pawn));
-        log(&format!("\tKnights: {}\n", knight));
-        log(&format!("\tBishops: {}\n", bishop));
-        log(&format!("\tRooks: {}\n", rook));
-        log(&format!("\tQueens: {}\n", queen));
-        log(&format!("\tKing   : {}\n", king));
-        log(&format!("Sum: {}\n", sum));
-    }
     sum
 }
 
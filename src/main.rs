@@ -11,6 +11,7 @@ fn main() {
     core::move_generation::magic::init_magics();
     core::board_representation::zobrist_hashing::init_at_program_start();
     core::search::init_constants();
+    core::evaluation::kpk::init_kpk();
     log("Should have initialized everything!");
 
     let new_now = Instant::now();
@@ -19,13 +20,23 @@ fn main() {
         new_now.duration_since(now).as_secs() * 1000
             + u64::from(new_now.duration_since(now).subsec_millis())
     ));
-    let mut args = std::env::args();
-    if args.nth(1) == Some("bench".to_owned()) {
-        bench(
-            args.nth(2)
-                .and_then(|depth| depth.parse::<usize>().ok())
-                .unwrap_or(13),
-        );
+    let mut args = std::env::args().skip(1);
+    if args.next() == Some("bench".to_owned()) {
+        let depth = args
+            .next()
+            .and_then(|depth| depth.parse::<usize>().ok())
+            .unwrap_or(13);
+        //Comma separated thread counts, e.g. "bench 13 1,2,4,8" to measure the SMP scaling
+        let threads: Vec<usize> = args
+            .next()
+            .map(|threads| {
+                threads
+                    .split(',')
+                    .map(|count| count.parse::<usize>().expect("Invalid thread count!"))
+                    .collect()
+            })
+            .unwrap_or_else(|| vec![1]);
+        bench(depth, &threads);
     } else {
         core::uci::uci_parser::parse_loop();
     }
@@ -83,28 +94,52 @@ const BENCHMARKING_POSITIONS: [&str; 50] = [
     "3br1k1/p1pn3p/1p3n2/5pNq/2P1p3/1PN3PP/P2Q1PB1/4R1K1 w - - 0 23",
     "2r2b2/5p2/5k2/p1r1pP2/P2pB3/1P3P2/K1P3R1/7R w - - 23 93",
 ];
-fn bench(depth: usize) {
+fn bench(depth: usize, threads: &[usize]) {
     let itcs = Arc::new(InterThreadCommunicationSystem::new());
-    InterThreadCommunicationSystem::update_thread_count(&itcs, 1);
-    *itcs.cache() = core::search::cache::Cache::with_size(8);
-    let before_time = Instant::now();
-    let mut nodes = 0;
-    for position in BENCHMARKING_POSITIONS.iter() {
-        let state = core::board_representation::game_state::GameState::from_fen(position);
-        core::search::searcher::search_move(
-            Arc::clone(&itcs),
-            depth as i16,
-            state,
-            Vec::new(),
-            core::search::timecontrol::TimeControl::Infinite,
-        );
-        nodes += itcs.get_nodes_sum();
-        itcs.cache().clear();
+    itcs.replace_cache(core::search::cache::Cache::with_size(8));
+    let mut runs = Vec::with_capacity(threads.len());
+    for &thread_count in threads {
+        InterThreadCommunicationSystem::update_thread_count(&itcs, thread_count);
+        let before_time = Instant::now();
+        let mut nodes = 0;
+        for position in BENCHMARKING_POSITIONS.iter() {
+            let state = core::board_representation::game_state::GameState::from_fen(position);
+            core::search::searcher::search_move(
+                Arc::clone(&itcs),
+                depth as i16,
+                state,
+                Vec::new(),
+                core::search::timecontrol::TimeControl::Infinite,
+            );
+            nodes += itcs.get_nodes_sum();
+            itcs.cache().clear();
+        }
+        let dur = Instant::now()
+            .duration_since(before_time)
+            .as_millis()
+            .max(1);
+        println!("Threads: {}", thread_count);
+        println!("Time: {}ms", dur);
+        println!("Nodes: {}", nodes);
+        println!("NPS: {:.0}", 1000. * nodes as f64 / dur as f64);
+        runs.push((thread_count, dur, nodes));
+    }
+    if runs.len() > 1 {
+        //Time to depth and nodes per second relative to the first thread count
+        let (_, base_dur, base_nodes) = runs[0];
+        let base_nps = base_nodes as f64 / base_dur as f64;
+        println!("Threads\tTime\tNodes\tTime-Speedup\tNPS-Speedup");
+        for (thread_count, dur, nodes) in runs {
+            println!(
+                "{}\t{}ms\t{}\t{:.2}\t{:.2}",
+                thread_count,
+                dur,
+                nodes,
+                base_dur as f64 / dur as f64,
+                nodes as f64 / dur as f64 / base_nps
+            );
+        }
     }
-    let dur = Instant::now().duration_since(before_time).as_millis();
-    println!("Time: {}ms", dur);
-    println!("Nodes: {}", nodes);
-    println!("NPS: {:.0}", 1000. * nodes as f64 / dur as f64)
 }
 #[cfg(test)]
 mod tests {
@@ -112,6 +147,7 @@ mod tests {
     use core::board_representation::game_state_attack_container::GameStateAttackContainer;
     use core::evaluation::phase::Phase;
     use core::evaluation::psqt_evaluation::psqt;
+    use core::evaluation::{eval_breakdown, eval_game_state_from_null};
     use core::misc::KING_BASE_PATH;
     use core::move_generation::makemove::make_move;
     use core::move_generation::movegen;
@@ -361,6 +397,19 @@ mod tests {
         }
     }
 
+    #[test]
+    fn eval_breakdown_test() {
+        for position in super::BENCHMARKING_POSITIONS.iter() {
+            let g = GameState::from_fen(position);
+            let breakdown = eval_breakdown(&g);
+            assert_eq!(
+                breakdown.final_eval,
+                eval_game_state_from_null(&g).final_eval
+            );
+            assert_eq!(breakdown.psqt[0] - breakdown.psqt[1], g.psqt);
+        }
+    }
+
     #[test]
     #[ignore]
     fn pgn_test() {
@@ -19,18 +19,22 @@ impl Iterator for GameParser {
         let next = self.pgn_parser.next();
         let mut vec_res: Vec<GameMove> = Vec::new();
         let mut vec_gs: Vec<GameState> = Vec::new();
-        vec_gs.push(GameState::standard());
         match next {
             None => None,
-            Some(res) => {
-                let game = res.replace("\r", "").replace("\n", " ");
-                if game.contains("--") || game.contains('*') || game.contains("..") {
+            Some((fen, res)) => {
+                //Games of the referee start from the FEN tag if the opening was an EPD position
+                vec_gs.push(match fen {
+                    Some(fen) => GameState::from_fen(&fen),
+                    None => GameState::standard(),
+                });
+                let game = strip_comments(&res.replace("\r", "").replace("\n", " "));
+                if game.contains("--") || game.contains('*') {
                     //Invalid state
                     return Some((vec_res, vec_gs, -2));
                 }
                 //log(&format!("{}\n", game));
-                let moves = game.split(' ').collect::<Vec<&str>>();
-                for move_str in moves.iter().take(moves.len() - 2) {
+                let moves = game.split_whitespace().collect::<Vec<&str>>();
+                for move_str in moves.iter().take(moves.len() - 1) {
                     let mut move_str = (*move_str).to_string();
                     if move_str.contains('.') {
                         move_str = move_str.rsplit('.').collect::<Vec<&str>>()[0].to_string();
@@ -53,7 +57,7 @@ impl Iterator for GameParser {
                         break;
                     }
                 }
-                let last_elem = moves[moves.len() - 2];
+                let last_elem = moves[moves.len() - 1];
                 let mut score = 0;
                 if last_elem == "1-0" {
                     score = 1;
@@ -68,6 +72,24 @@ impl Iterator for GameParser {
     }
 }
 
+//Removes {comments}, like the one the referee writes after the opening
+pub fn strip_comments(move_text: &str) -> String {
+    let mut res = String::with_capacity(move_text.len());
+    let mut in_comment = false;
+    for character in move_text.chars() {
+        match character {
+            '{' => in_comment = true,
+            '}' => {
+                in_comment = false;
+                res.push(' ');
+            }
+            _ if !in_comment => res.push(character),
+            _ => {}
+        }
+    }
+    res
+}
+
 pub fn find_castle(
     movelist: &movegen::MoveList,
     g: &GameState,
@@ -283,18 +305,22 @@ pub struct PGNParser {
     pub reader: BufReader<File>,
 }
 
+//Yields the FEN tag, if there is one, and the move text of every game
 impl Iterator for PGNParser {
-    type Item = String;
+    type Item = (Option<String>, String);
 
-    fn next(&mut self) -> Option<String> {
+    fn next(&mut self) -> Option<(Option<String>, String)> {
         let mut res_str = String::new();
+        let mut fen = None;
         let mut line = String::new();
         let mut res = self.reader.read_line(&mut line);
         while match res {
             Err(_e) => false,
             Ok(_e) => true,
         } {
-            if line.contains("1.") && !line.contains('[') {
+            if line.starts_with("[FEN ") {
+                fen = line.split('"').nth(1).map(|fen| fen.to_owned());
+            } else if !line.trim().is_empty() && !line.contains('[') {
                 loop {
                     res_str.push_str(&line);
                     if res_str.contains("1-0")
@@ -325,9 +351,70 @@ impl Iterator for PGNParser {
             }
         }
         if !res_str.is_empty() {
-            Some(res_str)
+            Some((fen, res_str))
         } else {
             None
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pgn::pgn_writer::{get_pgn_string, PGNMetadata};
+    use std::io::Write;
+
+    #[test]
+    fn fen_start_test() {
+        let fen = "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3";
+        let mut state = GameState::from_fen(fen);
+        let mut movelist = movegen::MoveList::default();
+        let mut moves = Vec::new();
+        for mv in ["f1b5", "a7a6", "b5c6", "d7c6", "e1g1"].iter() {
+            let (from, to, _) = GameMove::string_to_move(mv);
+            let attack_container = GameStateAttackContainer::from_state(&state);
+            movegen::generate_moves(&state, false, &mut movelist, &attack_container);
+            let mv = movelist
+                .move_list
+                .iter()
+                .map(|mv| mv.0)
+                .find(|mv| mv.from as usize == from && mv.to as usize == to)
+                .unwrap();
+            state = make_move(&state, &mv);
+            moves.push(mv);
+        }
+        let mut metadata = PGNMetadata::default();
+        metadata.result = Some("1/2-1/2".to_owned());
+        //Black to move, so the move text starts with "3..."
+        let black_start = make_move(&GameState::from_fen(fen), &moves[0]);
+        metadata.starting_position = black_start.to_fen();
+        let mut pgn = get_pgn_string(&metadata, moves[1..].to_vec(), Some(2));
+        metadata.starting_position = fen.to_owned();
+        pgn.push_str(&get_pgn_string(&metadata, moves.clone(), Some(2)));
+
+        let path = std::env::temp_dir().join("fabchess_fen_start_test.pgn");
+        File::create(&path)
+            .unwrap()
+            .write_all(pgn.as_bytes())
+            .unwrap();
+        let parser = GameParser {
+            pgn_parser: PGNParser {
+                reader: BufReader::new(File::open(&path).unwrap()),
+            },
+            is_opening: false,
+            opening_load_untilply: 0,
+            move_list: movegen::MoveList::default(),
+            attack_container: GameStateAttackContainer::default(),
+        };
+        let games: Vec<_> = parser.collect();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[0].0, moves[1..].to_vec());
+        assert_eq!(games[1].0, moves);
+        for game in games.iter() {
+            assert_eq!(game.1.last().unwrap().hash, state.hash);
+            assert_eq!(game.2, 0);
+        }
+        assert_eq!(games[1].1[0].hash, GameState::from_fen(fen).hash);
+    }
+}
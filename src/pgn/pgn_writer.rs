@@ -14,6 +14,10 @@ pub struct PGNMetadata {
     pub black: Option<String>,
     pub result: Option<String>,
     pub termination: Option<String>,
+    pub time_control: Option<String>,
+    //Only used if both players have different time controls
+    pub white_time_control: Option<String>,
+    pub black_time_control: Option<String>,
     pub starting_position: String,
 }
 impl PGNMetadata {
@@ -34,6 +38,9 @@ impl Default for PGNMetadata {
             black: None,
             result: None,
             termination: None,
+            time_control: None,
+            white_time_control: None,
+            black_time_control: None,
             starting_position: crate::misc::STD_FEN.to_owned(),
         }
     }
@@ -90,6 +97,15 @@ pub fn get_pgn_string(
     if let Some(s) = &metadata.termination {
         res_str.push_str(&format!("[Termination \"{}\"]\n", s));
     }
+    if let Some(s) = &metadata.time_control {
+        res_str.push_str(&format!("[TimeControl \"{}\"]\n", s));
+    }
+    if let Some(s) = &metadata.white_time_control {
+        res_str.push_str(&format!("[WhiteTimeControl \"{}\"]\n", s));
+    }
+    if let Some(s) = &metadata.black_time_control {
+        res_str.push_str(&format!("[BlackTimeControl \"{}\"]\n", s));
+    }
     if metadata.starting_position != crate::misc::STD_FEN {
         res_str.push_str("[SetUp \"1\"]\n");
         res_str.push_str(&format!("[FEN \"{}\"]\n", metadata.starting_position));
@@ -4,7 +4,10 @@ use super::*;
 use super::{MATE_SCORE, MAX_SEARCH_DEPTH, STANDARD_SCORE};
 use crate::evaluation::eval_game_state;
 use crate::move_generation::makemove::{make_move, make_nullmove};
-use crate::search::moveordering::{MoveOrderer, NORMAL_STAGES};
+use crate::move_generation::movegen;
+use crate::search::history::{update_history_entry, HISTORY_MAX};
+use crate::search::moveordering::{MoveOrderer, NORMAL_STAGES, QUIESCENCE_STAGES};
+use crate::search::quiescence::PIECE_VALUES;
 use crate::search::searcher::Thread;
 
 pub const FUTILITY_MARGIN: i16 = 90;
@@ -15,8 +18,21 @@ pub const NULL_MOVE_PRUNING_DEPTH: i16 = 3;
 pub const HISTORY_PRUNING_DEPTH: i16 = 2;
 pub const HISTORY_PRUNING_THRESHOLD: isize = 0;
 pub const SEE_PRUNING_DEPTH: i16 = 6;
-pub const SEE_PRUNING_CAPTURE_MULT: f64 = -23.;
+pub const SEE_PRUNING_CAPTURE_MULT: i16 = -23;
 pub const SEE_PRUNING_QUIET_MULT: f64 = -23.;
+pub const SINGULAR_EXTENSION_DEPTH: i16 = 8;
+pub const SINGULAR_EXTENSION_TT_DEPTH: i16 = 3;
+pub const SINGULAR_EXTENSION_MARGIN: i16 = 2;
+pub const RAZORING_DEPTH: i16 = 2;
+pub const RAZORING_MARGIN: i16 = 300;
+pub const PROBCUT_DEPTH: i16 = 5;
+pub const PROBCUT_MARGIN: i16 = 150;
+pub const PROBCUT_REDUCTION: i16 = 4;
+//Late move reductions grow with sqrt(depth / divisor - 1) + sqrt(move index / divisor - 1), the divisors are
+//in hundredths. PV nodes are reduced by the factor in percent
+pub const LMR_DEPTH_DIVISOR: i16 = 200;
+pub const LMR_INDEX_DIVISOR: i16 = 200;
+pub const LMR_PV_FACTOR: i16 = 66;
 
 pub fn principal_variation_search(mut p: CombinedSearchParameters, thread: &mut Thread) -> i16 {
     //Step 0. Prepare variables
@@ -24,6 +40,8 @@ pub fn principal_variation_search(mut p: CombinedSearchParameters, thread: &mut
     clear_pv(p.current_depth, thread);
     let root = p.current_depth == 0;
     let is_pv_node = p.beta - p.alpha > 1;
+    //Set if this node searches all moves except the TT move to find out if it is singular
+    let excluded_move = thread.excluded_moves[p.current_depth];
     //Step 1. Check timeout and if stop flag is set, if we are main thread
     if thread.search_statistics.nodes_searched % 4096 == 0 {
         checkup(thread)
@@ -33,6 +51,7 @@ pub fn principal_variation_search(mut p: CombinedSearchParameters, thread: &mut
             thread.id,
             thread.search_statistics.nodes_searched,
             thread.search_statistics.seldepth,
+            &thread.cache_statistics,
         );
     }
     if thread.self_stop {
@@ -82,17 +101,22 @@ pub fn principal_variation_search(mut p: CombinedSearchParameters, thread: &mut
     //Step 8. TT Lookup
     let mut static_evaluation = None;
     let mut tt_move: Option<GameMove> = None;
-    if let SearchInstruction::StopSearching(res) = thread.itcs.cache().lookup(
-        &p,
-        &mut static_evaluation,
-        &mut tt_move,
-        thread.root_plies_played,
-    ) {
-        #[cfg(feature = "search-statistics")]
-        {
-            thread.search_statistics.add_cache_hit_aj_replace_ns();
+    //The entry belongs to the search with the TT move, so it is of no use when excluding it
+    if excluded_move.is_none() {
+        if let SearchInstruction::StopSearching(res) = thread.itcs.cache().lookup(
+            &p,
+            &mut static_evaluation,
+            &mut tt_move,
+            thread.root_plies_played,
+        ) {
+            thread.cache_statistics.add_probe(true, true);
+            #[cfg(feature = "search-statistics")]
+            {
+                thread.search_statistics.add_cache_hit_aj_replace_ns();
+            }
+            return res;
         }
-        return res;
+        thread.cache_statistics.add_probe(tt_move.is_some(), false);
     }
     #[cfg(feature = "search-statistics")]
     {
@@ -109,23 +133,39 @@ pub fn principal_variation_search(mut p: CombinedSearchParameters, thread: &mut
     make_eval(&p, thread, &mut static_evaluation, prunable);
 
     //Step 10. Prunings
-    if prunable {
-        //Step 10.1 Static Null Move Pruning
+    if prunable && excluded_move.is_none() {
+        //Step 10.1 Razoring
+        if let SearchInstruction::StopSearching(res) = razoring(&p, thread, static_evaluation) {
+            return res;
+        }
+        //Step 10.2 Static Null Move Pruning
         if let SearchInstruction::StopSearching(res) =
             static_null_move_pruning(&p, thread, static_evaluation)
         {
             return res;
         }
-        //Step 10.2 Null Move Forward Pruning
+        //Step 10.3 Null Move Forward Pruning
         if let SearchInstruction::StopSearching(res) =
             null_move_pruning(&p, thread, static_evaluation)
         {
             return res;
         }
+        //Step 10.4 ProbCut
+        if let SearchInstruction::StopSearching(res) =
+            probcut(&p, thread, static_evaluation, &tt_move)
+        {
+            return res;
+        }
     }
 
     //Step 11. Internal Iterative Deepening
-    if is_pv_node && !incheck && pv_table_move.is_none() && tt_move.is_none() && p.depth_left > 6 {
+    if is_pv_node
+        && !incheck
+        && excluded_move.is_none()
+        && pv_table_move.is_none()
+        && tt_move.is_none()
+        && p.depth_left > 6
+    {
         if let SearchInstruction::StopSearching(res) =
             internal_iterative_deepening(&p, thread, &mut tt_move)
         {
@@ -133,13 +173,27 @@ pub fn principal_variation_search(mut p: CombinedSearchParameters, thread: &mut
         }
     }
 
-    //Step 12. Futil Pruning and margin preparation
-    let futil_margin = prepare_futility_pruning(&p, static_evaluation);
+    //Step 12. Singular extensions. If all moves except the TT move fail low against a margin below the
+    // TT score, the TT move is extended. If they fail high against beta, prune the node (Multi-Cut)
+    let mut singular_move = None;
+    if let Some(tt_move) = tt_move {
+        if !root && excluded_move.is_none() {
+            if let SearchInstruction::StopSearching(res) =
+                singular_extension(&p, thread, &tt_move, &mut singular_move)
+            {
+                return res;
+            }
+        }
+    }
+
+    //Step 13. Futil Pruning and margin preparation
+    let futil_margin = prepare_futility_pruning(&p, thread, static_evaluation);
 
     //Step 14. Iterate through all moves
     let mut current_max_score = STANDARD_SCORE;
     let mut index: usize = 0;
     let mut quiets_tried: usize = 0;
+    let mut captures_tried: usize = 0;
     let mut move_orderer = MoveOrderer {
         stage: 0,
         stages: &NORMAL_STAGES,
@@ -152,6 +206,10 @@ pub fn principal_variation_search(mut p: CombinedSearchParameters, thread: &mut
             break;
         }
         let (mv, move_score) = mv.unwrap(); //Move score is only set for bad_capture
+        if excluded_move == Some(mv) {
+            continue;
+        }
+        let extension = if singular_move == Some(mv) { 1 } else { 0 };
 
         //Step 14.4. UCI Reporting at root
         //uci_report_move(&p, su, &mv, index);
@@ -164,15 +222,17 @@ pub fn principal_variation_search(mut p: CombinedSearchParameters, thread: &mut
         };
         let is_quiet_move = !isc && !isp;
         let gives_check = p.game_state.gives_check(&mv);
+        let history = if is_quiet_move {
+            quiet_history(&p, thread, &mv)
+        } else {
+            0
+        };
 
         if !root
             && is_quiet_move
             && current_max_score > MATED_IN_MAX
             && (futil_margin <= p.alpha
-                || p.depth_left <= HISTORY_PRUNING_DEPTH
-                    && thread.history_score[p.game_state.color_to_move][mv.from as usize]
-                        [mv.to as usize]
-                        < HISTORY_PRUNING_THRESHOLD)
+                || p.depth_left <= HISTORY_PRUNING_DEPTH && history < HISTORY_PRUNING_THRESHOLD)
             && p.game_state.has_non_pawns(p.game_state.color_to_move)
             && !gives_check
         {
@@ -186,11 +246,7 @@ pub fn principal_variation_search(mut p: CombinedSearchParameters, thread: &mut
                 continue;
             }
             //Step 14.6. History Pruning. Skip quiet moves in low depths if they are below threshold
-            if p.depth_left <= HISTORY_PRUNING_DEPTH
-                && thread.history_score[p.game_state.color_to_move][mv.from as usize]
-                    [mv.to as usize]
-                    < HISTORY_PRUNING_THRESHOLD
-            {
+            if p.depth_left <= HISTORY_PRUNING_DEPTH && history < HISTORY_PRUNING_THRESHOLD {
                 #[cfg(feature = "search-statistics")]
                 {
                     thread.search_statistics.add_history_pruned();
@@ -199,7 +255,7 @@ pub fn principal_variation_search(mut p: CombinedSearchParameters, thread: &mut
                 continue;
             }
             //Step 14.7 SEE Pruning. Skip quiet moves which have negative SEE Score on low depths
-            if p.depth_left <= SEE_PRUNING_DEPTH && false {
+            if p.depth_left <= thread.params.see_pruning_depth && false {
                 let see_value = 0.;
                 if see_value < SEE_PRUNING_QUIET_MULT * (p.depth_left as f64 + 3.) {
                     index += 1;
@@ -209,8 +265,11 @@ pub fn principal_variation_search(mut p: CombinedSearchParameters, thread: &mut
         } else if !root
             && isc
             && current_max_score > MATED_IN_MAX
-            && p.depth_left <= SEE_PRUNING_DEPTH
-            && move_score < SEE_PRUNING_CAPTURE_MULT * p.depth_left as f64 * p.depth_left as f64
+            && p.depth_left <= thread.params.see_pruning_depth
+            && move_score
+                < f64::from(thread.params.see_pruning_capture_mult)
+                    * p.depth_left as f64
+                    * p.depth_left as f64
             && p.game_state.has_non_pawns(p.game_state.color_to_move)
             && !gives_check
         {
@@ -229,6 +288,7 @@ pub fn principal_variation_search(mut p: CombinedSearchParameters, thread: &mut
             0
         };
 
+        thread.current_moves[p.current_depth] = Some(mv);
         let next_state = make_move(p.game_state, &mv);
         //Step 14.8. Search the moves
         let mut following_score: i16;
@@ -241,7 +301,7 @@ pub fn principal_variation_search(mut p: CombinedSearchParameters, thread: &mut
                 CombinedSearchParameters::from(
                     -p.beta,
                     -p.alpha,
-                    p.depth_left - 1 - reduction,
+                    p.depth_left - 1 + extension - reduction,
                     &next_state,
                     -p.color,
                     p.current_depth + 1,
@@ -253,7 +313,7 @@ pub fn principal_variation_search(mut p: CombinedSearchParameters, thread: &mut
                     CombinedSearchParameters::from(
                         -p.beta,
                         -p.alpha,
-                        p.depth_left - 1,
+                        p.depth_left - 1 + extension,
                         &next_state,
                         -p.color,
                         p.current_depth + 1,
@@ -268,7 +328,7 @@ pub fn principal_variation_search(mut p: CombinedSearchParameters, thread: &mut
                 CombinedSearchParameters::from(
                     -p.alpha - 1,
                     -p.alpha,
-                    p.depth_left - 1 - reduction,
+                    p.depth_left - 1 + extension - reduction,
                     &next_state,
                     -p.color,
                     p.current_depth + 1,
@@ -280,7 +340,7 @@ pub fn principal_variation_search(mut p: CombinedSearchParameters, thread: &mut
                     CombinedSearchParameters::from(
                         -p.beta,
                         -p.alpha,
-                        p.depth_left - 1,
+                        p.depth_left - 1 + extension,
                         &next_state,
                         -p.color,
                         p.current_depth + 1,
@@ -317,9 +377,14 @@ pub fn principal_variation_search(mut p: CombinedSearchParameters, thread: &mut
             }
             if !isc {
                 update_quiet_cutoff(&p, thread, &mv, quiets_tried);
+            } else {
+                update_capture_cutoff(&p, thread, &mv, captures_tried);
             }
             break;
-        } else if !isc {
+        } else if isc {
+            thread.captures_tried[p.current_depth][captures_tried] = Some(mv);
+            captures_tried += 1;
+        } else {
             //Step 14.12 Move does not cause beta cutoff, add to quiet moves tried and update butterfly heuristic
             thread.quiets_tried[p.current_depth][quiets_tried] = Some(mv);
             quiets_tried += 1;
@@ -333,6 +398,11 @@ pub fn principal_variation_search(mut p: CombinedSearchParameters, thread: &mut
 
     thread.history.pop();
 
+    //The excluded move may be the only legal one, so neither the end condition nor the TT entry apply
+    if excluded_move.is_some() {
+        return current_max_score;
+    }
+
     debug_assert!(!move_orderer.has_legal_move || current_max_score > STANDARD_SCORE);
     //Step 15. Evaluate leafs correctly
     let game_status =
@@ -350,7 +420,7 @@ pub fn principal_variation_search(mut p: CombinedSearchParameters, thread: &mut
 
     //Step 16. Make TT Entry
     if !thread.self_stop {
-        thread.itcs.cache().insert(
+        let replacement = thread.itcs.cache().insert(
             &p,
             &thread.pv_table[p.current_depth].pv[0].expect("Can't unwrap move for TT"),
             current_max_score,
@@ -358,6 +428,7 @@ pub fn principal_variation_search(mut p: CombinedSearchParameters, thread: &mut
             thread.root_plies_played,
             static_evaluation,
         );
+        thread.cache_statistics.add_insert(replacement);
     }
 
     //Step 17. Return
@@ -440,8 +511,11 @@ pub fn make_eval(
 ) {
     if static_evaluation.is_none()
         && (prunable
-            && (p.depth_left <= STATIC_NULL_MOVE_DEPTH || p.depth_left >= NULL_MOVE_PRUNING_DEPTH)
-            || p.depth_left <= FUTILITY_DEPTH)
+            && (p.depth_left <= thread.params.static_null_move_depth
+                || p.depth_left >= thread.params.null_move_pruning_depth
+                || p.depth_left <= thread.params.razoring_depth
+                || p.depth_left >= thread.params.probcut_depth)
+            || p.depth_left <= thread.params.futility_depth)
     {
         let eval_res = eval_game_state(
             p.game_state,
@@ -457,15 +531,75 @@ pub fn make_eval(
     }
 }
 
+//If the static evaluation is far below alpha at low depth, only captures could raise alpha. Verify this with a
+//quiescence search of this node
+#[inline(always)]
+pub fn razoring(
+    p: &CombinedSearchParameters,
+    thread: &mut Thread,
+    static_evaluation: Option<i16>,
+) -> SearchInstruction {
+    if p.depth_left <= thread.params.razoring_depth
+        && p.alpha.abs() < -MATED_IN_MAX
+        && static_evaluation.expect("Razoring") * p.color
+            + thread.params.razoring_margin * p.depth_left
+            <= p.alpha
+        && !has_quiet_check(p, thread)
+    {
+        //The quiescence search would see this node as a repetition
+        thread.history.pop();
+        let score = q_search(
+            CombinedSearchParameters::from(
+                p.alpha,
+                p.alpha + 1,
+                0,
+                p.game_state,
+                p.color,
+                p.current_depth,
+            ),
+            thread,
+        );
+        if thread.self_stop {
+            return SearchInstruction::StopSearching(STANDARD_SCORE);
+        }
+        if score <= p.alpha {
+            #[cfg(feature = "search-statistics")]
+            {
+                thread.search_statistics.add_razoring();
+            }
+            return SearchInstruction::StopSearching(score);
+        }
+        thread
+            .history
+            .push(p.game_state.hash, p.game_state.half_moves == 0);
+    }
+    SearchInstruction::ContinueSearching
+}
+
+//The quiescence search doesn't try quiet checks, which may be all that is left in a lost position
+pub fn has_quiet_check(p: &CombinedSearchParameters, thread: &mut Thread) -> bool {
+    let movelist = &mut thread.movelist.move_lists[p.current_depth];
+    movegen::generate_moves(
+        p.game_state,
+        false,
+        movelist,
+        &thread.attack_container.attack_containers[p.current_depth],
+    );
+    movelist
+        .move_list
+        .iter()
+        .any(|mv| !mv.0.is_capture() && p.game_state.gives_check(&mv.0))
+}
+
 #[inline(always)]
 pub fn static_null_move_pruning(
     p: &CombinedSearchParameters,
     thread: &mut Thread,
     static_evaluation: Option<i16>,
 ) -> SearchInstruction {
-    if p.depth_left <= STATIC_NULL_MOVE_DEPTH
+    if p.depth_left <= thread.params.static_null_move_depth
         && static_evaluation.expect("Static null move") * p.color
-            - STATIC_NULL_MOVE_MARGIN * p.depth_left
+            - thread.params.static_null_move_margin * p.depth_left
             >= p.beta
     {
         thread.history.pop();
@@ -475,7 +609,7 @@ pub fn static_null_move_pruning(
         }
         SearchInstruction::StopSearching(
             static_evaluation.expect("Static null move 2") * p.color
-                - STATIC_NULL_MOVE_DEPTH * p.depth_left,
+                - thread.params.static_null_move_depth * p.depth_left,
         )
     } else {
         SearchInstruction::ContinueSearching
@@ -488,10 +622,11 @@ pub fn null_move_pruning(
     thread: &mut Thread,
     static_evaluation: Option<i16>,
 ) -> SearchInstruction {
-    if p.depth_left >= NULL_MOVE_PRUNING_DEPTH
+    if p.depth_left >= thread.params.null_move_pruning_depth
         && p.game_state.has_non_pawns(p.game_state.color_to_move)
         && static_evaluation.expect("null move static") * p.color >= p.beta
     {
+        thread.current_moves[p.current_depth] = None;
         let nextgs = make_nullmove(p.game_state);
         let rat = -principal_variation_search(
             CombinedSearchParameters::from(
@@ -516,6 +651,69 @@ pub fn null_move_pruning(
     SearchInstruction::ContinueSearching
 }
 
+//If a good capture beats beta by a margin in a reduced search, the full search will most likely fail high too.
+//Captures are first verified by a quiescence search
+#[inline(always)]
+pub fn probcut(
+    p: &CombinedSearchParameters,
+    thread: &mut Thread,
+    static_evaluation: Option<i16>,
+    tt_move: &Option<GameMove>,
+) -> SearchInstruction {
+    if p.depth_left < thread.params.probcut_depth || p.beta.abs() >= -MATED_IN_MAX {
+        return SearchInstruction::ContinueSearching;
+    }
+    let probcut_beta = p.beta + thread.params.probcut_margin;
+    let static_evaluation = static_evaluation.expect("ProbCut") * p.color;
+    let tt_capture = tt_move.filter(|mv| mv.is_capture());
+    let mut move_orderer = MoveOrderer {
+        stage: 0,
+        stages: &QUIESCENCE_STAGES,
+        gen_only_captures: true,
+        has_legal_move: false,
+    };
+    while let Some((mv, _)) = move_orderer.next(thread, p, &None, &tt_capture) {
+        if static_evaluation + PIECE_VALUES[mv.get_captured_piece().to_index()] < probcut_beta {
+            continue;
+        }
+        thread.current_moves[p.current_depth] = Some(mv);
+        let next_state = make_move(p.game_state, &mv);
+        let search = |depth_left: i16, thread: &mut Thread| {
+            -principal_variation_search(
+                CombinedSearchParameters::from(
+                    -probcut_beta,
+                    -probcut_beta + 1,
+                    depth_left,
+                    &next_state,
+                    -p.color,
+                    p.current_depth + 1,
+                ),
+                thread,
+            )
+        };
+        let mut score = search(0, thread);
+        if score >= probcut_beta {
+            score = search(
+                (p.depth_left - 1 - thread.params.probcut_reduction).max(0),
+                thread,
+            );
+        }
+        if thread.self_stop {
+            thread.history.pop();
+            return SearchInstruction::StopSearching(STANDARD_SCORE);
+        }
+        if score >= probcut_beta {
+            #[cfg(feature = "search-statistics")]
+            {
+                thread.search_statistics.add_probcut();
+            }
+            thread.history.pop();
+            return SearchInstruction::StopSearching(score);
+        }
+    }
+    SearchInstruction::ContinueSearching
+}
+
 #[inline(always)]
 pub fn internal_iterative_deepening(
     p: &CombinedSearchParameters,
@@ -548,14 +746,76 @@ pub fn internal_iterative_deepening(
     SearchInstruction::ContinueSearching
 }
 
+#[inline(always)]
+pub fn singular_extension(
+    p: &CombinedSearchParameters,
+    thread: &mut Thread,
+    tt_move: &GameMove,
+    singular_move: &mut Option<GameMove>,
+) -> SearchInstruction {
+    if p.depth_left < SINGULAR_EXTENSION_DEPTH {
+        return SearchInstruction::ContinueSearching;
+    }
+    //The TT move needs a lower bound which was searched almost as deep as this node
+    let ce = thread.itcs.cache().probe(p.game_state.hash);
+    let tt_score = match ce {
+        Some(ce)
+            if ce.beta
+                && ce.depth as i16 >= p.depth_left - SINGULAR_EXTENSION_TT_DEPTH
+                && ce.score.abs() < -MATED_IN_MAX =>
+        {
+            ce.score
+        }
+        _ => return SearchInstruction::ContinueSearching,
+    };
+    let singular_beta = tt_score - thread.params.singular_extension_margin * p.depth_left;
+    thread.history.pop();
+    thread.excluded_moves[p.current_depth] = Some(*tt_move);
+    let score = principal_variation_search(
+        CombinedSearchParameters::from(
+            singular_beta - 1,
+            singular_beta,
+            (p.depth_left - 1) / 2,
+            &p.game_state,
+            p.color,
+            p.current_depth,
+        ),
+        thread,
+    );
+    thread.excluded_moves[p.current_depth] = None;
+    if thread.self_stop {
+        return SearchInstruction::StopSearching(STANDARD_SCORE);
+    }
+    if score < singular_beta {
+        #[cfg(feature = "search-statistics")]
+        {
+            thread.search_statistics.add_singular_extension();
+        }
+        *singular_move = Some(*tt_move);
+    } else if singular_beta >= p.beta {
+        //Multi-Cut: Atleast two moves beat beta
+        #[cfg(feature = "search-statistics")]
+        {
+            thread.search_statistics.add_multi_cut();
+        }
+        return SearchInstruction::StopSearching(singular_beta);
+    }
+    thread
+        .history
+        .push(p.game_state.hash, p.game_state.half_moves == 0);
+    SearchInstruction::ContinueSearching
+}
+
 #[inline(always)]
 pub fn prepare_futility_pruning(
     p: &CombinedSearchParameters,
+    thread: &Thread,
     static_evaluation: Option<i16>,
 ) -> i16 {
-    let futil_pruning = p.depth_left <= FUTILITY_DEPTH && p.current_depth > 0;
+    let futil_pruning = p.depth_left <= thread.params.futility_depth && p.current_depth > 0;
     if futil_pruning {
-        static_evaluation.expect("Futil pruning") * p.color + p.depth_left * FUTILITY_MARGIN
+        static_evaluation.expect("Futil pruning") * p.color
+            + p.depth_left * thread.params.futility_margin
     } else {
         MATE_SCORE
     }
@@ -570,13 +830,17 @@ pub fn compute_lmr_reduction(
     iscp: bool,
     gives_check: bool,
 ) -> i16 {
-    let mut reduction = ((f64::from(p.depth_left) / 2. - 1.).max(0.).sqrt()
-        + (index as f64 / 2.0 - 1.).max(0.).sqrt()) as i16;
+    let depth_divisor = f64::from(thread.params.lmr_depth_divisor) / 100.;
+    let index_divisor = f64::from(thread.params.lmr_index_divisor) / 100.;
+    let mut reduction = ((f64::from(p.depth_left) / depth_divisor - 1.)
+        .max(0.)
+        .sqrt()
+        + (index as f64 / index_divisor - 1.).max(0.).sqrt()) as i16;
     if iscp {
         reduction /= 2;
     }
     if p.beta - p.alpha > 1 {
-        reduction = (f64::from(reduction) * 0.66) as i16;
+        reduction = (f64::from(reduction) * f64::from(thread.params.lmr_pv_factor) / 100.) as i16;
     }
     if gives_check {
         reduction -= 1;
@@ -608,6 +872,40 @@ pub fn uci_report_pv(
     }
 }
 
+//The opponent's last move and our own last move
+#[inline(always)]
+pub fn previous_moves(
+    thread: &Thread,
+    current_depth: usize,
+) -> (Option<GameMove>, Option<GameMove>) {
+    (
+        if current_depth >= 1 {
+            thread.current_moves[current_depth - 1]
+        } else {
+            None
+        },
+        if current_depth >= 2 {
+            thread.current_moves[current_depth - 2]
+        } else {
+            None
+        },
+    )
+}
+
+#[inline(always)]
+pub fn quiet_history(p: &CombinedSearchParameters, thread: &Thread, mv: &GameMove) -> isize {
+    let (last_move, own_last_move) = previous_moves(thread, p.current_depth);
+    let side = p.game_state.color_to_move;
+    thread.history_score[side][mv.from as usize][mv.to as usize]
+        + thread.counter_move_history.get(side, &last_move, mv)
+        + thread.follow_up_history.get(side, &own_last_move, mv)
+}
+
+#[inline(always)]
+pub fn history_bonus(depth_left: isize) -> isize {
+    (depth_left * depth_left).min(HISTORY_MAX)
+}
+
 #[inline(always)]
 pub fn update_quiet_cutoff(
     p: &CombinedSearchParameters,
@@ -615,10 +913,23 @@ pub fn update_quiet_cutoff(
     mv: &GameMove,
     quiets_tried: usize,
 ) {
-    thread.hh_score[p.game_state.color_to_move][mv.from as usize][mv.to as usize] +=
+    let side = p.game_state.color_to_move;
+    thread.hh_score[side][mv.from as usize][mv.to as usize] +=
         p.depth_left as usize * p.depth_left as usize;
-    thread.history_score[p.game_state.color_to_move][mv.from as usize][mv.to as usize] +=
+    thread.history_score[side][mv.from as usize][mv.to as usize] +=
         p.depth_left as isize * p.depth_left as isize;
+    let (last_move, own_last_move) = previous_moves(thread, p.current_depth);
+    let bonus = history_bonus(p.depth_left as isize);
+    thread
+        .counter_move_history
+        .update(side, &last_move, mv, bonus);
+    thread
+        .follow_up_history
+        .update(side, &own_last_move, mv, bonus);
+    if let Some(last_move) = last_move {
+        thread.counter_moves[side][last_move.piece_type.to_index()][last_move.to as usize] =
+            Some(*mv);
+    }
     decrement_history_quiets(
         thread,
         p.current_depth,
@@ -649,9 +960,176 @@ pub fn decrement_history_quiets(
     depth_left: isize,
     side_to_move: usize,
 ) {
+    let (last_move, own_last_move) = previous_moves(thread, current_depth);
     for i in 0..quiets_tried {
-        let mv = thread.quiets_tried[current_depth][i].as_ref().unwrap();
+        let mv = thread.quiets_tried[current_depth][i].unwrap();
         thread.history_score[side_to_move][mv.from as usize][mv.to as usize] -=
             depth_left * depth_left;
+        thread.counter_move_history.update(
+            side_to_move,
+            &last_move,
+            &mv,
+            -history_bonus(depth_left),
+        );
+        thread.follow_up_history.update(
+            side_to_move,
+            &own_last_move,
+            &mv,
+            -history_bonus(depth_left),
+        );
+    }
+}
+
+#[inline(always)]
+pub fn update_capture_cutoff(
+    p: &CombinedSearchParameters,
+    thread: &mut Thread,
+    mv: &GameMove,
+    captures_tried: usize,
+) {
+    let side = p.game_state.color_to_move;
+    let bonus = history_bonus(p.depth_left as isize);
+    update_history_entry(
+        &mut thread.capture_history[side][mv.piece_type.to_index()][mv.to as usize]
+            [mv.get_captured_piece().to_index()],
+        bonus,
+    );
+    for i in 0..captures_tried {
+        let mv = thread.captures_tried[p.current_depth][i].unwrap();
+        update_history_entry(
+            &mut thread.capture_history[side][mv.piece_type.to_index()][mv.to as usize]
+                [mv.get_captured_piece().to_index()],
+            -bonus,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluation::eval_game_state_from_null;
+    use crate::search::params::SearchParams;
+    use crate::testing::epd::load_epd;
+
+    fn node_thread(state: &GameState, current_depth: usize) -> Thread {
+        let mut thread = Thread::test_thread(1);
+        thread.attack_container.attack_containers[current_depth].write_state(state);
+        thread.history.push(state.hash, false);
+        thread
+    }
+
+    fn is_stop(instruction: &SearchInstruction) -> bool {
+        match instruction {
+            SearchInstruction::StopSearching(_) => true,
+            _ => false,
+        }
+    }
+
+    //Iterative deepening with full windows
+    fn search(state: &GameState, depth: i16, params: SearchParams) -> ScoredPrincipalVariation {
+        let mut thread = Thread::test_thread(1);
+        thread.params = params;
+        for depth in 1..=depth {
+            principal_variation_search(
+                CombinedSearchParameters::from(
+                    -16000,
+                    16000,
+                    depth,
+                    state,
+                    if state.color_to_move == WHITE { 1 } else { -1 },
+                    0,
+                ),
+                &mut thread,
+            );
+            assert_eq!(thread.history.pointer, 0);
+        }
+        thread.current_pv
+    }
+
+    //If a pruning stops the search of a node, it removes the node from the history like the node would do itself
+    #[test]
+    fn razoring_test() {
+        for (fen, alpha, razored) in [
+            ("4k3/8/8/3q4/8/4P3/8/4K2N w - - 0 1", -1000, true),
+            //The quiescence search finds exd5
+            ("4k3/8/8/3q4/4P3/8/8/4K2N w - - 0 1", -1000, false),
+            //The quiescence search doesn't try Ra8+
+            ("4k3/8/8/3q4/8/8/8/R5K1 w - - 0 1", 0, false),
+            ("4k3/8/8/3Q4/8/4P3/8/4K2n w - - 0 1", 0, false),
+        ]
+        .iter()
+        {
+            let state = GameState::from_fen(fen);
+            let mut thread = node_thread(&state, 1);
+            let p = CombinedSearchParameters::from(*alpha, *alpha + 1, 1, &state, 1, 1);
+            let static_evaluation = Some(eval_game_state_from_null(&state).final_eval);
+            let instruction = razoring(&p, &mut thread, static_evaluation);
+            assert_eq!(is_stop(&instruction), *razored);
+            assert_eq!(thread.history.pointer, if *razored { 0 } else { 1 });
+        }
+    }
+
+    #[test]
+    fn probcut_test() {
+        for (fen, cut) in [
+            ("4k3/8/8/3q4/4P3/8/7Q/4K3 w - - 0 1", true),
+            ("4k3/8/8/3q4/8/4P3/7Q/4K3 w - - 0 1", false),
+        ]
+        .iter()
+        {
+            let state = GameState::from_fen(fen);
+            let mut thread = node_thread(&state, 1);
+            let p = CombinedSearchParameters::from(-1, 0, 6, &state, 1, 1);
+            let static_evaluation = Some(eval_game_state_from_null(&state).final_eval);
+            let instruction = probcut(&p, &mut thread, static_evaluation, &None);
+            assert_eq!(is_stop(&instruction), *cut);
+            assert_eq!(thread.history.pointer, if *cut { 0 } else { 1 });
+        }
+    }
+
+    //Razoring and ProbCut don't change the mates found in the mate suite
+    #[test]
+    fn razoring_probcut_search_test() {
+        let mut without = SearchParams::default();
+        without.razoring_depth = 0;
+        without.probcut_depth = MAX_SEARCH_DEPTH as i16;
+        let mut mates = 0;
+        for position in load_epd("testsuites/mate.epd").iter() {
+            let direct_mate = position.direct_mate.unwrap();
+            if direct_mate > 3 {
+                continue;
+            }
+            let depth = 2 * direct_mate as i16;
+            let with = search(&position.state, depth, SearchParams::default());
+            let without = search(&position.state, depth, without);
+            if with.score.abs() > -MATED_IN_MAX || without.score.abs() > -MATED_IN_MAX {
+                mates += 1;
+                assert_eq!(with.score, without.score, "{}", position.fen);
+                assert_eq!(with.score, MATE_SCORE - (2 * direct_mate as i16 - 1));
+                assert!(position.is_solution(&with.pv.pv[0].unwrap()));
+                assert!(position.is_solution(&without.pv.pv[0].unwrap()));
+            }
+        }
+        assert!(mates >= 10);
+        //They keep the history balanced
+        let bench = std::fs::read_to_string("benchmarking_positions.txt").unwrap();
+        for fen in bench.lines().take(5) {
+            search(&GameState::from_fen(fen), 5, SearchParams::default());
+        }
+    }
+
+    //Razoring and ProbCut need the static evaluation even if no other pruning does
+    #[test]
+    fn pruning_params_eval_test() {
+        let mut params = SearchParams::default();
+        params.futility_depth = 0;
+        params.static_null_move_depth = 0;
+        params.null_move_pruning_depth = 12;
+        params.razoring_depth = 3;
+        params.probcut_depth = 2;
+        let bench = std::fs::read_to_string("benchmarking_positions.txt").unwrap();
+        for fen in bench.lines().take(5) {
+            search(&GameState::from_fen(fen), 5, params);
+        }
     }
 }
@@ -1,22 +1,34 @@
 use crate::board_representation::game_state::{
     GameMove, GameMoveType, GameState, PieceType, BISHOP, KNIGHT, PAWN, QUEEN, ROOK,
 };
+use crate::board_representation::zobrist_hashing::{ZOBRIST_KEYS, ZOBRIST_SEED};
+use crate::search::cache_memory::BucketMemory;
+use crate::search::statistics::TableStatistics;
 use crate::search::{CombinedSearchParameters, SearchInstruction};
-use std::cell::UnsafeCell;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Error, ErrorKind, Read, Result, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
 
 pub const INVALID_STATIC_EVALUATION: i16 = -32768;
 pub const DEFAULT_HASH_SIZE: usize = 256; //IN MB
 pub const MIN_HASH_SIZE: usize = 0; //IN MB
 pub const MAX_HASH_SIZE: usize = 131072; //IN MB
 
+//Entries only store the plies played at the root modulo this
+pub const AGE_CYCLE: usize = 32;
+//Header of a saved table: magic, version of the entry format, Zobrist seed and key and the amount of buckets
+pub const CACHE_FILE_MAGIC: &[u8; 4] = b"FCTT";
+pub const CACHE_FILE_VERSION: u32 = 1;
+pub const CACHE_FILE_HEADER_SIZE: u64 = 56;
+
+//Buckets are shared by all threads without locks. An entry which was torn by concurrent writes fails the
+//verification of its key and is treated as a miss
 pub struct Cache {
     pub entries: usize,
     pub buckets: usize,
-    pub cache: UnsafeCell<Vec<CacheBucket>>,
+    pub cache: BucketMemory,
 }
 
-unsafe impl std::marker::Sync for Cache {}
-
 impl Default for Cache {
     fn default() -> Self {
         Cache::with_size(DEFAULT_HASH_SIZE)
@@ -25,13 +37,14 @@ impl Default for Cache {
 
 impl Cache {
     pub fn with_size(mb_size: usize) -> Self {
-        let buckets = 1024 * 1024 * mb_size / 64;
-        let entries = buckets * 3;
-        let cache = UnsafeCell::new(vec![CacheBucket::default(); buckets]);
+        Cache::with_buckets(1024 * 1024 * mb_size / 64)
+    }
+
+    pub fn with_buckets(buckets: usize) -> Self {
         Cache {
-            entries,
+            entries: buckets * 3,
             buckets,
-            cache,
+            cache: BucketMemory::new(buckets),
         }
     }
     pub fn fill_status(&self) -> usize {
@@ -43,18 +56,16 @@ impl Cache {
         let mut full = 0;
 
         let mut index = 0;
-        while index < unsafe { (&mut *self.cache.get()).len() } && counted_entries < 500 {
-            let bucket = unsafe { (&mut *self.cache.get()).get(index).unwrap() };
+        while index < self.cache.len() && counted_entries < 500 {
+            full += self.cache[index].fill_status();
             index += 1;
-            full += bucket.fill_status();
             counted_entries += 3;
         }
         //Count upper 500 entries
-        let mut index = unsafe { (&mut *self.cache.get()).len() - 1 };
+        let mut index = self.cache.len() - 1;
         while counted_entries < 1000 {
-            let bucket = unsafe { (&mut *self.cache.get()).get(index).unwrap() };
             debug_assert!(index > 0);
-            full += bucket.fill_status();
+            full += self.cache[index].fill_status();
             counted_entries += 3;
             if index == 0 {
                 break;
@@ -65,25 +76,98 @@ impl Cache {
     }
 
     pub fn clear(&self) {
-        unsafe {
-            *self.cache.get() = vec![CacheBucket::default(); self.buckets];
+        self.cache.clear();
+    }
+
+    //Scans the whole table, ages are counted relative to the given root
+    pub fn table_statistics(&self, root_plies_played: usize) -> TableStatistics {
+        let mut statistics = TableStatistics::default();
+        let age = CacheEntry::age(root_plies_played) as usize;
+        for bucket in self.cache.iter() {
+            for entry in bucket.0.iter() {
+                let entry = entry.load();
+                if !entry.is_invalid() {
+                    let relative_age = (age + AGE_CYCLE - entry.plies_played as usize) % AGE_CYCLE;
+                    statistics.add_entry(&entry, relative_age);
+                }
+            }
         }
+        statistics.entries = self.entries as u64;
+        statistics
     }
 
-    pub fn age_entry(&self, hash: u64, new_age: u16) {
-        unsafe {
-            (&mut *self.cache.get())
-                .get_unchecked_mut(hash as usize % self.buckets)
-                .age_entry(hash, new_age);
+    pub fn mb_size(&self) -> usize {
+        self.buckets * 64 / (1024 * 1024)
+    }
+
+    pub fn save(&self, path: &str) -> Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(CACHE_FILE_MAGIC)?;
+        writer.write_all(&CACHE_FILE_VERSION.to_le_bytes())?;
+        writer.write_all(&ZOBRIST_SEED)?;
+        writer.write_all(&ZOBRIST_KEYS.side_to_move.to_le_bytes())?;
+        writer.write_all(&(self.buckets as u64).to_le_bytes())?;
+        for bucket in self.cache.iter() {
+            for entry in bucket.0.iter() {
+                let (key, data) = entry.words();
+                writer.write_all(&key.to_le_bytes())?;
+                writer.write_all(&data.to_le_bytes())?;
+            }
+        }
+        writer.flush()
+    }
+
+    //The loaded table has the size of the saved one
+    pub fn load(path: &str) -> Result<Cache> {
+        let file = File::open(path)?;
+        let file_size = file.metadata()?.len();
+        let mut reader = BufReader::new(file);
+        let invalid = |reason: &str| Err(Error::new(ErrorKind::InvalidData, reason.to_owned()));
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != CACHE_FILE_MAGIC {
+            return invalid("Not a hash file");
+        }
+        if read_u64(&mut reader, 4)? != u64::from(CACHE_FILE_VERSION) {
+            return invalid("Hash file has a different entry format version");
         }
+        let mut seed = [0u8; 32];
+        reader.read_exact(&mut seed)?;
+        if seed != ZOBRIST_SEED || read_u64(&mut reader, 8)? != ZOBRIST_KEYS.side_to_move {
+            return invalid("Hash file was saved with different Zobrist keys");
+        }
+        //Check the amount of buckets before allocating them
+        let buckets = read_u64(&mut reader, 8)?;
+        if buckets == 0 || buckets > (MAX_HASH_SIZE * 1024 * 1024 / 64) as u64 {
+            return invalid("Hash file has an invalid amount of buckets");
+        }
+        if file_size != CACHE_FILE_HEADER_SIZE + buckets * 3 * 16 {
+            return invalid("Hash file size doesn't match its amount of buckets");
+        }
+        let cache = Cache::with_buckets(buckets as usize);
+        for bucket in cache.cache.iter() {
+            for entry in bucket.0.iter() {
+                let key = read_u64(&mut reader, 8)?;
+                entry.set_words(key, read_u64(&mut reader, 8)?);
+            }
+        }
+        Ok(cache)
+    }
+
+    #[inline(always)]
+    fn bucket(&self, hash: u64) -> &CacheBucket {
+        unsafe { self.cache.get_unchecked(hash as usize % self.buckets) }
     }
 
-    pub fn get(&self, hash: u64) -> CacheBucket {
-        unsafe {
-            (&mut *self.cache.get())
-                .get_unchecked(hash as usize % self.buckets)
-                .clone()
+    pub fn age_entry(&self, hash: u64, new_age: u16) {
+        self.bucket(hash).age_entry(hash, new_age);
+    }
+
+    pub fn probe(&self, hash: u64) -> Option<CacheEntry> {
+        if self.entries == 0 {
+            return None;
         }
+        self.bucket(hash).probe(hash)
     }
 
     pub fn insert(
@@ -94,23 +178,18 @@ impl Cache {
         original_alpha: i16,
         root_plies_played: usize,
         static_evaluation: Option<i16>,
-    ) {
+    ) -> Replacement {
         if self.entries == 0 {
-            return;
-        }
-        let index = p.game_state.hash as usize % self.buckets;
-        unsafe {
-            (&mut *self.cache.get())
-                .get_unchecked_mut(index)
-                .replace_entry(
-                    p,
-                    mv,
-                    score,
-                    original_alpha,
-                    root_plies_played,
-                    static_evaluation,
-                );
-        };
+            return Replacement::Skipped;
+        }
+        self.bucket(p.game_state.hash).replace_entry(
+            p,
+            mv,
+            score,
+            original_alpha,
+            root_plies_played,
+            static_evaluation,
+        )
     }
 
     pub fn lookup(
@@ -120,10 +199,7 @@ impl Cache {
         tt_move: &mut Option<GameMove>,
         root_plies: usize,
     ) -> SearchInstruction {
-        if self.entries == 0 {
-            return SearchInstruction::ContinueSearching;
-        }
-        let ce = self.get(p.game_state.hash).probe(p.game_state.hash);
+        let ce = self.probe(p.game_state.hash);
         if let Some(ce) = ce {
             if ce.depth >= p.depth_left as i8
                 && (p.beta - p.alpha <= 1 || p.depth_left <= 0)
@@ -138,28 +214,108 @@ impl Cache {
                 *static_evaluation = Some(ce.static_evaluation);
             }
             *tt_move = Some(mv);
-            if ce.plies_played != root_plies as u16 {
-                self.age_entry(p.game_state.hash, root_plies as u16);
+            if ce.plies_played != CacheEntry::age(root_plies) {
+                self.age_entry(p.game_state.hash, CacheEntry::age(root_plies));
             }
         }
         SearchInstruction::ContinueSearching
     }
 }
 
+fn read_u64(reader: &mut impl Read, bytes: usize) -> Result<u64> {
+    let mut buffer = [0u8; 8];
+    reader.read_exact(&mut buffer[..bytes])?;
+    Ok(u64::from_le_bytes(buffer))
+}
+
+//What happened to the bucket on an insert
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Replacement {
+    Empty,        //Written into an empty slot
+    SamePosition, //Overwrote the entry of the same position
+    Aged,         //Overwrote an entry of an earlier search
+    LowestScore,  //Overwrote the entry with the lowest depth in the bucket
+    Skipped,      //Nothing was written
+}
+
+//The key is the hash XORed with the data, so an entry is only valid if both words were written together
+#[derive(Default)]
+pub struct AtomicCacheEntry {
+    key: AtomicU64,
+    data: AtomicU64,
+}
+
+impl AtomicCacheEntry {
+    #[inline(always)]
+    pub fn load(&self) -> CacheEntry {
+        let data = self.data.load(Ordering::Relaxed);
+        let key = self.key.load(Ordering::Relaxed);
+        CacheEntry::unpack(key ^ data, data)
+    }
+
+    #[inline(always)]
+    pub fn store(&self, entry: &CacheEntry) {
+        let data = entry.pack();
+        self.set_words(entry.hash ^ data, data);
+    }
+
+    pub fn words(&self) -> (u64, u64) {
+        (
+            self.key.load(Ordering::Relaxed),
+            self.data.load(Ordering::Relaxed),
+        )
+    }
+
+    #[inline(always)]
+    pub fn set_words(&self, key: u64, data: u64) {
+        self.key.store(key, Ordering::Relaxed);
+        self.data.store(data, Ordering::Relaxed);
+    }
+}
+
 #[repr(align(64))]
-#[derive(Copy, Clone)]
-pub struct CacheBucket([CacheEntry; 3]);
+#[derive(Default)]
+pub struct CacheBucket(pub [AtomicCacheEntry; 3]);
 
 impl CacheBucket {
+    //Works on a copy of the entries, of which the changed ones are written back
     pub fn replace_entry(
-        &mut self,
+        &self,
         p: &CombinedSearchParameters,
         mv: &GameMove,
         score: i16,
         original_alpha: i16,
         root_plies_played: usize,
         static_evaluation: Option<i16>,
-    ) -> bool {
+    ) -> Replacement {
+        let loaded = [self.0[0].load(), self.0[1].load(), self.0[2].load()];
+        let mut entries = loaded;
+        let res = CacheBucket::replace_in(
+            &mut entries,
+            p,
+            mv,
+            score,
+            original_alpha,
+            CacheEntry::age(root_plies_played),
+            static_evaluation,
+        );
+        for i in 0..3 {
+            if entries[i] != loaded[i] {
+                self.0[i].store(&entries[i]);
+            }
+        }
+        res
+    }
+
+    fn replace_in(
+        entries: &mut [CacheEntry; 3],
+        p: &CombinedSearchParameters,
+        mv: &GameMove,
+        score: i16,
+        original_alpha: i16,
+        age: u16,
+        static_evaluation: Option<i16>,
+    ) -> Replacement {
         let lower_bound = score >= p.beta;
         let upper_bound = score <= original_alpha;
         let pv_node = p.beta - p.alpha > 1;
@@ -167,7 +323,7 @@ impl CacheBucket {
             cache_entry.write(
                 p.game_state.hash,
                 p.depth_left,
-                root_plies_played as u16,
+                age,
                 score,
                 static_evaluation,
                 pv_node,
@@ -176,119 +332,128 @@ impl CacheBucket {
                 &mv,
             )
         };
-        let renew_entry = |cache_entry: &mut CacheEntry| -> bool {
-            if cache_entry.plies_played < root_plies_played as u16
+        let renew_entry = |cache_entry: &mut CacheEntry| -> Replacement {
+            if cache_entry.plies_played != age
                 || cache_entry.get_score() <= p.depth_left as f64 * if pv_node { 1. } else { 0.7 }
             {
+                let replacement = if cache_entry.is_invalid() {
+                    Replacement::Empty
+                } else if cache_entry.validate_hash(p.game_state.hash) {
+                    Replacement::SamePosition
+                } else {
+                    Replacement::Aged
+                };
                 write_entry(cache_entry);
-                true
+                replacement
             } else {
-                false
+                Replacement::Skipped
             }
         };
 
-        if self.0[0].is_invalid()
-            || self.0[0].plies_played < root_plies_played as u16
-            || self.0[0].validate_hash(p.game_state.hash)
+        if entries[0].is_invalid()
+            || entries[0].plies_played != age
+            || entries[0].validate_hash(p.game_state.hash)
         {
-            let res = self.0[0].is_invalid();
-            renew_entry(&mut self.0[0]);
+            let res = renew_entry(&mut entries[0]);
             return res;
-        } else if self.0[1].is_invalid()
-            || self.0[1].plies_played < root_plies_played as u16
-            || self.0[1].validate_hash(p.game_state.hash)
+        } else if entries[1].is_invalid()
+            || entries[1].plies_played != age
+            || entries[1].validate_hash(p.game_state.hash)
         {
-            let res = self.0[1].is_invalid();
-            renew_entry(&mut self.0[1]);
-            self.0.swap(0, 1);
+            let res = renew_entry(&mut entries[1]);
+            entries.swap(0, 1);
             return res;
-        } else if self.0[2].is_invalid()
-            || self.0[2].plies_played < root_plies_played as u16
-            || self.0[2].validate_hash(p.game_state.hash)
+        } else if entries[2].is_invalid()
+            || entries[2].plies_played != age
+            || entries[2].validate_hash(p.game_state.hash)
         {
-            let res = self.0[2].is_invalid();
-            renew_entry(&mut self.0[2]);
-            self.0.swap(0, 2);
-            self.0.swap(1, 2);
+            let res = renew_entry(&mut entries[2]);
+            entries.swap(0, 2);
+            entries.swap(1, 2);
             return res;
         }
-        let mut min_score = self.0[2].get_score();
+        let mut min_score = entries[2].get_score();
         let mut min_entry = 2;
 
-        if self.0[1].get_score() < min_score {
-            min_score = self.0[1].get_score();
+        if entries[1].get_score() < min_score {
+            min_score = entries[1].get_score();
             min_entry = 1;
         }
-        if self.0[0].get_score() < min_score {
-            min_score = self.0[0].get_score();
+        if entries[0].get_score() < min_score {
+            min_score = entries[0].get_score();
             min_entry = 0;
         }
         let new_score = p.depth_left as f64 * if pv_node { 1. } else { 0.7 };
         if new_score >= min_score {
-            write_entry(&mut self.0[min_entry]);
+            write_entry(&mut entries[min_entry]);
+            Replacement::LowestScore
+        } else {
+            Replacement::Skipped
         }
-        false
     }
 
     pub fn probe(&self, hash: u64) -> Option<CacheEntry> {
         if hash == 0u64 {
             return None;
         }
-        if self.0[0].validate_hash(hash) {
-            return Some(self.0[0]);
-        } else if self.0[1].validate_hash(hash) {
-            return Some(self.0[1]);
-        } else if self.0[2].validate_hash(hash) {
-            return Some(self.0[2]);
+        for entry in self.0.iter() {
+            let entry = entry.load();
+            if !entry.is_invalid() && entry.validate_hash(hash) {
+                return Some(entry);
+            }
         }
         None
     }
 
-    pub fn age_entry(&mut self, hash: u64, new_age: u16) {
-        if self.0[0].validate_hash(hash) {
-            self.0[0].plies_played = new_age;
-        } else if self.0[1].validate_hash(hash) {
-            self.0[1].plies_played = new_age;
-        } else if self.0[2].validate_hash(hash) {
-            self.0[2].plies_played = new_age;
+    pub fn age_entry(&self, hash: u64, new_age: u16) {
+        for entry in self.0.iter() {
+            let mut loaded = entry.load();
+            if !loaded.is_invalid() && loaded.validate_hash(hash) {
+                loaded.plies_played = new_age;
+                entry.store(&loaded);
+                return;
+            }
         }
     }
 
     pub fn fill_status(&self) -> usize {
-        (if self.0[0].is_invalid() { 0 } else { 1 })
-            + (if self.0[1].is_invalid() { 0 } else { 1 })
-            + (if self.0[2].is_invalid() { 0 } else { 1 })
+        self.0
+            .iter()
+            .filter(|entry| !entry.load().is_invalid())
+            .count()
     }
-}
-impl Default for CacheBucket {
-    fn default() -> Self {
-        CacheBucket([CacheEntry::invalid(); 3])
+
+    pub fn clear(&self) {
+        for entry in self.0.iter() {
+            entry.store(&CacheEntry::invalid());
+        }
     }
 }
 
-#[repr(C)]
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq, Debug)]
 pub struct CacheEntry {
+    pub hash: u64,
     pub alpha: bool,
     pub beta: bool,
     pub pv_node: bool,
     pub depth: i8,
-    pub plies_played: u16,
+    pub plies_played: u16, //Modulo AGE_CYCLE
     pub score: i16,
-    pub upper_hash: u32,
-    pub lower_hash: u32,
     pub mv: u16,
     pub static_evaluation: i16,
 }
 
 impl CacheEntry {
+    pub fn age(root_plies_played: usize) -> u16 {
+        (root_plies_played % AGE_CYCLE) as u16
+    }
+
     pub fn get_score(&self) -> f64 {
         self.depth as f64 * if self.pv_node { 1. } else { 0.7 }
     }
 
     pub fn validate_hash(&self, hash: u64) -> bool {
-        (self.upper_hash as u64) == (hash >> 32)
-            && ((self.lower_hash ^ self.mv as u32) as u64) == (hash & 0xFFFFFFFF)
+        self.hash == hash
     }
     //I know this is not idiomatic, but it saves memory...
     pub fn is_invalid(&self) -> bool {
@@ -296,8 +461,7 @@ impl CacheEntry {
     }
     pub fn invalid() -> CacheEntry {
         CacheEntry {
-            upper_hash: 0,
-            lower_hash: 0,
+            hash: 0,
             depth: 0,
             plies_played: 0,
             score: 0,
@@ -308,6 +472,40 @@ impl CacheEntry {
             pv_node: false,
         }
     }
+
+    //Bits 0-15 move, 16-31 score, 32-47 static evaluation, 48-55 depth, 56-58 flags, 59-63 age.
+    //An invalid entry is all zeros
+    pub fn pack(&self) -> u64 {
+        if self.is_invalid() {
+            return 0;
+        }
+        u64::from(self.mv)
+            | u64::from(self.score as u16) << 16
+            | u64::from(self.static_evaluation as u16) << 32
+            | u64::from(self.depth as u8) << 48
+            | u64::from(self.alpha) << 56
+            | u64::from(self.beta) << 57
+            | u64::from(self.pv_node) << 58
+            | u64::from(self.plies_played) << 59
+    }
+
+    pub fn unpack(hash: u64, data: u64) -> CacheEntry {
+        if data == 0 {
+            return CacheEntry::invalid();
+        }
+        CacheEntry {
+            hash,
+            mv: data as u16,
+            score: (data >> 16) as u16 as i16,
+            static_evaluation: (data >> 32) as u16 as i16,
+            depth: (data >> 48) as u8 as i8,
+            alpha: (data >> 56) & 1 == 1,
+            beta: (data >> 57) & 1 == 1,
+            pv_node: (data >> 58) & 1 == 1,
+            plies_played: (data >> 59) as u16,
+        }
+    }
+
     pub fn write(
         &mut self,
         hash: u64,
@@ -320,16 +518,14 @@ impl CacheEntry {
         beta: bool,
         mv: &GameMove,
     ) {
-        let mv = CacheEntry::mv_to_u16(mv);
-        self.upper_hash = (hash >> 32) as u32;
-        self.lower_hash = (hash & 0xFFFFFFFF) as u32 ^ mv as u32;
+        self.hash = hash;
+        self.mv = CacheEntry::mv_to_u16(mv);
         self.depth = depth as i8;
         self.plies_played = plies_played;
         self.score = score;
         self.alpha = alpha;
         self.beta = beta;
         self.pv_node = pv_node;
-        self.mv = mv;
         self.static_evaluation = if static_evaluation.is_some() {
             static_evaluation.unwrap()
         } else {
@@ -487,9 +683,14 @@ impl CacheEntry {
 
 #[cfg(test)]
 mod tests {
-    use super::CacheEntry;
+    use super::{
+        AtomicCacheEntry, Cache, CacheBucket, CacheEntry, Replacement, CACHE_FILE_HEADER_SIZE,
+    };
     use crate::board_representation::game_state::{GameMove, GameMoveType, GameState, PieceType};
     use crate::move_generation::makemove::make_move;
+    use crate::search::CombinedSearchParameters;
+    use std::io::ErrorKind;
+    use std::sync::Arc;
 
     #[test]
     fn mv_to_u16_test() {
@@ -696,4 +897,216 @@ mod tests {
             assert_eq!(d5d6res.piece_type, d5d6.piece_type);
         }
     }
+
+    #[test]
+    fn pack_test() {
+        let mut entry = CacheEntry::invalid();
+        assert_eq!(entry.pack(), 0);
+        assert_eq!(CacheEntry::unpack(0, 0), entry);
+        entry.hash = 0xDEAD_BEEF_0123_4567;
+        entry.mv = 0xABCD;
+        entry.score = -14999;
+        entry.static_evaluation = super::INVALID_STATIC_EVALUATION;
+        entry.depth = -3;
+        entry.beta = true;
+        entry.pv_node = true;
+        entry.plies_played = CacheEntry::age(95);
+        assert_eq!(CacheEntry::unpack(entry.hash, entry.pack()), entry);
+    }
+
+    #[test]
+    fn replacement_test() {
+        let cache = Cache::with_buckets(1);
+        let mut state = GameState::standard();
+        let mv = GameMove {
+            from: 12,
+            to: 28,
+            piece_type: PieceType::Pawn,
+            move_type: GameMoveType::Quiet,
+        };
+        let mut insert = |hash: u64, depth: i16, root_plies_played: usize| {
+            state.hash = hash;
+            cache.insert(
+                &CombinedSearchParameters::from(-16000, 16000, depth, &state, 1, 0),
+                &mv,
+                0,
+                -16000,
+                root_plies_played,
+                None,
+            )
+        };
+        assert_eq!(insert(1, 5, 0), Replacement::Empty);
+        assert_eq!(insert(1, 6, 0), Replacement::SamePosition);
+        assert_eq!(insert(1, 2, 0), Replacement::Skipped);
+        assert_eq!(insert(2, 3, 0), Replacement::Empty);
+        assert_eq!(insert(3, 4, 0), Replacement::Empty);
+        assert_eq!(insert(4, 1, 0), Replacement::Skipped);
+        assert_eq!(insert(4, 7, 0), Replacement::LowestScore);
+        assert_eq!(insert(5, 1, 2), Replacement::Aged);
+
+        let statistics = cache.table_statistics(2);
+        assert_eq!(statistics.entries, 3);
+        assert_eq!(statistics.used, 3);
+        assert_eq!(statistics.ages[0], 1);
+        assert_eq!(statistics.ages[2], 2);
+        assert_eq!(statistics.exact, 3);
+        assert_eq!(statistics.pv_nodes, 3);
+        assert_eq!(statistics.depths[128 + 1], 1);
+    }
+
+    #[test]
+    fn save_load_test() {
+        let cache = Cache::with_size(1);
+        let mut state = GameState::standard();
+        let mv = GameMove {
+            from: 12,
+            to: 28,
+            piece_type: PieceType::Pawn,
+            move_type: GameMoveType::Quiet,
+        };
+        for i in 0..1000u64 {
+            state.hash = i.wrapping_mul(0x9E37_79B9_7F4A_7C15);
+            cache.insert(
+                &CombinedSearchParameters::from(-16000, 16000, 5, &state, 1, 0),
+                &mv,
+                i as i16,
+                -16000,
+                0,
+                None,
+            );
+        }
+        let path = std::env::temp_dir().join(format!("fabchess_hash_{}.bin", std::process::id()));
+        let path = path.to_str().unwrap();
+        cache.save(path).expect("Couldn't save hash");
+        let loaded = Cache::load(path).expect("Couldn't load hash");
+        assert_eq!(loaded.mb_size(), 1);
+        for i in 0..1000u64 {
+            let hash = i.wrapping_mul(0x9E37_79B9_7F4A_7C15);
+            assert_eq!(loaded.probe(hash), cache.probe(hash));
+        }
+        //A truncated file or an invalid amount of buckets is rejected before allocating the table
+        let bytes = std::fs::read(path).unwrap();
+        assert_eq!(bytes.len() as u64, CACHE_FILE_HEADER_SIZE + 16384 * 48);
+        let invalid_data = |bytes: &[u8]| {
+            std::fs::write(path, bytes).unwrap();
+            Cache::load(path).err().map(|e| e.kind()) == Some(ErrorKind::InvalidData)
+        };
+        assert!(invalid_data(&bytes[..bytes.len() - 8]));
+        for buckets in [0u64, 1, 16385, u64::MAX].iter() {
+            let mut header = bytes.clone();
+            header[48..56].copy_from_slice(&buckets.to_le_bytes());
+            assert!(invalid_data(&header));
+        }
+        std::fs::remove_file(path).unwrap();
+    }
+
+    //Threads write one of PAYLOADS different entries for a hash. An entry torn by concurrent writes would mix them
+    const PAYLOADS: u64 = 4;
+
+    fn expected_entry(hash: u64, payload: u64) -> (GameMove, i16, i16, i16) {
+        let data = hash ^ (payload + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+        let mv = GameMove {
+            from: (data >> 20) as u8 % 64,
+            to: (data >> 26) as u8 % 64,
+            move_type: GameMoveType::Quiet,
+            piece_type: PieceType::Knight,
+        };
+        let score = (data >> 32) as i16 % 10000;
+        let static_evaluation = (data >> 48) as i16 % 5000;
+        let depth = (data >> 40) as i16 % 60;
+        (mv, score, static_evaluation, depth)
+    }
+
+    fn is_expected_entry(entry: &CacheEntry, hash: u64) -> bool {
+        (0..PAYLOADS).any(|payload| {
+            let (mv, score, static_evaluation, depth) = expected_entry(hash, payload);
+            entry.hash == hash
+                && entry.mv == CacheEntry::mv_to_u16(&mv)
+                && entry.score == score
+                && entry.static_evaluation == static_evaluation
+                && i16::from(entry.depth) == depth
+                && entry.pv_node
+                && !entry.alpha
+                && !entry.beta
+        })
+    }
+
+    fn xorshift(seed: &mut u64) -> u64 {
+        *seed ^= *seed << 13;
+        *seed ^= *seed >> 7;
+        *seed ^= *seed << 17;
+        *seed
+    }
+
+    #[test]
+    fn torn_entry_test() {
+        let hash = 0x1234_5678_9ABC_DEF0;
+        let mut entries = Vec::new();
+        for payload in 0..2 {
+            let (mv, score, static_evaluation, depth) = expected_entry(hash, payload);
+            let mut entry = CacheEntry::invalid();
+            entry.write(
+                hash,
+                depth,
+                0,
+                score,
+                Some(static_evaluation),
+                true,
+                false,
+                false,
+                &mv,
+            );
+            let atomic_entry = AtomicCacheEntry::default();
+            atomic_entry.store(&entry);
+            assert!(is_expected_entry(&atomic_entry.load(), hash));
+            entries.push(atomic_entry.words());
+        }
+        //The key of one write with the data of another doesn't verify
+        let bucket = CacheBucket::default();
+        bucket.0[0].set_words(entries[0].0, entries[1].1);
+        bucket.0[1].set_words(entries[1].0, entries[0].1);
+        assert!(bucket.probe(hash).is_none());
+    }
+
+    #[test]
+    fn stress_test() {
+        let cache = Arc::new(Cache::with_size(1));
+        let mut threads = Vec::new();
+        for id in 0..8u64 {
+            let cache = Arc::clone(&cache);
+            threads.push(std::thread::spawn(move || {
+                let mut seed = 0x9E37_79B9_7F4A_7C15 ^ (id + 1);
+                let mut state = GameState::standard();
+                let mut hits = 0;
+                for _ in 0..200_000 {
+                    //Only use a few buckets, so that the threads keep writing the same entries
+                    let random = xorshift(&mut seed);
+                    let hash = (random & !0x3FFF) | (random >> 61);
+                    let (mv, score, static_evaluation, depth) =
+                        expected_entry(hash, (id + (random >> 12)) % PAYLOADS);
+                    if random & 0x100 != 0 {
+                        state.hash = hash;
+                        cache.insert(
+                            &CombinedSearchParameters::from(-16000, 16000, depth, &state, 1, 0),
+                            &mv,
+                            score,
+                            -16000,
+                            (random >> 9) as usize % 3,
+                            Some(static_evaluation),
+                        );
+                    }
+                    if let Some(entry) = cache.probe(hash) {
+                        hits += 1;
+                        assert!(is_expected_entry(&entry, hash));
+                    }
+                }
+                hits
+            }));
+        }
+        let hits: usize = threads
+            .into_iter()
+            .map(|thread| thread.join().expect("A thread found an inconsistent entry"))
+            .sum();
+        assert!(hits > 0);
+    }
 }
@@ -0,0 +1,192 @@
+use crate::search::cache::CacheBucket;
+use std::alloc::{alloc, dealloc, handle_alloc_error, Layout};
+use std::fmt::{Display, Formatter, Result};
+use std::mem::size_of;
+use std::ops::Deref;
+use std::ptr::NonNull;
+use std::thread;
+
+pub const HUGE_PAGE_SIZE: usize = 2 * 1024 * 1024;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Pages {
+    Normal,
+    Transparent, //Transparent huge pages requested with madvise
+    Huge,        //Explicit huge pages, which have to be reserved in /proc/sys/vm/nr_hugepages
+}
+
+impl Display for Pages {
+    fn fmt(&self, formatter: &mut Formatter) -> Result {
+        match self {
+            Pages::Normal => write!(formatter, "normal pages"),
+            Pages::Transparent => write!(formatter, "transparent huge pages"),
+            Pages::Huge => write!(formatter, "huge pages"),
+        }
+    }
+}
+
+//The buckets of the transposition table. On Linux the memory is backed by huge pages if possible, which saves
+//TLB misses on nearly every probe. The memory is zeroed by several threads, so that large tables don't stall
+//the engine, and since a page is placed on the NUMA node of the thread touching it first, the table is spread
+//over all nodes
+pub struct BucketMemory {
+    buckets: NonNull<CacheBucket>,
+    len: usize,
+    bytes: usize,
+    pub pages: Pages,
+}
+
+unsafe impl Send for BucketMemory {}
+unsafe impl Sync for BucketMemory {}
+
+impl BucketMemory {
+    pub fn new(len: usize) -> Self {
+        if len == 0 {
+            return BucketMemory {
+                buckets: NonNull::dangling(),
+                len,
+                bytes: 0,
+                pages: Pages::Normal,
+            };
+        }
+        let bytes = len
+            .checked_mul(size_of::<CacheBucket>())
+            .expect("Hash table size overflows");
+        let (ptr, bytes, pages) = allocate(bytes);
+        //A zeroed bucket only holds invalid entries
+        zero_parallel(ptr.as_ptr(), bytes);
+        BucketMemory {
+            buckets: ptr.cast(),
+            len,
+            bytes,
+            pages,
+        }
+    }
+
+    pub fn clear(&self) {
+        let chunk_size = self.len / worker_count() + 1;
+        thread::scope(|scope| {
+            for chunk in self.chunks(chunk_size) {
+                scope.spawn(move || chunk.iter().for_each(CacheBucket::clear));
+            }
+        });
+    }
+}
+
+impl Deref for BucketMemory {
+    type Target = [CacheBucket];
+
+    fn deref(&self) -> &[CacheBucket] {
+        unsafe { std::slice::from_raw_parts(self.buckets.as_ptr(), self.len) }
+    }
+}
+
+impl Drop for BucketMemory {
+    fn drop(&mut self) {
+        if self.len != 0 {
+            free(self.buckets.cast(), self.bytes, self.pages);
+        }
+    }
+}
+
+fn worker_count() -> usize {
+    thread::available_parallelism().map_or(1, |count| count.get())
+}
+
+fn zero_parallel(ptr: *mut u8, bytes: usize) {
+    let chunk_size = (bytes / worker_count() + 1).next_multiple_of(size_of::<CacheBucket>());
+    //Raw pointers can't be sent to other threads
+    let address = ptr as usize;
+    thread::scope(|scope| {
+        for start in (0..bytes).step_by(chunk_size) {
+            let len = chunk_size.min(bytes - start);
+            scope.spawn(move || unsafe {
+                std::ptr::write_bytes((address + start) as *mut u8, 0, len)
+            });
+        }
+    });
+}
+
+fn layout(bytes: usize, align: usize) -> Layout {
+    Layout::from_size_align(bytes, align).expect("Invalid layout for the hash table")
+}
+
+fn allocate_aligned(bytes: usize, align: usize) -> NonNull<u8> {
+    let layout = layout(bytes, align);
+    NonNull::new(unsafe { alloc(layout) }).unwrap_or_else(|| handle_alloc_error(layout))
+}
+
+//Returns the memory, its size rounded up to whole pages and the kind of pages used
+#[cfg(target_os = "linux")]
+fn allocate(bytes: usize) -> (NonNull<u8>, usize, Pages) {
+    let bytes = bytes.next_multiple_of(HUGE_PAGE_SIZE);
+    let ptr = unsafe {
+        libc::mmap(
+            std::ptr::null_mut(),
+            bytes,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_PRIVATE | libc::MAP_ANONYMOUS | libc::MAP_HUGETLB,
+            -1,
+            0,
+        )
+    };
+    if ptr != libc::MAP_FAILED {
+        return (NonNull::new(ptr as *mut u8).unwrap(), bytes, Pages::Huge);
+    }
+    let ptr = allocate_aligned(bytes, HUGE_PAGE_SIZE);
+    let advice = unsafe {
+        libc::madvise(
+            ptr.as_ptr() as *mut libc::c_void,
+            bytes,
+            libc::MADV_HUGEPAGE,
+        )
+    };
+    let pages = if advice == 0 {
+        Pages::Transparent
+    } else {
+        Pages::Normal
+    };
+    (ptr, bytes, pages)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn allocate(bytes: usize) -> (NonNull<u8>, usize, Pages) {
+    (allocate_aligned(bytes, 64), bytes, Pages::Normal)
+}
+
+fn free(ptr: NonNull<u8>, bytes: usize, pages: Pages) {
+    match pages {
+        #[cfg(target_os = "linux")]
+        Pages::Huge => unsafe {
+            libc::munmap(ptr.as_ptr() as *mut libc::c_void, bytes);
+        },
+        #[cfg(target_os = "linux")]
+        _ => unsafe { dealloc(ptr.as_ptr(), layout(bytes, HUGE_PAGE_SIZE)) },
+        #[cfg(not(target_os = "linux"))]
+        _ => unsafe { dealloc(ptr.as_ptr(), layout(bytes, 64)) },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BucketMemory;
+
+    #[test]
+    fn bucket_memory_test() {
+        let memory = BucketMemory::new(100_000);
+        assert_eq!(memory.len(), 100_000);
+        assert!(memory.iter().all(|bucket| bucket.fill_status() == 0));
+        memory[0].0[1].set_words(1, 2);
+        memory[99_999].0[2].set_words(3, 4);
+        assert_eq!(
+            memory
+                .iter()
+                .map(|bucket| bucket.fill_status())
+                .sum::<usize>(),
+            2
+        );
+        memory.clear();
+        assert!(memory.iter().all(|bucket| bucket.fill_status() == 0));
+        assert!(BucketMemory::new(0).is_empty());
+    }
+}
@@ -1,5 +1,13 @@
 use super::MAX_SEARCH_DEPTH;
-use crate::board_representation::game_state::GameState;
+use crate::board_representation::game_state::{GameMove, GameState};
+
+//Scores of the history tables below stay within +-HISTORY_MAX
+pub const HISTORY_MAX: isize = 16384;
+
+#[inline(always)]
+pub fn update_history_entry(entry: &mut isize, bonus: isize) {
+    *entry += bonus - *entry * bonus.abs() / HISTORY_MAX;
+}
 
 #[derive(Clone)]
 pub struct History {
@@ -44,3 +52,124 @@ impl History {
         occurences
     }
 }
+
+//Scores of quiet moves keyed on a previous move, e.g. the last move of the opponent (counter move history)
+//or our own last move (follow up history). Indexed by side to move, previous piece and to-square, piece and to-square
+pub struct ContinuationHistory(Vec<isize>);
+
+impl Default for ContinuationHistory {
+    fn default() -> Self {
+        ContinuationHistory(vec![0; 2 * 6 * 64 * 6 * 64])
+    }
+}
+
+impl ContinuationHistory {
+    #[inline(always)]
+    fn index(side_to_move: usize, previous: &GameMove, mv: &GameMove) -> usize {
+        (((side_to_move * 6 + previous.piece_type.to_index()) * 64 + previous.to as usize) * 6
+            + mv.piece_type.to_index())
+            * 64
+            + mv.to as usize
+    }
+
+    #[inline(always)]
+    pub fn get(&self, side_to_move: usize, previous: &Option<GameMove>, mv: &GameMove) -> isize {
+        match previous {
+            Some(previous) => self.0[ContinuationHistory::index(side_to_move, previous, mv)],
+            None => 0,
+        }
+    }
+
+    #[inline(always)]
+    pub fn update(
+        &mut self,
+        side_to_move: usize,
+        previous: &Option<GameMove>,
+        mv: &GameMove,
+        bonus: isize,
+    ) {
+        if let Some(previous) = previous {
+            update_history_entry(
+                &mut self.0[ContinuationHistory::index(side_to_move, previous, mv)],
+                bonus,
+            );
+        }
+    }
+
+    pub fn clear(&mut self) {
+        for entry in self.0.iter_mut() {
+            *entry = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board_representation::game_state::{GameMoveType, PieceType};
+    use crate::search::alphabeta::update_quiet_cutoff;
+    use crate::search::searcher::Thread;
+    use crate::search::CombinedSearchParameters;
+
+    fn quiet(from: u8, to: u8, piece_type: PieceType) -> GameMove {
+        GameMove {
+            from,
+            to,
+            move_type: GameMoveType::Quiet,
+            piece_type,
+        }
+    }
+
+    #[test]
+    fn update_history_entry_test() {
+        let mut entry = 0;
+        update_history_entry(&mut entry, 100);
+        assert_eq!(entry, 100);
+        update_history_entry(&mut entry, -100);
+        assert_eq!(entry, 0);
+        update_history_entry(&mut entry, -100);
+        assert_eq!(entry, -100);
+        //Repeated bonuses and maluses saturate at +-HISTORY_MAX
+        for _ in 0..1000 {
+            update_history_entry(&mut entry, HISTORY_MAX);
+            assert!(entry <= HISTORY_MAX);
+        }
+        assert_eq!(entry, HISTORY_MAX);
+        for _ in 0..1000 {
+            update_history_entry(&mut entry, -HISTORY_MAX);
+            assert!(entry >= -HISTORY_MAX);
+        }
+        assert_eq!(entry, -HISTORY_MAX);
+    }
+
+    #[test]
+    fn quiet_cutoff_test() {
+        let state = GameState::from_fen("4k3/8/8/8/8/8/8/R3K3 w Q - 0 1");
+        let mut thread = Thread::test_thread(0);
+        let last_move = quiet(52, 60, PieceType::King);
+        let own_last_move = quiet(1, 0, PieceType::Rook);
+        thread.current_moves[0] = Some(own_last_move);
+        thread.current_moves[1] = Some(last_move);
+        let cutoff = quiet(0, 56, PieceType::Rook);
+        let tried = quiet(4, 5, PieceType::King);
+        thread.quiets_tried[2][0] = Some(tried);
+        let p = CombinedSearchParameters::from(0, 1, 6, &state, 1, 2);
+        update_quiet_cutoff(&p, &mut thread, &cutoff, 1);
+
+        //The move causing the cutoff gets a bonus, the quiets tried before it a malus
+        let side = state.color_to_move;
+        assert_eq!(thread.history_score[side][0][56], 36);
+        assert_eq!(thread.history_score[side][4][5], -36);
+        let last_move = Some(last_move);
+        let own_last_move = Some(own_last_move);
+        assert!(thread.counter_move_history.get(side, &last_move, &cutoff) > 0);
+        assert!(thread.counter_move_history.get(side, &last_move, &tried) < 0);
+        assert!(thread.follow_up_history.get(side, &own_last_move, &cutoff) > 0);
+        assert!(thread.follow_up_history.get(side, &own_last_move, &tried) < 0);
+        assert_eq!(
+            thread.counter_moves[side][PieceType::King.to_index()][60],
+            Some(cutoff)
+        );
+        assert_eq!(thread.killer_moves[2][0], Some(cutoff));
+    }
+}
@@ -0,0 +1,294 @@
+use crate::board_representation::game_state::{GameMove, GameState};
+use crate::board_representation::game_state_attack_container::GameStateAttackContainer;
+use crate::move_generation::makemove::make_move;
+use crate::move_generation::movegen::{generate_moves, MoveList};
+
+pub const INFINITE_PROOF: u32 = u32::MAX;
+pub const MATE_SEARCH_NODE_SIZE: usize = std::mem::size_of::<Node>();
+pub const MIN_MATE_SEARCH_NODES: usize = 1 << 20;
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum MateResult {
+    Mate(Vec<GameMove>), //The mating line with the shortest mate against the longest defence found
+    NoMate,              //There is no mate in the given amount of moves
+    Unknown,             //The search was stopped or ran out of nodes
+}
+
+//Node of the proof tree. The children of a node are stored next to each other
+struct Node {
+    mv: Option<GameMove>,
+    or_node: bool,    //The attacker is to move
+    remaining: usize, //Moves left for the attacker
+    first_child: usize,
+    children: usize,
+    proof: u32,
+    disproof: u32,
+}
+
+//Proof-number search for a mate of the side to move in at most the given amount of moves. Nodes don't store
+//their position, it is replayed from the root when selecting the most proving node
+pub struct MateSearch {
+    root: GameState,
+    nodes: Vec<Node>,
+    movelist: MoveList,
+    child_movelist: MoveList,
+    attack_container: GameStateAttackContainer,
+}
+
+impl MateSearch {
+    pub fn new(root: &GameState, moves: usize) -> Self {
+        let mut search = MateSearch {
+            root: root.clone(),
+            nodes: Vec::new(),
+            movelist: MoveList::default(),
+            child_movelist: MoveList::default(),
+            attack_container: GameStateAttackContainer::default(),
+        };
+        let (proof, disproof) = search.evaluate(root, true, moves);
+        search.nodes.push(Node {
+            mv: None,
+            or_node: true,
+            remaining: moves,
+            first_child: 0,
+            children: 0,
+            proof,
+            disproof,
+        });
+        search
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    //Stop is polled every 1024 expansions
+    pub fn run(&mut self, max_nodes: usize, stop: &dyn Fn() -> bool) -> MateResult {
+        let mut expansions = 0;
+        while self.nodes[0].proof != 0 && self.nodes[0].disproof != 0 {
+            if self.nodes.len() >= max_nodes || expansions % 1024 == 0 && stop() {
+                return MateResult::Unknown;
+            }
+            expansions += 1;
+            //Select the most proving node
+            let mut path = vec![0];
+            let mut state = self.root.clone();
+            let mut index = 0;
+            while self.nodes[index].children > 0 {
+                index = self.select_child(index);
+                state = make_move(&state, self.nodes[index].mv.as_ref().unwrap());
+                path.push(index);
+            }
+            self.expand(index, &state);
+            for &index in path.iter().rev() {
+                self.update(index);
+            }
+        }
+        if self.nodes[0].proof == 0 {
+            MateResult::Mate(self.mating_line())
+        } else {
+            MateResult::NoMate
+        }
+    }
+
+    //Proof and disproof number of a new node
+    fn evaluate(&mut self, state: &GameState, or_node: bool, remaining: usize) -> (u32, u32) {
+        self.attack_container.write_state(state);
+        let info = generate_moves(
+            state,
+            false,
+            &mut self.child_movelist,
+            &self.attack_container,
+        );
+        let moves = self.child_movelist.move_list.len() as u32;
+        if moves == 0 {
+            if info.stm_incheck && !or_node {
+                (0, INFINITE_PROOF)
+            } else {
+                (INFINITE_PROOF, 0)
+            }
+        } else if remaining == 0 {
+            (INFINITE_PROOF, 0)
+        } else if or_node {
+            (1, moves)
+        } else {
+            //Positions where the defender has few moves are tried first
+            (moves, 1)
+        }
+    }
+
+    fn expand(&mut self, index: usize, state: &GameState) {
+        self.attack_container.write_state(state);
+        generate_moves(state, false, &mut self.movelist, &self.attack_container);
+        let or_node = !self.nodes[index].or_node;
+        let remaining = if or_node {
+            self.nodes[index].remaining
+        } else {
+            self.nodes[index].remaining - 1
+        };
+        self.nodes[index].first_child = self.nodes.len();
+        self.nodes[index].children = self.movelist.move_list.len();
+        for i in 0..self.movelist.move_list.len() {
+            let mv = self.movelist.move_list[i].0;
+            let (proof, disproof) = self.evaluate(&make_move(state, &mv), or_node, remaining);
+            self.nodes.push(Node {
+                mv: Some(mv),
+                or_node,
+                remaining,
+                first_child: 0,
+                children: 0,
+                proof,
+                disproof,
+            });
+        }
+    }
+
+    fn children(&self, index: usize) -> std::ops::Range<usize> {
+        let node = &self.nodes[index];
+        node.first_child..node.first_child + node.children
+    }
+
+    fn select_child(&self, index: usize) -> usize {
+        if self.nodes[index].or_node {
+            self.children(index)
+                .min_by_key(|&child| self.nodes[child].proof)
+                .unwrap()
+        } else {
+            self.children(index)
+                .min_by_key(|&child| self.nodes[child].disproof)
+                .unwrap()
+        }
+    }
+
+    fn update(&mut self, index: usize) {
+        let children = self.children(index);
+        let proofs = children.clone().map(|child| self.nodes[child].proof);
+        let disproofs = children.map(|child| self.nodes[child].disproof);
+        let (proof, disproof) = if self.nodes[index].or_node {
+            (proofs.min().unwrap(), saturating_sum(disproofs))
+        } else {
+            (saturating_sum(proofs), disproofs.min().unwrap())
+        };
+        self.nodes[index].proof = proof;
+        self.nodes[index].disproof = disproof;
+    }
+
+    //Moves of the attacker until mate in a proven node
+    fn mate_length(&self, index: usize) -> usize {
+        let node = &self.nodes[index];
+        if node.or_node {
+            1 + self
+                .children(index)
+                .filter(|&child| self.nodes[child].proof == 0)
+                .map(|child| self.mate_length(child))
+                .min()
+                .unwrap()
+        } else {
+            self.children(index)
+                .map(|child| self.mate_length(child))
+                .max()
+                .unwrap_or(0)
+        }
+    }
+
+    fn mating_line(&self) -> Vec<GameMove> {
+        let mut line = Vec::new();
+        let mut index = 0;
+        while self.nodes[index].children > 0 {
+            index = if self.nodes[index].or_node {
+                self.children(index)
+                    .filter(|&child| self.nodes[child].proof == 0)
+                    .min_by_key(|&child| self.mate_length(child))
+                    .unwrap()
+            } else {
+                self.children(index)
+                    .max_by_key(|&child| self.mate_length(child))
+                    .unwrap()
+            };
+            line.push(self.nodes[index].mv.unwrap());
+        }
+        line
+    }
+}
+
+fn saturating_sum(numbers: impl Iterator<Item = u32>) -> u32 {
+    numbers.fold(0, |sum, number| sum.saturating_add(number))
+}
+
+//Searches mates in 1, 2, ... moves, so that the shortest mate is found. Returns the result and the amount of
+//nodes of all searches
+pub fn find_mate(
+    state: &GameState,
+    moves: usize,
+    max_nodes: usize,
+    stop: &dyn Fn() -> bool,
+) -> (MateResult, usize) {
+    let mut nodes = 0;
+    for moves in 1..=moves {
+        let mut search = MateSearch::new(state, moves);
+        let result = search.run(max_nodes, stop);
+        nodes += search.node_count();
+        if result != MateResult::NoMate {
+            return (result, nodes);
+        }
+    }
+    (MateResult::NoMate, nodes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_mate, MateResult};
+    use crate::board_representation::game_state::GameState;
+    use crate::board_representation::game_state_attack_container::GameStateAttackContainer;
+    use crate::move_generation::makemove::make_move;
+    use crate::move_generation::movegen::{generate_moves, MoveList};
+    use crate::testing::epd::load_epd;
+
+    #[test]
+    fn mate_suite_test() {
+        let mut movelist = MoveList::default();
+        for position in load_epd("testsuites/mate.epd") {
+            let mate = position.direct_mate.unwrap();
+            let line = match find_mate(&position.state, mate, 1 << 22, &|| false).0 {
+                MateResult::Mate(line) => line,
+                result => panic!("{:?} for {:?}", result, position.id),
+            };
+            assert_eq!(line.len(), 2 * mate - 1, "{:?}", position.id);
+            assert!(position.is_solution(&line[0]), "{:?}", position.id);
+            let mut state = position.state.clone();
+            for mv in line.iter() {
+                state = make_move(&state, mv);
+            }
+            let info = generate_moves(
+                &state,
+                false,
+                &mut movelist,
+                &GameStateAttackContainer::from_state(&state),
+            );
+            assert!(info.stm_incheck && movelist.move_list.is_empty());
+        }
+    }
+
+    #[test]
+    fn no_mate_test() {
+        let no_mate = |fen: &str, moves: usize| {
+            find_mate(&GameState::from_fen(fen), moves, 1 << 22, &|| false).0
+        };
+        assert_eq!(
+            no_mate("8/8/8/8/8/k7/8/K1R5 w - - 0 1", 5),
+            MateResult::NoMate
+        );
+        assert_eq!(
+            no_mate("4r1k1/5ppp/8/8/8/8/1Q3PPP/6K1 w - - 0 1", 3),
+            MateResult::NoMate
+        );
+        //Stalemating isn't a mate
+        assert_eq!(
+            no_mate("k7/8/1Q6/8/8/8/8/7K w - - 0 1", 1),
+            MateResult::NoMate
+        );
+        assert_eq!(
+            find_mate(&GameState::standard(), 3, 1000, &|| false).0,
+            MateResult::Unknown
+        );
+    }
+}
@@ -1,7 +1,10 @@
 pub mod alphabeta;
 pub mod cache;
+pub mod cache_memory;
 pub mod history;
+pub mod mate_search;
 pub mod moveordering;
+pub mod params;
 pub mod quiescence;
 pub mod reserved_memory;
 pub mod searcher;
@@ -11,7 +14,7 @@ pub mod timecontrol;
 use crate::board_representation::game_state::*;
 use crate::board_representation::game_state_attack_container::GameStateAttackContainer;
 use crate::search::searcher::Thread;
-use crate::search::timecontrol::TimeControlInformation;
+use crate::search::timecontrol::{TimeControl, TimeControlInformation};
 use history::History;
 use std::fmt::{Display, Formatter, Result};
 
@@ -188,19 +191,40 @@ pub fn in_check(game_state: &GameState, attack_container: &GameStateAttackContai
 
 #[inline(always)]
 pub fn checkup(thread: &mut Thread) {
-    if (thread.id == 0
-        && thread.tc.time_over(
-            thread.itcs.get_time_elapsed(),
-            &TimeControlInformation {
-                high_score_diff: false,
-                time_saved: thread.time_saved,
-                stable_pv: thread
-                    .itcs
-                    .stable_pv
-                    .load(std::sync::atomic::Ordering::Relaxed),
-            },
-            thread.itcs.uci_options().move_overhead,
-        ))
+    //The other threads publish their node counts regularly, our own is only published once the limit is reached
+    let node_limit_reached = match thread.tc {
+        TimeControl::Nodes(nodes) if thread.id == 0 => {
+            let published =
+                thread.itcs.nodes_searched()[thread.id].load(std::sync::atomic::Ordering::Relaxed);
+            let reached = thread.itcs.get_nodes_sum() - published
+                + thread.search_statistics.nodes_searched
+                >= nodes;
+            if reached {
+                thread.itcs.update(
+                    thread.id,
+                    thread.search_statistics.nodes_searched,
+                    thread.search_statistics.seldepth,
+                    &thread.cache_statistics,
+                );
+            }
+            reached
+        }
+        _ => false,
+    };
+    if node_limit_reached
+        || (thread.id == 0
+            && thread.tc.time_over(
+                thread.itcs.get_time_elapsed(),
+                &TimeControlInformation {
+                    high_score_diff: false,
+                    time_saved: thread.time_saved,
+                    stable_pv: thread
+                        .itcs
+                        .stable_pv
+                        .load(std::sync::atomic::Ordering::Relaxed),
+                },
+                thread.itcs.uci_options().move_overhead,
+            ))
         || *thread
             .itcs
             .timeout_flag
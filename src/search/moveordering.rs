@@ -1,5 +1,7 @@
 use crate::board_representation::game_state::{GameMove, GameMoveType, PieceType};
 use crate::move_generation::movegen;
+use crate::search::alphabeta::previous_moves;
+use crate::search::history::HISTORY_MAX;
 use crate::search::moveordering::MoveOrderingStage::{
     BadCapture, GoodCapture, GoodCaptureInitialization, Killer, PVMove, Quiet, QuietInitialization,
     TTMove,
@@ -11,6 +13,8 @@ use crate::search::{CombinedSearchParameters, GradedMove};
 //For MVV-LVA calculation
 pub const ATTACKER_VALUE: [i16; 6] = [0, 1, 2, 3, 4, 5];
 pub const TARGET_VALUE: [i16; 5] = [100, 400, 400, 650, 1100];
+//Keeps the capture history below the difference of the smallest victim and the largest attacker
+pub const CAPTURE_HISTORY_DIVISOR: isize = 328;
 
 pub fn mvvlva(mv: &GameMove) -> i16 {
     debug_assert!(mv.is_capture());
@@ -105,10 +109,17 @@ impl MoveOrderer {
                     }
                 }
 
-                //Give any capture move in movelist its MVV-LVA score
+                //Give any capture move in movelist its MVV-LVA score, adjusted by capture history
+                let capture_history = &thread.capture_history[p.game_state.color_to_move];
                 for mv in our_mvlist.move_list.iter_mut() {
                     if mv.0.is_capture() {
-                        mv.1 = Some(f64::from(mvvlva(&mv.0)));
+                        mv.1 = Some(
+                            f64::from(mvvlva(&mv.0))
+                                + (capture_history[mv.0.piece_type.to_index()][mv.0.to as usize]
+                                    [mv.0.get_captured_piece().to_index()]
+                                    / CAPTURE_HISTORY_DIVISOR)
+                                    as f64,
+                        );
                     }
                 }
 
@@ -162,12 +173,22 @@ impl MoveOrderer {
                             .unwrap()
                             .is_capture()
                 );
+                //The counter move to the opponent's last move is tried together with the killers
+                let counter_move =
+                    previous_moves(thread, p.current_depth)
+                        .0
+                        .and_then(|last_move| {
+                            thread.counter_moves[p.game_state.color_to_move]
+                                [last_move.piece_type.to_index()]
+                                [last_move.to as usize]
+                        });
                 let our_list = &mut thread.movelist.move_lists[p.current_depth];
                 let mut found_index = our_list.move_list.len();
                 for (index, gmv) in our_list.move_list.iter().enumerate() {
                     if gmv.1.is_none()
                         && (Some(gmv.0) == thread.killer_moves[p.current_depth][0]
-                            || Some(gmv.0) == thread.killer_moves[p.current_depth][1])
+                            || Some(gmv.0) == thread.killer_moves[p.current_depth][1]
+                            || Some(gmv.0) == counter_move)
                     {
                         found_index = index;
                         break;
@@ -183,17 +204,30 @@ impl MoveOrderer {
                 }
             }
             MoveOrderingStage::QuietInitialization => {
+                let (last_move, own_last_move) = previous_moves(thread, p.current_depth);
                 for mv in thread.movelist.move_lists[p.current_depth]
                     .move_list
                     .iter_mut()
                 {
                     if mv.1.is_none() {
                         debug_assert!(!mv.0.is_capture());
+                        //Both continuation histories are within +-HISTORY_MAX, so the score isn't negative
+                        let continuation_history = thread.counter_move_history.get(
+                            p.game_state.color_to_move,
+                            &last_move,
+                            &mv.0,
+                        ) + thread.follow_up_history.get(
+                            p.game_state.color_to_move,
+                            &own_last_move,
+                            &mv.0,
+                        );
                         mv.1 = Some(
-                            thread.hh_score[p.game_state.color_to_move][mv.0.from as usize]
+                            (thread.hh_score[p.game_state.color_to_move][mv.0.from as usize]
                                 [mv.0.to as usize] as f64
                                 / thread.bf_score[p.game_state.color_to_move][mv.0.from as usize]
                                     [mv.0.to as usize] as f64
+                                + continuation_history as f64 / HISTORY_MAX as f64
+                                + 2.)
                                 / 1000.0,
                         );
                     }
@@ -235,3 +269,50 @@ impl MoveOrderer {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board_representation::game_state::GameState;
+
+    fn quiet(from: u8, to: u8, piece_type: PieceType) -> GameMove {
+        GameMove {
+            from,
+            to,
+            move_type: GameMoveType::Quiet,
+            piece_type,
+        }
+    }
+
+    #[test]
+    fn killer_stage_test() {
+        let state = GameState::from_fen("4k3/8/8/8/8/8/8/R3K3 w Q - 0 1");
+        let mut thread = Thread::test_thread(0);
+        let last_move = quiet(52, 60, PieceType::King);
+        let counter_move = quiet(0, 56, PieceType::Rook);
+        let killer_move = quiet(4, 5, PieceType::King);
+        thread.current_moves[0] = Some(last_move);
+        thread.counter_moves[state.color_to_move][PieceType::King.to_index()][60] =
+            Some(counter_move);
+        thread.killer_moves[1][0] = Some(killer_move);
+        thread.attack_container.attack_containers[1].write_state(&state);
+        let p = CombinedSearchParameters::from(0, 1, 6, &state, 1, 1);
+
+        //The killer and the counter move are tried before all other quiets, and only once
+        let mut move_orderer = MoveOrderer {
+            stage: 0,
+            stages: &NORMAL_STAGES,
+            gen_only_captures: false,
+            has_legal_move: false,
+        };
+        let mut moves = Vec::new();
+        while let Some((mv, _)) = move_orderer.next(&mut thread, &p, &None, &None) {
+            moves.push(mv);
+        }
+        assert_eq!(moves.len(), 16);
+        assert!(moves[..2].contains(&counter_move));
+        assert!(moves[..2].contains(&killer_move));
+        assert!(!moves[2..].contains(&counter_move));
+        assert!(!moves[2..].contains(&killer_move));
+    }
+}
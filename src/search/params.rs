@@ -0,0 +1,240 @@
+use crate::search::alphabeta::*;
+use crate::search::quiescence::DELTA_PRUNING;
+use crate::search::searcher::{ASPIRATION_DELTA, ASPIRATION_GROWTH, ASPIRATION_SCORE_DIVISOR};
+
+//Search constants which can be changed at runtime with hidden UCI options, mostly for tuning them.
+//The constants they are named after are the defaults
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct SearchParams {
+    pub futility_margin: i16,
+    pub futility_depth: i16,
+    pub static_null_move_margin: i16,
+    pub static_null_move_depth: i16,
+    pub null_move_pruning_depth: i16,
+    pub see_pruning_depth: i16,
+    pub see_pruning_capture_mult: i16,
+    pub singular_extension_margin: i16,
+    pub razoring_depth: i16,
+    pub razoring_margin: i16,
+    pub probcut_depth: i16,
+    pub probcut_margin: i16,
+    pub probcut_reduction: i16,
+    pub delta_pruning: i16,
+    pub lmr_depth_divisor: i16,
+    pub lmr_index_divisor: i16,
+    pub lmr_pv_factor: i16,
+    pub aspiration_delta: i16,
+    pub aspiration_score_divisor: i16,
+    pub aspiration_growth: i16,
+}
+
+impl Default for SearchParams {
+    fn default() -> Self {
+        SearchParams {
+            futility_margin: FUTILITY_MARGIN,
+            futility_depth: FUTILITY_DEPTH,
+            static_null_move_margin: STATIC_NULL_MOVE_MARGIN,
+            static_null_move_depth: STATIC_NULL_MOVE_DEPTH,
+            null_move_pruning_depth: NULL_MOVE_PRUNING_DEPTH,
+            see_pruning_depth: SEE_PRUNING_DEPTH,
+            see_pruning_capture_mult: SEE_PRUNING_CAPTURE_MULT,
+            singular_extension_margin: SINGULAR_EXTENSION_MARGIN,
+            razoring_depth: RAZORING_DEPTH,
+            razoring_margin: RAZORING_MARGIN,
+            probcut_depth: PROBCUT_DEPTH,
+            probcut_margin: PROBCUT_MARGIN,
+            probcut_reduction: PROBCUT_REDUCTION,
+            delta_pruning: DELTA_PRUNING,
+            lmr_depth_divisor: LMR_DEPTH_DIVISOR,
+            lmr_index_divisor: LMR_INDEX_DIVISOR,
+            lmr_pv_factor: LMR_PV_FACTOR,
+            aspiration_delta: ASPIRATION_DELTA,
+            aspiration_score_divisor: ASPIRATION_SCORE_DIVISOR,
+            aspiration_growth: ASPIRATION_GROWTH,
+        }
+    }
+}
+
+impl SearchParams {
+    //Case insensitive, like UCI option names
+    pub fn parameter(&mut self, name: &str) -> Option<&mut i16> {
+        tunable_parameter(name).map(move |parameter| (parameter.field)(self))
+    }
+}
+
+//A hidden UCI spin option. Step is the perturbation SPSA starts with
+pub struct TunableParameter {
+    pub name: &'static str,
+    pub min: i16,
+    pub max: i16,
+    pub step: i16,
+    pub field: fn(&mut SearchParams) -> &mut i16,
+}
+
+pub fn tunable_parameter(name: &str) -> Option<&'static TunableParameter> {
+    TUNABLE_PARAMETERS
+        .iter()
+        .find(|parameter| parameter.name.eq_ignore_ascii_case(name))
+}
+
+pub const TUNABLE_PARAMETERS: [TunableParameter; 20] = [
+    TunableParameter {
+        name: "FutilityMargin",
+        min: 0,
+        max: 300,
+        step: 10,
+        field: |p| &mut p.futility_margin,
+    },
+    TunableParameter {
+        name: "FutilityDepth",
+        min: 0,
+        max: 12,
+        step: 1,
+        field: |p| &mut p.futility_depth,
+    },
+    TunableParameter {
+        name: "StaticNullMoveMargin",
+        min: 0,
+        max: 400,
+        step: 10,
+        field: |p| &mut p.static_null_move_margin,
+    },
+    TunableParameter {
+        name: "StaticNullMoveDepth",
+        min: 0,
+        max: 12,
+        step: 1,
+        field: |p| &mut p.static_null_move_depth,
+    },
+    TunableParameter {
+        name: "NullMovePruningDepth",
+        min: 1,
+        max: 12,
+        step: 1,
+        field: |p| &mut p.null_move_pruning_depth,
+    },
+    TunableParameter {
+        name: "SeePruningDepth",
+        min: 0,
+        max: 12,
+        step: 1,
+        field: |p| &mut p.see_pruning_depth,
+    },
+    TunableParameter {
+        name: "SeePruningCaptureMult",
+        min: -100,
+        max: 0,
+        step: 3,
+        field: |p| &mut p.see_pruning_capture_mult,
+    },
+    TunableParameter {
+        name: "SingularExtensionMargin",
+        min: 0,
+        max: 10,
+        step: 1,
+        field: |p| &mut p.singular_extension_margin,
+    },
+    TunableParameter {
+        name: "RazoringDepth",
+        min: 0,
+        max: 6,
+        step: 1,
+        field: |p| &mut p.razoring_depth,
+    },
+    TunableParameter {
+        name: "RazoringMargin",
+        min: 0,
+        max: 1000,
+        step: 25,
+        field: |p| &mut p.razoring_margin,
+    },
+    TunableParameter {
+        name: "ProbCutDepth",
+        min: 2,
+        max: 12,
+        step: 1,
+        field: |p| &mut p.probcut_depth,
+    },
+    TunableParameter {
+        name: "ProbCutMargin",
+        min: 0,
+        max: 500,
+        step: 15,
+        field: |p| &mut p.probcut_margin,
+    },
+    TunableParameter {
+        name: "ProbCutReduction",
+        min: 1,
+        max: 8,
+        step: 1,
+        field: |p| &mut p.probcut_reduction,
+    },
+    TunableParameter {
+        name: "DeltaPruning",
+        min: 0,
+        max: 500,
+        step: 10,
+        field: |p| &mut p.delta_pruning,
+    },
+    TunableParameter {
+        name: "LmrDepthDivisor",
+        min: 50,
+        max: 800,
+        step: 20,
+        field: |p| &mut p.lmr_depth_divisor,
+    },
+    TunableParameter {
+        name: "LmrIndexDivisor",
+        min: 50,
+        max: 800,
+        step: 20,
+        field: |p| &mut p.lmr_index_divisor,
+    },
+    TunableParameter {
+        name: "LmrPvFactor",
+        min: 0,
+        max: 100,
+        step: 5,
+        field: |p| &mut p.lmr_pv_factor,
+    },
+    TunableParameter {
+        name: "AspirationDelta",
+        min: 1,
+        max: 200,
+        step: 3,
+        field: |p| &mut p.aspiration_delta,
+    },
+    TunableParameter {
+        name: "AspirationScoreDivisor",
+        min: 1,
+        max: 500,
+        step: 5,
+        field: |p| &mut p.aspiration_score_divisor,
+    },
+    TunableParameter {
+        name: "AspirationGrowth",
+        min: 101,
+        max: 400,
+        step: 10,
+        field: |p| &mut p.aspiration_growth,
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parameter_test() {
+        let mut params = SearchParams::default();
+        for parameter in TUNABLE_PARAMETERS.iter() {
+            let default = *params.parameter(parameter.name).unwrap();
+            assert!(parameter.min <= default && default <= parameter.max);
+            assert!(parameter.step > 0);
+        }
+        *params.parameter("futilitymargin").unwrap() = 42;
+        assert_eq!(params.futility_margin, 42);
+        assert_ne!(params, SearchParams::default());
+        assert!(params.parameter("Hash").is_none());
+    }
+}
@@ -63,7 +63,8 @@ pub fn q_search(mut p: CombinedSearchParameters, thread: &mut Thread) -> i16 {
     if !incheck {
         if let SearchInstruction::StopSearching(res) = adjust_standpat(&mut p, stand_pat.unwrap()) {
             return res;
-        } else if let SearchInstruction::StopSearching(res) = delta_pruning(&p, stand_pat.unwrap())
+        } else if let SearchInstruction::StopSearching(res) =
+            delta_pruning(&p, stand_pat.unwrap(), thread.params.delta_pruning)
         {
             return res;
         }
@@ -78,12 +79,14 @@ pub fn q_search(mut p: CombinedSearchParameters, thread: &mut Thread) -> i16 {
                 .cache()
                 .lookup(&p, &mut None, &mut tt_move, thread.root_plies_played)
         {
+            thread.cache_statistics.add_probe(true, true);
             #[cfg(feature = "search-statistics")]
             {
                 thread.search_statistics.add_cache_hit_aj_replace_ns();
             }
             return res;
         }
+        thread.cache_statistics.add_probe(tt_move.is_some(), false);
     }
     #[cfg(feature = "search-statistics")]
     {
@@ -118,6 +121,8 @@ pub fn q_search(mut p: CombinedSearchParameters, thread: &mut Thread) -> i16 {
         gen_only_captures: !incheck,
         has_legal_move: false,
     };
+    #[cfg(feature = "search-statistics")]
+    let mut index: usize = 0;
 
     loop {
         let mv = move_orderer.next(thread, &p, &None, &tt_move);
@@ -131,11 +136,13 @@ pub fn q_search(mut p: CombinedSearchParameters, thread: &mut Thread) -> i16 {
                 p.game_state.phase.phase,
                 *stand_pat.as_ref().unwrap(),
                 p.alpha,
+                thread.params.delta_pruning,
             )
         {
             continue;
         }
         debug_assert!(incheck || capture_move.is_capture());
+        thread.current_moves[p.current_depth] = Some(capture_move);
         let next_g = make_move(p.game_state, &capture_move);
         //Step 8.4. Search move
         let score = -q_search(
@@ -170,6 +177,10 @@ pub fn q_search(mut p: CombinedSearchParameters, thread: &mut Thread) -> i16 {
         if score > p.alpha {
             p.alpha = score;
         }
+        #[cfg(feature = "search-statistics")]
+        {
+            index += 1;
+        }
     }
 
     thread.history.pop();
@@ -188,7 +199,7 @@ pub fn q_search(mut p: CombinedSearchParameters, thread: &mut Thread) -> i16 {
 
     //Step 10. Make TT entry
     if has_pv && p.depth_left == 0 && !thread.self_stop {
-        thread.itcs.cache().insert(
+        let replacement = thread.itcs.cache().insert(
             &p,
             &thread.pv_table[p.current_depth].pv[0].expect("Can't unwrap move for TT in qsearch!"),
             current_max_score,
@@ -200,6 +211,7 @@ pub fn q_search(mut p: CombinedSearchParameters, thread: &mut Thread) -> i16 {
                 Some(*stand_pat.as_ref().unwrap() * p.color)
             },
         );
+        thread.cache_statistics.add_insert(replacement);
     }
 
     //Step 11. Return
@@ -218,8 +230,12 @@ pub fn adjust_standpat(p: &mut CombinedSearchParameters, stand_pat: i16) -> Sear
 }
 
 #[inline(always)]
-pub fn delta_pruning(p: &CombinedSearchParameters, stand_pat: i16) -> SearchInstruction {
-    let diff = p.alpha - stand_pat - DELTA_PRUNING;
+pub fn delta_pruning(
+    p: &CombinedSearchParameters,
+    stand_pat: i16,
+    margin: i16,
+) -> SearchInstruction {
+    let diff = p.alpha - stand_pat - margin;
     if diff > 0 && best_move_value(p.game_state) < diff {
         SearchInstruction::StopSearching(stand_pat)
     } else {
@@ -249,7 +265,13 @@ pub fn best_move_value(state: &GameState) -> i16 {
 }
 
 #[inline(always)]
-pub fn passes_delta_pruning(capture_move: &GameMove, phase: f64, eval: i16, alpha: i16) -> bool {
+pub fn passes_delta_pruning(
+    capture_move: &GameMove,
+    phase: f64,
+    eval: i16,
+    alpha: i16,
+    margin: i16,
+) -> bool {
     if phase == 0.0 || eval >= alpha {
         return true;
     }
@@ -261,7 +283,7 @@ pub fn passes_delta_pruning(capture_move: &GameMove, phase: f64, eval: i16, alph
         GameMoveType::EnPassant => &PieceType::Pawn,
         _ => panic!("No capture!"),
     };
-    eval + captured_piece.to_piece_score().interpolate(phase) + DELTA_PRUNING >= alpha
+    eval + captured_piece.to_piece_score().interpolate(phase) + margin >= alpha
 }
 
 #[inline(always)]
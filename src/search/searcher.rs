@@ -1,7 +1,8 @@
 use super::alphabeta::principal_variation_search;
 use super::cache::Cache;
-use super::history::History;
-use super::statistics::SearchStatistics;
+use super::history::{ContinuationHistory, History};
+use super::params::SearchParams;
+use super::statistics::{CacheStatistics, SearchStatistics};
 use super::timecontrol::TimeControl;
 use super::GameMove;
 use super::PrincipalVariation;
@@ -15,6 +16,7 @@ use crate::move_generation::movegen::{generate_moves, MoveList};
 use crate::search::reserved_memory::{ReservedAttackContainer, ReservedMoveList};
 use crate::search::{CombinedSearchParameters, ScoredPrincipalVariation, MATE_SCORE};
 use crate::uci::uci_engine::UCIOptions;
+use rand::Rng;
 use std::cell::UnsafeCell;
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::AtomicU64;
@@ -25,31 +27,33 @@ use std::sync::{Arc, Mutex, RwLock};
 use std::thread;
 use std::time::Instant;
 
-pub const DEFAULT_SKIP_RATIO: usize = 2;
-pub const MIN_SKIP_RATIO: usize = 1;
-pub const MAX_SKIP_RATIO: usize = 1024;
-
 pub const DEFAULT_THREADS: usize = 1;
 pub const MAX_THREADS: usize = 65536;
 pub const MIN_THREADS: usize = 1;
+//Helper threads skip a depth with a chance of 1/HELPER_SKIP_CHANCE, so that they don't all search the same depths
+pub const HELPER_SKIP_CHANCE: u32 = 2;
+//Added to the score difference of every vote, so that the thread with the lowest score still has a say
+pub const VOTE_SCORE_OFFSET: i64 = 14;
+//The aspiration window starts at ASPIRATION_DELTA + |score| / ASPIRATION_SCORE_DIVISOR and grows by
+//ASPIRATION_GROWTH percent on every fail
+pub const ASPIRATION_DELTA: i16 = 14;
+pub const ASPIRATION_SCORE_DIVISOR: i16 = 50;
+pub const ASPIRATION_GROWTH: i16 = 150;
 
-#[derive(Copy, Clone)]
-pub enum DepthInformation {
-    FullySearched,
-    CurrentlySearchedBy(usize),
-    UnSearched,
-}
 pub struct InterThreadCommunicationSystem {
     pub uci_options: UnsafeCell<UCIOptions>,
     pub best_pv: Mutex<ScoredPrincipalVariation>,
     pub stable_pv: AtomicBool,
-    pub depth_info: Mutex<[DepthInformation; MAX_SEARCH_DEPTH]>,
-    pub start_time: RwLock<Instant>, //Only used for reporting
-    pub nodes_searched: UnsafeCell<Vec<AtomicU64>>, // Only used for reporting
-    pub seldepth: AtomicUsize,       // Only used for reporting
-    pub cache: UnsafeCell<Cache>,    //Only used for reporting
+    pub thread_results: Mutex<Vec<ScoredPrincipalVariation>>, //Deepest completed iteration of every thread
+    pub start_time: RwLock<Instant>,                          //Only used for reporting
+    pub nodes_searched: UnsafeCell<Vec<AtomicU64>>,           // Only used for reporting
+    pub seldepth: AtomicUsize,                                // Only used for reporting
+    pub cache: UnsafeCell<Cache>,                             //Only replaced through replace_cache
+    pub search_lock: Mutex<()>,                               //Held while a search runs
     pub cache_status: AtomicUsize,
     pub last_cache_status: Mutex<Option<Instant>>,
+    pub thread_cache_statistics: Mutex<Vec<CacheStatistics>>, //Published by the threads with their node counts
+    pub cache_statistics: Mutex<CacheStatistics>, //Sum of all searches since the table was cleared
     pub timeout_flag: RwLock<bool>,
     pub saved_time: AtomicU64,
     pub tx: RwLock<Vec<Sender<ThreadInstruction>>>,
@@ -58,8 +62,22 @@ pub struct InterThreadCommunicationSystem {
 }
 
 impl InterThreadCommunicationSystem {
-    pub fn cache(&self) -> &mut Cache {
-        unsafe { self.cache.get().as_mut().unwrap() }
+    pub fn cache(&self) -> &Cache {
+        unsafe { self.cache.get().as_ref().unwrap() }
+    }
+    //Threads probe the table without locks, so it can only be replaced while no search runs.
+    //A running search is stopped first
+    pub fn replace_cache(&self, cache: Cache) {
+        let _search = match self.search_lock.try_lock() {
+            Ok(guard) => guard,
+            Err(_) => {
+                *self.timeout_flag.write().unwrap() = true;
+                self.search_lock.lock().unwrap()
+            }
+        };
+        unsafe {
+            *self.cache.get() = cache;
+        }
     }
     pub fn uci_options(&self) -> &mut UCIOptions {
         unsafe { self.uci_options.get().as_mut().unwrap() }
@@ -73,13 +91,16 @@ impl InterThreadCommunicationSystem {
             uci_options: UnsafeCell::new(UCIOptions::default()),
             best_pv: Mutex::new(ScoredPrincipalVariation::default()),
             stable_pv: AtomicBool::new(false),
-            depth_info: Mutex::new([DepthInformation::UnSearched; MAX_SEARCH_DEPTH]),
+            thread_results: Mutex::new(Vec::new()),
             nodes_searched: UnsafeCell::new(Vec::new()),
             seldepth: AtomicUsize::new(0),
             start_time: RwLock::new(Instant::now()),
             last_cache_status: Mutex::new(None),
             cache_status: AtomicUsize::new(0),
+            thread_cache_statistics: Mutex::new(Vec::new()),
+            cache_statistics: Mutex::new(CacheStatistics::default()),
             cache: UnsafeCell::new(Cache::with_size(0)),
+            search_lock: Mutex::new(()),
             timeout_flag: RwLock::new(false),
             saved_time: AtomicU64::new(0u64),
             tx: RwLock::new(Vec::new()),
@@ -104,6 +125,10 @@ impl InterThreadCommunicationSystem {
         let itcs_nodes_searched = itcs.nodes_searched();
         *itcs_tx = Vec::with_capacity(new_thread_count);
         *itcs_nodes_searched = Vec::with_capacity(new_thread_count);
+        *itcs.thread_results.lock().unwrap() =
+            vec![ScoredPrincipalVariation::default(); new_thread_count];
+        *itcs.thread_cache_statistics.lock().unwrap() =
+            vec![CacheStatistics::default(); new_thread_count];
         for id in 0..new_thread_count {
             itcs_nodes_searched.push(AtomicU64::new(0));
             let (tx, rx) = channel();
@@ -117,17 +142,37 @@ impl InterThreadCommunicationSystem {
         }
     }
 
+    pub fn reset_cache_statistics(&self) {
+        *self.cache_statistics.lock().unwrap() = CacheStatistics::default();
+    }
+
     pub fn get_time_elapsed(&self) -> u64 {
         let now = Instant::now();
         let dur = now.duration_since(*self.start_time.read().unwrap());
         dur.as_millis() as u64
     }
 
-    pub fn update(&self, thread_id: usize, nodes_searched: u64, seldepth: usize) {
+    pub fn update(
+        &self,
+        thread_id: usize,
+        nodes_searched: u64,
+        seldepth: usize,
+        cache_statistics: &CacheStatistics,
+    ) {
         let curr_seldepth = self.seldepth.load(Ordering::Relaxed);
         self.seldepth
             .store(curr_seldepth.max(seldepth), Ordering::Relaxed);
         self.nodes_searched()[thread_id].store(nodes_searched, Ordering::Relaxed);
+        self.thread_cache_statistics.lock().unwrap()[thread_id] = *cache_statistics;
+    }
+
+    //Sum of all threads in the current search
+    pub fn search_cache_statistics(&self) -> CacheStatistics {
+        let mut res = CacheStatistics::default();
+        for statistics in self.thread_cache_statistics.lock().unwrap().iter() {
+            res.add(statistics);
+        }
+        res
     }
 
     pub fn get_nodes_sum(&self) -> u64 {
@@ -153,90 +198,126 @@ impl InterThreadCommunicationSystem {
                 *curr_best = scored_pv.clone();
             }
             //Report to UCI
-            let searched_nodes: u64 = self.get_nodes_sum();
-            let elapsed_time = self.get_time_elapsed();
-            let mut cache_status = self.last_cache_status.lock().unwrap();
-            let fill_status = if cache_status.is_none()
-                || Instant::now()
-                    .duration_since(cache_status.unwrap())
-                    .as_millis()
-                    > 200
-            {
-                *cache_status = Some(Instant::now());
-                self.cache_status
-                    .store(self.cache().fill_status(), Ordering::Relaxed);
-                self.cache_status.load(Ordering::Relaxed)
-            } else {
-                self.cache_status.load(Ordering::Relaxed)
-            };
-            let score_string = if scored_pv.score.abs() > MATE_SCORE - 200 {
-                let dtm = if scored_pv.score > 0 {
-                    (MATE_SCORE - scored_pv.score) / 2 + 1
-                } else {
-                    (-MATE_SCORE - scored_pv.score) / 2
-                };
-                format!("score mate {}", dtm)
+            self.report_pv(scored_pv);
+        }
+    }
+
+    pub fn report_pv(&self, scored_pv: &ScoredPrincipalVariation) {
+        let searched_nodes: u64 = self.get_nodes_sum();
+        let elapsed_time = self.get_time_elapsed();
+        let mut cache_status = self.last_cache_status.lock().unwrap();
+        let refresh_cache_status = cache_status.is_none()
+            || Instant::now()
+                .duration_since(cache_status.unwrap())
+                .as_millis()
+                > 200;
+        if refresh_cache_status {
+            *cache_status = Some(Instant::now());
+            self.cache_status
+                .store(self.cache().fill_status(), Ordering::Relaxed);
+        }
+        let fill_status = self.cache_status.load(Ordering::Relaxed);
+        let score_string = if scored_pv.score.abs() > MATE_SCORE - 200 {
+            let dtm = if scored_pv.score > 0 {
+                (MATE_SCORE - scored_pv.score) / 2 + 1
             } else {
-                format!("score cp {}", scored_pv.score)
+                (-MATE_SCORE - scored_pv.score) / 2
             };
-            println!(
-                "info depth {} seldepth {} nodes {} nps {} hashfull {:.0} time {} {} pv {}",
-                scored_pv.depth,
-                self.seldepth.load(Ordering::Relaxed),
-                searched_nodes,
-                (searched_nodes as f64 / (elapsed_time.max(1) as f64 / 1000.0)) as u64,
-                fill_status,
-                self.get_time_elapsed(),
-                score_string,
-                scored_pv.pv
-            );
+            format!("score mate {}", dtm)
+        } else {
+            format!("score cp {}", scored_pv.score)
+        };
+        println!(
+            "info depth {} seldepth {} nodes {} nps {} hashfull {:.0} time {} {} pv {}",
+            scored_pv.depth,
+            self.seldepth.load(Ordering::Relaxed),
+            searched_nodes,
+            (searched_nodes as f64 / (elapsed_time.max(1) as f64 / 1000.0)) as u64,
+            fill_status,
+            self.get_time_elapsed(),
+            score_string,
+            scored_pv.pv
+        );
+        if refresh_cache_status {
+            println!("info String Hash {}", self.search_cache_statistics());
+        }
+    }
+
+    pub fn register_result(&self, thread_id: usize, scored_pv: &ScoredPrincipalVariation) {
+        self.thread_results.lock().unwrap()[thread_id] = scored_pv.clone();
+    }
+
+    //The move voted for by all threads, or the deepest pv if no thread completed an iteration yet
+    pub fn best_result(&self) -> ScoredPrincipalVariation {
+        let results = self.thread_results.lock().unwrap();
+        if self.uci_options().debug_print {
+            for (id, result) in results.iter().enumerate() {
+                if let Some(mv) = result.pv.pv[0] {
+                    println!(
+                        "info String Thread {} votes for {:?} with depth {} and score {}",
+                        id, mv, result.depth, result.score
+                    );
+                }
+            }
+        }
+        match vote(&results) {
+            Some(result) => result.clone(),
+            None => self.best_pv.lock().unwrap().clone(),
         }
     }
 
-    pub fn report_bestmove(&self) {
+    pub fn report_bestmove(&self, best_result: &ScoredPrincipalVariation) {
+        //The voted result may come from another thread or iteration than the last reported pv
+        let best_pv = self.best_pv.lock().unwrap().clone();
+        if best_pv.depth != best_result.depth
+            || best_pv.score != best_result.score
+            || format!("{}", best_pv.pv) != format!("{}", best_result.pv)
+        {
+            self.report_pv(best_result);
+        }
         println!(
             "bestmove {:?}",
-            self.best_pv.lock().unwrap().pv.pv[0]
+            best_result.pv.pv[0]
                 .as_ref()
                 .expect("Could not unwrap pv for bestmove!")
         );
     }
+}
 
-    pub fn get_next_depth(&self, mut from_depth: usize) -> (usize, bool) {
-        if from_depth == 0 {
-            return (1, true);
-        }
-        from_depth -= 1; //Depth 1 has index 0
-        let mut depth_info = self.depth_info.lock().unwrap();
-        depth_info[from_depth] = DepthInformation::FullySearched;
-        let mut next_depth = from_depth + 1;
-        let mut main_thread = false;
-        while next_depth < MAX_SEARCH_DEPTH {
-            match depth_info[next_depth] {
-                DepthInformation::FullySearched => {
-                    next_depth += 1;
-                }
-                DepthInformation::CurrentlySearchedBy(other_thread) => {
-                    if other_thread as f64
-                        >= self.uci_options().threads as f64 / self.uci_options().skip_ratio as f64
-                    {
-                        next_depth += 1;
-                    } else {
-                        depth_info[next_depth] =
-                            DepthInformation::CurrentlySearchedBy(other_thread + 1);
-                        break;
-                    }
-                }
-                DepthInformation::UnSearched => {
-                    main_thread = true;
-                    depth_info[next_depth] = DepthInformation::CurrentlySearchedBy(1);
-                    break;
-                }
-            }
+//Every thread votes for the best move of its deepest completed iteration, weighted by depth and by how much
+//its score exceeds the lowest score of all threads. Returns the deepest result for the move with most votes,
+//unless a thread found a mate
+pub fn vote(results: &[ScoredPrincipalVariation]) -> Option<&ScoredPrincipalVariation> {
+    let results: Vec<&ScoredPrincipalVariation> = results
+        .iter()
+        .filter(|result| result.pv.pv[0].is_some())
+        .collect();
+    let min_score = results.iter().map(|result| result.score).min()?;
+    let mut votes: Vec<(GameMove, i64)> = Vec::new();
+    for result in results.iter() {
+        let weight = (i64::from(result.score) - i64::from(min_score) + VOTE_SCORE_OFFSET)
+            * result.depth as i64;
+        let mv = result.pv.pv[0].unwrap();
+        match votes.iter_mut().find(|vote| vote.0 == mv) {
+            Some(vote) => vote.1 += weight,
+            None => votes.push((mv, weight)),
         }
-
-        (next_depth + 1, main_thread)
     }
+    let votes_for = |result: &ScoredPrincipalVariation| {
+        votes
+            .iter()
+            .find(|vote| Some(vote.0) == result.pv.pv[0])
+            .unwrap()
+            .1
+    };
+    results.into_iter().max_by_key(|result| {
+        let score = i64::from(result.score);
+        if result.score > -MATED_IN_MAX {
+            (1, score, result.depth as i64, score)
+        } else {
+            (0, votes_for(result), result.depth as i64, score)
+        }
+    })
 }
 unsafe impl std::marker::Sync for InterThreadCommunicationSystem {}
 pub enum ThreadInstruction {
@@ -254,17 +335,25 @@ pub struct Thread {
     pub pv_table: Vec<PrincipalVariation>,
     pub killer_moves: [[Option<GameMove>; 2]; MAX_SEARCH_DEPTH],
     pub quiets_tried: [[Option<GameMove>; 128]; MAX_SEARCH_DEPTH],
+    pub captures_tried: [[Option<GameMove>; 128]; MAX_SEARCH_DEPTH],
+    pub excluded_moves: [Option<GameMove>; MAX_SEARCH_DEPTH], //Set while searching for singular moves
+    pub current_moves: [Option<GameMove>; MAX_SEARCH_DEPTH], //Move played in each ply, None for null moves
+    pub counter_moves: [[[Option<GameMove>; 64]; 6]; 2], //Quiet move refuting the previous piece and to-square
+    pub counter_move_history: ContinuationHistory,
+    pub follow_up_history: ContinuationHistory,
+    pub capture_history: [[[[isize; 5]; 64]; 6]; 2], //Side, piece, to-square, captured piece
     pub hh_score: [[[usize; 64]; 64]; 2],
     pub bf_score: [[[usize; 64]; 64]; 2],
     pub history_score: [[[isize; 64]; 64]; 2],
     pub see_buffer: Vec<i16>,
     pub search_statistics: SearchStatistics,
-    pub tc: TimeControl, //Only thread 0 takes care of Timecontrol though
+    pub cache_statistics: CacheStatistics,
+    pub params: SearchParams, //Copied from the UCI options when a search starts
+    pub tc: TimeControl,      //Only thread 0 takes care of Timecontrol though
     pub time_saved: u64,
     pub self_stop: bool, //This is set when timeout_stop is set(timeout_stop isn't always polled)
     pub current_pv: ScoredPrincipalVariation,
     pub pv_applicable: Vec<u64>, //Hashes of gamestates the pv plays along
-    pub main_thread_in_depth: bool,
     rx: Receiver<ThreadInstruction>,
     tx: Sender<()>,
 }
@@ -314,22 +403,47 @@ impl Thread {
             pv_table,
             killer_moves: [[None; 2]; MAX_SEARCH_DEPTH],
             quiets_tried: [[None; 128]; MAX_SEARCH_DEPTH],
+            captures_tried: [[None; 128]; MAX_SEARCH_DEPTH],
+            excluded_moves: [None; MAX_SEARCH_DEPTH],
+            current_moves: [None; MAX_SEARCH_DEPTH],
+            counter_moves: [[[None; 64]; 6]; 2],
+            counter_move_history: ContinuationHistory::default(),
+            follow_up_history: ContinuationHistory::default(),
+            capture_history: [[[[0; 5]; 64]; 6]; 2],
             hh_score: [[[0; 64]; 64]; 2],
             bf_score: [[[1; 64]; 64]; 2],
             history_score: [[[0; 64]; 64]; 2],
             see_buffer: vec![0i16; MAX_SEARCH_DEPTH],
             search_statistics: SearchStatistics::default(),
+            cache_statistics: CacheStatistics::default(),
+            params: SearchParams::default(),
             tc: TimeControl::MoveTime(0u64),
             time_saved: 0u64,
             self_stop: false,
             current_pv: ScoredPrincipalVariation::default(),
             pv_applicable: Vec::with_capacity(MAX_SEARCH_DEPTH),
-            main_thread_in_depth: false,
             rx,
             tx,
         }
     }
 
+    //A thread outside of the thread pool, for searching positions in tests
+    #[cfg(test)]
+    pub fn test_thread(hash_size: usize) -> Self {
+        let itcs = Arc::new(InterThreadCommunicationSystem::new());
+        itcs.replace_cache(Cache::with_size(hash_size));
+        itcs.nodes_searched().push(AtomicU64::new(0));
+        itcs.thread_cache_statistics
+            .lock()
+            .unwrap()
+            .push(CacheStatistics::default());
+        let (_, rx) = channel();
+        let (tx, _) = channel();
+        let mut thread = Thread::new(0, itcs, rx, tx);
+        thread.tc = TimeControl::Infinite;
+        thread
+    }
+
     fn run(&mut self) {
         loop {
             let msg: ThreadInstruction = self.rx.recv().unwrap();
@@ -344,12 +458,19 @@ impl Thread {
                     self.time_saved = time_saved;
                     self.pv_applicable.clear();
                     self.current_pv = ScoredPrincipalVariation::default();
-                    self.main_thread_in_depth = false;
                     self.killer_moves = [[None; 2]; MAX_SEARCH_DEPTH];
+                    self.excluded_moves = [None; MAX_SEARCH_DEPTH];
+                    self.current_moves = [None; MAX_SEARCH_DEPTH];
+                    self.counter_moves = [[[None; 64]; 6]; 2];
+                    self.counter_move_history.clear();
+                    self.follow_up_history.clear();
+                    self.capture_history = [[[[0; 5]; 64]; 6]; 2];
                     self.hh_score = [[[0; 64]; 64]; 2];
                     self.bf_score = [[[1; 64]; 64]; 2];
                     self.history_score = [[[0; 64]; 64]; 2];
                     self.search_statistics = SearchStatistics::default();
+                    self.cache_statistics = CacheStatistics::default();
+                    self.params = self.itcs.uci_options().search_params;
                     self.tc = tc;
                     self.self_stop = false;
                     self.search(max_depth, state);
@@ -366,12 +487,15 @@ impl Thread {
                 self.id
             );
         }
+        let mut rng = rand::thread_rng();
         let mut curr_depth = 0;
         let mut previous_score: Option<i16> = None;
         loop {
-            let temp = self.itcs.get_next_depth(curr_depth);
-            curr_depth = temp.0;
-            self.main_thread_in_depth = temp.1;
+            //Every thread does its own iterative deepening. Helper threads randomly skip depths
+            curr_depth += 1;
+            if self.id > 0 && curr_depth > 1 && rng.gen_range(0, HELPER_SKIP_CHANCE) == 0 {
+                curr_depth += 1;
+            }
             if curr_depth as i16 > max_depth {
                 break;
             }
@@ -383,10 +507,10 @@ impl Thread {
                 );
             }
             let mut delta = if previous_score.is_some() {
-                previous_score.unwrap().abs() / 50
+                previous_score.unwrap().abs() / self.params.aspiration_score_divisor
             } else {
                 0
-            } + 14;
+            } + self.params.aspiration_delta;
             let mut alpha = if curr_depth == 1 {
                 -16000
             } else {
@@ -433,12 +557,13 @@ impl Thread {
                         beta += delta;
                     }
                 }
-                delta = (f64::from(delta) * 1.5) as i16;
+                delta = (f64::from(delta) * f64::from(self.params.aspiration_growth) / 100.) as i16;
             }
             previous_score = Some(self.current_pv.score);
             if self.self_stop {
                 break;
             }
+            self.itcs.register_result(self.id, &self.current_pv);
         }
         if self.itcs.uci_options().debug_print {
             println!(
@@ -451,6 +576,7 @@ impl Thread {
             self.id,
             self.search_statistics.nodes_searched,
             self.search_statistics.seldepth,
+            &self.cache_statistics,
         );
         if self.id == 0 {
             *self
@@ -469,10 +595,17 @@ pub fn search_move(
     history: Vec<GameState>,
     tc: TimeControl,
 ) -> Option<i16> {
+    //replace_cache stops us by setting the timeout flag once we hold the lock
+    *itcs.timeout_flag.write().unwrap() = false;
+    let _search = itcs.search_lock.lock().unwrap();
     //1. Prepare itcs (reset things from previous search)
     *itcs.best_pv.lock().unwrap() = ScoredPrincipalVariation::default();
     itcs.stable_pv.store(false, Ordering::Relaxed);
-    *itcs.depth_info.lock().unwrap() = [DepthInformation::UnSearched; MAX_SEARCH_DEPTH];
+    itcs.thread_results
+        .lock()
+        .unwrap()
+        .iter_mut()
+        .for_each(|x| *x = ScoredPrincipalVariation::default());
     itcs.nodes_searched()
         .iter()
         .for_each(|x| x.store(0u64, Ordering::Relaxed));
@@ -480,7 +613,11 @@ pub fn search_move(
     *itcs.start_time.write().unwrap() = Instant::now();
     *itcs.last_cache_status.lock().unwrap() = None;
     itcs.cache_status.store(0, Ordering::Relaxed);
-    *itcs.timeout_flag.write().unwrap() = false;
+    itcs.thread_cache_statistics
+        .lock()
+        .unwrap()
+        .iter_mut()
+        .for_each(|x| *x = CacheStatistics::default());
 
     let time_saved_before = itcs.saved_time.load(Ordering::Relaxed);
     //Step 1. Check how many legal moves there are
@@ -538,7 +675,16 @@ pub fn search_move(
     }
 
     //Step 6. Report to UCI
-    itcs.report_bestmove();
+    let search_cache_statistics = itcs.search_cache_statistics();
+    itcs.cache_statistics
+        .lock()
+        .unwrap()
+        .add(&search_cache_statistics);
+    if itcs.uci_options().hash_stats_print {
+        println!("info String Hash {}", search_cache_statistics);
+    }
+    let best_result = itcs.best_result();
+    itcs.report_bestmove(&best_result);
     //Store new saved time
     let elapsed_time = itcs.get_time_elapsed();
     let new_timesaved: u64 = (time_saved_before as i64
@@ -550,6 +696,93 @@ pub fn search_move(
     .max(0) as u64;
     itcs.saved_time.store(new_timesaved, Ordering::Relaxed);
     //And return
-    let best_score = itcs.best_pv.lock().unwrap().score;
-    Some(best_score)
+    Some(best_result.score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board_representation::game_state::{GameMoveType, PieceType};
+    use crate::search::alphabeta::singular_extension;
+    use crate::search::SearchInstruction;
+
+    fn result(to: u8, depth: usize, score: i16) -> ScoredPrincipalVariation {
+        let mut pv = PrincipalVariation::new(1);
+        pv.pv[0] = Some(GameMove {
+            from: 12,
+            to,
+            move_type: GameMoveType::Quiet,
+            piece_type: PieceType::Pawn,
+        });
+        ScoredPrincipalVariation { score, pv, depth }
+    }
+
+    #[test]
+    fn vote_test() {
+        assert!(vote(&[ScoredPrincipalVariation::default()]).is_none());
+        //Two threads agreeing outvote a single deeper thread
+        let results = [result(20, 10, 30), result(20, 10, 25), result(28, 11, 30)];
+        let best = vote(&results).unwrap();
+        assert_eq!(best.pv.pv[0].unwrap().to, 20);
+        assert_eq!(best.score, 30);
+        //A much better score outweighs the agreement
+        let results = [result(20, 10, 30), result(20, 10, 30), result(28, 10, 100)];
+        assert_eq!(vote(&results).unwrap().pv.pv[0].unwrap().to, 28);
+        //The shortest mate wins regardless of the votes
+        let results = [
+            result(20, 12, 30),
+            result(20, 12, 30),
+            result(28, 9, MATE_SCORE - 5),
+        ];
+        assert_eq!(vote(&results).unwrap().pv.pv[0].unwrap().to, 28);
+        //Unfinished threads don't vote
+        let results = [ScoredPrincipalVariation::default(), result(28, 3, -50)];
+        assert_eq!(vote(&results).unwrap().depth, 3);
+    }
+
+    #[test]
+    fn singular_extension_test() {
+        //Only exd5 doesn't lose to the queen
+        let state = GameState::from_fen("4k3/8/8/3q4/4P3/8/8/4K3 w - - 0 1");
+        let tt_move = GameMove {
+            from: 28,
+            to: 35,
+            move_type: GameMoveType::Capture(PieceType::Queen),
+            piece_type: PieceType::Pawn,
+        };
+        let mut thread = Thread::test_thread(1);
+        let p = CombinedSearchParameters::from(0, 1, 8, &state, 1, 1);
+        thread.itcs.cache().insert(&p, &tt_move, 300, 0, 0, None);
+        thread.history.push(state.hash, false);
+        let mut singular_move = None;
+        let instruction = singular_extension(&p, &mut thread, &tt_move, &mut singular_move);
+        assert!(match instruction {
+            SearchInstruction::ContinueSearching => true,
+            _ => false,
+        });
+        assert_eq!(singular_move, Some(tt_move));
+        assert!(thread.excluded_moves.iter().all(|mv| mv.is_none()));
+        assert_eq!(thread.history.pointer, 1);
+        #[cfg(feature = "search-statistics")]
+        {
+            assert_eq!(thread.search_statistics.singular_extensions, 1);
+            assert_eq!(thread.search_statistics.multi_cuts, 0);
+        }
+    }
+
+    #[test]
+    fn cache_statistics_test() {
+        let mut thread = Thread::test_thread(1);
+        thread.cache_statistics.add_probe(true, false);
+        thread.cache_statistics.add_probe(true, true);
+        //Nothing is reported before the thread publishes its statistics
+        assert_eq!(thread.itcs.search_cache_statistics().probes, 0);
+        thread.itcs.update(0, 8192, 1, &thread.cache_statistics);
+        thread.cache_statistics.add_probe(false, false);
+        thread.itcs.update(0, 16384, 1, &thread.cache_statistics);
+        let statistics = thread.itcs.search_cache_statistics();
+        assert_eq!(statistics.probes, 3);
+        assert_eq!(statistics.hits, 2);
+        assert_eq!(statistics.cutoffs, 1);
+    }
 }
@@ -1,3 +1,4 @@
+use crate::search::cache::{CacheEntry, Replacement, AGE_CYCLE};
 use std::fmt::{Display, Formatter, Result};
 
 pub struct SearchStatistics {
@@ -23,6 +24,10 @@ pub struct SearchStatistics {
     pub iid_nodes: u64,
     pub futil_nodes: u64,
     pub history_pruned: u64,
+    pub singular_extensions: u64,
+    pub multi_cuts: u64,
+    pub razored: u64,
+    pub probcut_pruned: u64,
 }
 
 impl Default for SearchStatistics {
@@ -50,6 +55,10 @@ impl Default for SearchStatistics {
             iid_nodes: 0,
             futil_nodes: 0,
             history_pruned: 0,
+            singular_extensions: 0,
+            multi_cuts: 0,
+            razored: 0,
+            probcut_pruned: 0,
         }
     }
 }
@@ -111,7 +120,11 @@ impl SearchStatistics {
     #[inline(always)]
     pub fn add_q_beta_cutoff(&mut self, index: usize) {
         self.q_beta_cutoffs += 1;
-        self.q_beta_cutoffs_index[index] += 1;
+        if index > 31 {
+            self.q_beta_cutoffs_index[31] += 1;
+        } else {
+            self.q_beta_cutoffs_index[index] += 1;
+        }
     }
     #[inline(always)]
     pub fn add_q_beta_noncutoff(&mut self) {
@@ -143,6 +156,22 @@ impl SearchStatistics {
     pub fn add_nm_pruning(&mut self) {
         self.nm_pruned += 1;
     }
+    #[inline(always)]
+    pub fn add_singular_extension(&mut self) {
+        self.singular_extensions += 1;
+    }
+    #[inline(always)]
+    pub fn add_multi_cut(&mut self) {
+        self.multi_cuts += 1;
+    }
+    #[inline(always)]
+    pub fn add_razoring(&mut self) {
+        self.razored += 1;
+    }
+    #[inline(always)]
+    pub fn add_probcut(&mut self) {
+        self.probcut_pruned += 1;
+    }
 }
 
 impl Display for SearchStatistics {
@@ -218,6 +247,26 @@ impl Display for SearchStatistics {
             self.history_pruned,
             (self.history_pruned as f64 / self.normal_nodes_searched as f64 * 100.0)
         ));
+        res_str.push_str(&format!(
+            "Normal-Search Singular extensions : {} ({}%)\n",
+            self.singular_extensions,
+            (self.singular_extensions as f64 / self.normal_nodes_searched as f64 * 100.0)
+        ));
+        res_str.push_str(&format!(
+            "Normal-Search Multi-Cut-Pruned : {} ({}%)\n",
+            self.multi_cuts,
+            (self.multi_cuts as f64 / self.normal_nodes_searched as f64 * 100.0)
+        ));
+        res_str.push_str(&format!(
+            "Normal-Search Razored : {} ({}%)\n",
+            self.razored,
+            (self.razored as f64 / self.normal_nodes_searched as f64 * 100.0)
+        ));
+        res_str.push_str(&format!(
+            "Normal-Search ProbCut-Pruned : {} ({}%)\n",
+            self.probcut_pruned,
+            (self.probcut_pruned as f64 / self.normal_nodes_searched as f64 * 100.0)
+        ));
 
         res_str.push_str("\n");
         res_str.push_str(&format!(
@@ -252,3 +301,162 @@ impl Display for SearchStatistics {
         write!(formatter, "{}", res_str)
     }
 }
+
+fn percent(part: u64, total: u64) -> f64 {
+    part as f64 / total.max(1) as f64 * 100.0
+}
+
+//Transposition table usage of the search. Unlike the search statistics, these are always collected
+#[derive(Copy, Clone, Default)]
+pub struct CacheStatistics {
+    pub probes: u64,
+    pub hits: u64,
+    pub cutoffs: u64,
+    pub replacements: [u64; 5], //Indexed by Replacement
+}
+
+impl CacheStatistics {
+    #[inline(always)]
+    pub fn add_probe(&mut self, hit: bool, cutoff: bool) {
+        self.probes += 1;
+        if hit {
+            self.hits += 1;
+        }
+        if cutoff {
+            self.cutoffs += 1;
+        }
+    }
+    #[inline(always)]
+    pub fn add_insert(&mut self, replacement: Replacement) {
+        self.replacements[replacement as usize] += 1;
+    }
+    pub fn add(&mut self, other: &CacheStatistics) {
+        self.probes += other.probes;
+        self.hits += other.hits;
+        self.cutoffs += other.cutoffs;
+        for (sum, replacements) in self.replacements.iter_mut().zip(other.replacements.iter()) {
+            *sum += replacements;
+        }
+    }
+}
+
+impl Display for CacheStatistics {
+    fn fmt(&self, formatter: &mut Formatter) -> Result {
+        let inserts: u64 = self.replacements.iter().sum();
+        write!(
+            formatter,
+            "probes {} hits {} ({:.1}%) cutoffs {} ({:.1}%) inserts {} empty {} same {} aged {} lowest {} skipped {}",
+            self.probes,
+            self.hits,
+            percent(self.hits, self.probes),
+            self.cutoffs,
+            percent(self.cutoffs, self.probes),
+            inserts,
+            self.replacements[Replacement::Empty as usize],
+            self.replacements[Replacement::SamePosition as usize],
+            self.replacements[Replacement::Aged as usize],
+            self.replacements[Replacement::LowestScore as usize],
+            self.replacements[Replacement::Skipped as usize],
+        )
+    }
+}
+
+//Content of the whole transposition table, see Cache::table_statistics
+pub struct TableStatistics {
+    pub entries: u64,
+    pub used: u64,
+    pub ages: [u64; AGE_CYCLE], //Plies between the root and the search which last wrote or used the entry
+    pub depths: [u64; 256],     //Indexed by depth + 128
+    pub exact: u64,
+    pub lower_bounds: u64,
+    pub upper_bounds: u64,
+    pub pv_nodes: u64,
+}
+
+impl Default for TableStatistics {
+    fn default() -> Self {
+        TableStatistics {
+            entries: 0,
+            used: 0,
+            ages: [0; AGE_CYCLE],
+            depths: [0; 256],
+            exact: 0,
+            lower_bounds: 0,
+            upper_bounds: 0,
+            pv_nodes: 0,
+        }
+    }
+}
+
+impl TableStatistics {
+    pub fn add_entry(&mut self, entry: &CacheEntry, relative_age: usize) {
+        self.used += 1;
+        self.ages[relative_age] += 1;
+        self.depths[(i16::from(entry.depth) + 128) as usize] += 1;
+        if entry.beta {
+            self.lower_bounds += 1;
+        } else if entry.alpha {
+            self.upper_bounds += 1;
+        } else {
+            self.exact += 1;
+        }
+        if entry.pv_node {
+            self.pv_nodes += 1;
+        }
+    }
+}
+
+impl Display for TableStatistics {
+    fn fmt(&self, formatter: &mut Formatter) -> Result {
+        let mut res_str: String = String::new();
+        res_str.push_str(&format!(
+            "Entries: {}/{} ({:.0} permill)\n",
+            self.used,
+            self.entries,
+            percent(self.used, self.entries) * 10.0
+        ));
+        res_str.push_str("Age (plies before the root):\n");
+        for (age, count) in self
+            .ages
+            .iter()
+            .enumerate()
+            .filter(|(_, count)| **count > 0)
+        {
+            res_str.push_str(&format!(
+                "{:>4}: {} ({:.0} permill)\n",
+                age,
+                count,
+                percent(*count, self.entries) * 10.0
+            ));
+        }
+        res_str.push_str("Depth:\n");
+        for (index, count) in self
+            .depths
+            .iter()
+            .enumerate()
+            .filter(|(_, count)| **count > 0)
+        {
+            res_str.push_str(&format!(
+                "{:>4}: {} ({:.1}%)\n",
+                index as i16 - 128,
+                count,
+                percent(*count, self.used)
+            ));
+        }
+        res_str.push_str(&format!(
+            "Bounds: exact {} ({:.1}%) lower {} ({:.1}%) upper {} ({:.1}%)\n",
+            self.exact,
+            percent(self.exact, self.used),
+            self.lower_bounds,
+            percent(self.lower_bounds, self.used),
+            self.upper_bounds,
+            percent(self.upper_bounds, self.used)
+        ));
+        res_str.push_str(&format!(
+            "PV nodes: {} ({:.1}%)\n",
+            self.pv_nodes,
+            percent(self.pv_nodes, self.used)
+        ));
+        write!(formatter, "{}", res_str)
+    }
+}
@@ -24,6 +24,7 @@ pub enum TimeControl {
     MoveTime(u64),
     Infinite,
     Tournament(u64, u64, usize),
+    Nodes(u64),
 }
 
 impl TimeControl {
@@ -45,6 +46,7 @@ impl TimeControl {
                     format!("btime {} binc {} movestogo {}", time_left, inc, movestogo)
                 }
             }
+            TimeControl::Nodes(nodes) => format!("nodes {}", nodes),
         }
     }
     pub fn update(&mut self, time_spent: u64, tournament_info: Option<(usize, u64)>) {
@@ -66,6 +68,9 @@ impl TimeControl {
                 }
                 *self = TimeControl::Tournament(new_left, *inc, *movestogo);
             }
+            TimeControl::Nodes(nodes) => {
+                *self = TimeControl::Nodes(*nodes);
+            }
         }
     }
     pub fn time_left(&self) -> u64 {
@@ -74,6 +79,7 @@ impl TimeControl {
             TimeControl::MoveTime(left) => *left,
             TimeControl::Infinite => panic!("Should not call time_left on Infinite"),
             TimeControl::Tournament(left, _, _) => *left,
+            TimeControl::Nodes(_) => panic!("Should not call time_left on Nodes"),
         }
     }
     pub fn time_over(
@@ -109,6 +115,9 @@ impl TimeControl {
             return time_spent > move_time - move_overhead || *move_time < move_overhead;
         } else if let TimeControl::Infinite = self {
             return false;
+        } else if let TimeControl::Nodes(_) = self {
+            //Node limit is checked by the search itself
+            return false;
         } else if let TimeControl::Tournament(mytime, myinc, movestogo) = self {
             if time_spent as isize > *mytime as isize - 4 * move_overhead as isize {
                 return true;
@@ -171,6 +180,8 @@ impl TimeControl {
             res_str.push_str(&format!("Limited movetime: {}\n", time));
         } else if let TimeControl::Infinite = self {
             res_str.push_str("Infinite Time!\n");
+        } else if let TimeControl::Nodes(nodes) = self {
+            res_str.push_str(&format!("Limited nodes: {}\n", nodes));
         } else if let TimeControl::Tournament(mytime, myinc, movestogo) = self {
             res_str.push_str(&format!("My Time: {}\n", mytime));
             res_str.push_str(&format!("My Inc: {}\n", myinc));
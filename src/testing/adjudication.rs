@@ -0,0 +1,249 @@
+use crate::board_representation::game_state::{
+    GameResult, GameState, BISHOP, BLACK, KNIGHT, PAWN, QUEEN, ROOK, WHITE,
+};
+use crate::evaluation::endgame::kpk_is_win;
+use crate::testing::EndConditionInformation;
+use serde::{Deserialize, Serialize};
+
+//Score reported for mate announcements, beyond every sensible resign threshold
+pub const MATE_REPORTED_SCORE: isize = 100_000;
+
+//Adjudication rules for selfplay. Setting a moves count to 0 disables the rule.
+//Moves are counted per engine, so resign_moves = 5 means 5 consecutive moves of both engines
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub struct AdjudicationConfig {
+    pub resign_score: isize,
+    pub resign_moves: usize,
+    pub draw_score: isize,
+    pub draw_min_ply: usize,
+    pub draw_moves: usize,
+    pub tablebase: bool,
+}
+
+impl Default for AdjudicationConfig {
+    fn default() -> Self {
+        AdjudicationConfig {
+            resign_score: 1000,
+            resign_moves: 5,
+            draw_score: 10,
+            draw_min_ply: 68,
+            draw_moves: 10,
+            tablebase: false,
+        }
+    }
+}
+
+pub struct Adjudicator {
+    pub config: AdjudicationConfig,
+    //Reported scores of every move from white's point of view
+    white_scores: Vec<Option<isize>>,
+}
+
+impl Adjudicator {
+    pub fn new(config: AdjudicationConfig) -> Self {
+        Adjudicator {
+            config,
+            white_scores: Vec::with_capacity(100),
+        }
+    }
+
+    //Score is from the point of view of the engine which made the move
+    pub fn add_score(&mut self, score: Option<isize>, white_moved: bool) {
+        self.white_scores
+            .push(score.map(|score| if white_moved { score } else { -score }));
+    }
+
+    fn last_scores(&self, moves: usize) -> Option<Vec<isize>> {
+        if moves == 0 || self.white_scores.len() < 2 * moves {
+            return None;
+        }
+        self.white_scores[self.white_scores.len() - 2 * moves..]
+            .iter()
+            .cloned()
+            .collect()
+    }
+
+    //Has to be called after the scores of the move leading to state have been added
+    pub fn adjudicate(&self, state: &GameState) -> Option<(GameResult, EndConditionInformation)> {
+        //Step 1. Tablebase adjudication
+        if self.config.tablebase {
+            if let Some(result) = probe_tablebase(state) {
+                let endcondition = if result == GameResult::Draw {
+                    EndConditionInformation::DrawByTablebase
+                } else {
+                    EndConditionInformation::WinByTablebase
+                };
+                return Some((result, endcondition));
+            }
+        }
+        //Step 2. Resign adjudication, both engines agree on the winner
+        if let Some(scores) = self.last_scores(self.config.resign_moves) {
+            if scores
+                .iter()
+                .all(|score| *score >= self.config.resign_score)
+            {
+                return Some((
+                    GameResult::WhiteWin,
+                    EndConditionInformation::MateByadjudication,
+                ));
+            } else if scores
+                .iter()
+                .all(|score| *score <= -self.config.resign_score)
+            {
+                return Some((
+                    GameResult::BlackWin,
+                    EndConditionInformation::MateByadjudication,
+                ));
+            }
+        }
+        //Step 3. Draw adjudication
+        let ply = 2 * (state.full_moves - 1) + state.color_to_move;
+        if ply >= self.config.draw_min_ply {
+            if let Some(scores) = self.last_scores(self.config.draw_moves) {
+                if scores
+                    .iter()
+                    .all(|score| score.abs() <= self.config.draw_score)
+                {
+                    return Some((
+                        GameResult::Draw,
+                        EndConditionInformation::DrawByadjudication,
+                    ));
+                }
+            }
+        }
+        None
+    }
+}
+
+//Known results of positions with very few pieces: king and pawn vs king by the bitbase and
+//pawnless positions where no side has more than a minor piece (or two knights)
+pub fn probe_tablebase(state: &GameState) -> Option<GameResult> {
+    let (white, black) = (count_material(state, WHITE), count_material(state, BLACK));
+    let pawns = state.pieces[PAWN][WHITE] | state.pieces[PAWN][BLACK];
+    let heavy = state.pieces[ROOK][WHITE]
+        | state.pieces[ROOK][BLACK]
+        | state.pieces[QUEEN][WHITE]
+        | state.pieces[QUEEN][BLACK];
+    if heavy != 0 {
+        return None;
+    }
+    if pawns == 0 {
+        let minors = |side: (u32, u32, u32)| side.1 + side.2;
+        let draw = match (minors(white), minors(black)) {
+            (0, 0) | (0, 1) | (1, 0) | (1, 1) => true,
+            (2, 0) => white.2 == 0,
+            (0, 2) => black.2 == 0,
+            _ => false,
+        };
+        return if draw { Some(GameResult::Draw) } else { None };
+    }
+    for &(strong, strong_material, weak_material) in
+        [(WHITE, white, black), (BLACK, black, white)].iter()
+    {
+        if strong_material == (1, 0, 0) && weak_material == (0, 0, 0) {
+            return Some(if !kpk_is_win(state, strong) {
+                GameResult::Draw
+            } else if strong == WHITE {
+                GameResult::WhiteWin
+            } else {
+                GameResult::BlackWin
+            });
+        }
+    }
+    None
+}
+
+//Pawns, knights and bishops of one side
+fn count_material(state: &GameState, side: usize) -> (u32, u32, u32) {
+    (
+        state.pieces[PAWN][side].count_ones(),
+        state.pieces[KNIGHT][side].count_ones(),
+        state.pieces[BISHOP][side].count_ones(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn score_adjudication_test() {
+        let config = AdjudicationConfig {
+            resign_score: 500,
+            resign_moves: 2,
+            draw_score: 10,
+            draw_min_ply: 10,
+            draw_moves: 2,
+            tablebase: false,
+        };
+        let state = GameState::from_fen("r3k3/8/8/8/8/8/8/4K3 w - - 0 20");
+        let mut adjudicator = Adjudicator::new(config);
+        adjudicator.add_score(Some(600), true);
+        adjudicator.add_score(Some(-700), false);
+        adjudicator.add_score(Some(800), true);
+        assert!(adjudicator.adjudicate(&state).is_none());
+        //The losing engine disagrees
+        adjudicator.add_score(Some(-100), false);
+        assert!(adjudicator.adjudicate(&state).is_none());
+        adjudicator.add_score(Some(800), true);
+        adjudicator.add_score(Some(-MATE_REPORTED_SCORE), false);
+        adjudicator.add_score(Some(900), true);
+        assert!(adjudicator.adjudicate(&state).is_none());
+        adjudicator.add_score(Some(-600), false);
+        let (result, endcondition) = adjudicator.adjudicate(&state).unwrap();
+        assert_eq!(result, GameResult::WhiteWin);
+        assert!(matches!(
+            endcondition,
+            EndConditionInformation::MateByadjudication
+        ));
+
+        let mut adjudicator = Adjudicator::new(config);
+        for _ in 0..4 {
+            adjudicator.add_score(Some(-5), false);
+        }
+        let (result, _) = adjudicator.adjudicate(&state).unwrap();
+        assert_eq!(result, GameResult::Draw);
+        //Not before draw_min_ply
+        let early = GameState::from_fen("r3k3/8/8/8/8/8/8/4K3 w - - 0 3");
+        assert!(adjudicator.adjudicate(&early).is_none());
+        //Missing scores reset the adjudication
+        adjudicator.add_score(None, true);
+        assert!(adjudicator.adjudicate(&state).is_none());
+    }
+
+    #[test]
+    fn tablebase_adjudication_test() {
+        assert_eq!(
+            probe_tablebase(&GameState::from_fen("3k4/8/3K4/3P4/8/8/8/8 b - - 0 1")),
+            Some(GameResult::WhiteWin)
+        );
+        assert_eq!(
+            probe_tablebase(&GameState::from_fen("k7/8/8/8/P7/8/8/K7 w - - 0 1")),
+            Some(GameResult::Draw)
+        );
+        assert_eq!(
+            probe_tablebase(&GameState::from_fen("8/8/8/8/4p3/4k3/8/4K3 w - - 0 1")),
+            Some(GameResult::BlackWin)
+        );
+        assert_eq!(
+            probe_tablebase(&GameState::from_fen("8/8/3k4/3b4/8/8/3K1N2/8 w - - 0 1")),
+            Some(GameResult::Draw)
+        );
+        assert_eq!(
+            probe_tablebase(&GameState::from_fen("8/8/3k4/8/8/8/3KNN2/8 w - - 0 1")),
+            Some(GameResult::Draw)
+        );
+        assert_eq!(
+            probe_tablebase(&GameState::from_fen("8/8/3k4/8/8/8/3KBN2/8 w - - 0 1")),
+            None
+        );
+        assert_eq!(
+            probe_tablebase(&GameState::from_fen("8/8/3k4/8/8/8/3K1R2/8 w - - 0 1")),
+            None
+        );
+        assert_eq!(
+            probe_tablebase(&GameState::from_fen("8/8/3k4/8/3p4/8/3K1P2/8 w - - 0 1")),
+            None
+        );
+    }
+}
@@ -1,7 +1,9 @@
 use crate::logging::Logger;
 use crate::testing::queue::ThreadSafeString;
+use crate::testing::transcript::{Direction, Transcript};
 use std::io::BufReader;
 use std::sync::Arc;
+use std::thread;
 use std::time::{Duration, Instant};
 use tokio::prelude::*;
 
@@ -20,9 +22,16 @@ pub fn expect_output(
     time_frame: u64,
     output: tokio_process::ChildStdout,
     runtime: &mut tokio::runtime::Runtime,
+    transcript: Option<Transcript>,
 ) -> (Option<String>, Option<tokio_process::ChildStdout>, usize) {
     let lines_codec = tokio::codec::LinesCodec::new();
+    let transcript_moved = transcript.clone();
     let line_fut = tokio::codec::FramedRead::new(output, lines_codec)
+        .inspect(move |line| {
+            if let Some(transcript) = transcript_moved.as_ref() {
+                transcript.record(Direction::FromEngine, line);
+            }
+        })
         .filter(move |lines| lines.starts_with(&starts_with[..]))
         .into_future()
         .timeout(Duration::from_millis(time_frame));
@@ -32,8 +41,18 @@ pub fn expect_output(
     let dur = after.duration_since(before).as_millis() as usize;
     match result {
         Ok(s) => match s.0 {
-            Some(str) => (Some(str), Some(s.1.into_inner().into_inner()), dur),
-            _ => (None, None, dur),
+            Some(str) => (
+                Some(str),
+                Some(s.1.into_inner().into_inner().into_inner()),
+                dur,
+            ),
+            _ => {
+                //The stream ended, the engine closed its output
+                if let Some(transcript) = transcript {
+                    transcript.close_stdout();
+                }
+                (None, None, dur)
+            }
         },
         Err(_) => (None, None, dur),
     }
@@ -45,6 +64,7 @@ pub fn expect_output_and_listen_for_info(
     output: tokio_process::ChildStdout,
     runtime: &mut tokio::runtime::Runtime,
     info: String,
+    transcript: Option<Transcript>,
 ) -> (
     Option<String>,
     Option<tokio_process::ChildStdout>,
@@ -54,8 +74,12 @@ pub fn expect_output_and_listen_for_info(
     let info_listener = Arc::new(ThreadSafeString::default());
     let info_listener_moved = info_listener.clone();
     let lines_codec = tokio::codec::LinesCodec::new();
+    let transcript_moved = transcript.clone();
     let line_fut = tokio::codec::FramedRead::new(output, lines_codec)
         .inspect(move |line| {
+            if let Some(transcript) = transcript_moved.as_ref() {
+                transcript.record(Direction::FromEngine, line);
+            }
             if line.starts_with(&info) {
                 //println!("{}", line);
                 info_listener_moved.push(&format!("{} ", line));
@@ -76,24 +100,82 @@ pub fn expect_output_and_listen_for_info(
                 dur,
                 info_listener.get_inner(),
             ),
-            _ => (None, None, dur, info_listener.get_inner()),
+            _ => {
+                if let Some(transcript) = transcript {
+                    transcript.close_stdout();
+                }
+                (None, None, dur, info_listener.get_inner())
+            }
         },
         Err(_) => (None, None, dur, info_listener.get_inner()),
     }
 }
 
-pub fn write_stderr_to_log(
-    error_log: Arc<Logger>,
+//Reads stderr for the whole game, so every line is recorded when it arrives
+pub fn listen_to_stderr(
     stderr: tokio_process::ChildStderr,
     runtime: &mut tokio::runtime::Runtime,
+    transcript: Transcript,
 ) {
-    error_log.log("StdERR of child: \n", true);
+    let transcript_moved = transcript.clone();
     let line_fut = tokio::io::lines(BufReader::new(stderr))
-        .inspect(move |s| error_log.log(&format!("{}\n", s), true))
-        .collect()
-        .timeout(Duration::from_millis(100));
-    let result = runtime.block_on(line_fut);
-    match result {
-        _ => {}
-    };
+        .for_each(move |line| {
+            transcript_moved.record(Direction::Stderr, &line);
+            Ok(())
+        })
+        .then(move |_| {
+            transcript.close_stderr();
+            Ok(())
+        });
+    runtime.spawn(line_fut);
+}
+
+pub fn write_stderr_to_log(error_log: Arc<Logger>, transcript: &Transcript) {
+    //A crashing engine may still be writing
+    let start = Instant::now();
+    while !transcript.stderr_closed() && start.elapsed() < Duration::from_millis(100) {
+        thread::sleep(Duration::from_millis(5));
+    }
+    error_log.log("StdERR of child: \n", true);
+    for line in transcript.lines() {
+        if line.direction == Direction::Stderr {
+            error_log.log(&format!("[{:>8}ms] {}\n", line.time, line.line), true);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::{Command, Stdio};
+    use tokio_process::CommandExt;
+
+    #[cfg(unix)]
+    #[test]
+    fn stderr_test() {
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        let mut process = Command::new("sh")
+            .args(&["-c", "echo early >&2; sleep 0.3; echo late >&2"])
+            .stderr(Stdio::piped())
+            .spawn_async()
+            .unwrap();
+        let transcript = Transcript::default();
+        listen_to_stderr(
+            process.stderr().take().unwrap(),
+            &mut runtime,
+            transcript.clone(),
+        );
+        let start = Instant::now();
+        while !transcript.stderr_closed() && start.elapsed() < Duration::from_secs(5) {
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert!(transcript.stderr_closed());
+        //Each line keeps the time it arrived at
+        let lines = transcript.lines();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].line, "early");
+        assert_eq!(lines[1].line, "late");
+        assert_eq!(lines[1].direction, Direction::Stderr);
+        assert!(lines[1].time >= lines[0].time + 250);
+    }
 }
@@ -0,0 +1,216 @@
+use serde::{Deserialize, Serialize};
+
+//Extra milliseconds an engine gets on fixed movetime before it is disqualified
+pub const MOVETIME_TOLERANCE: u64 = 200;
+//Milliseconds an engine gets for a move on fixed nodes or fixed depth
+pub const FIXED_LIMIT_TIMEOUT: u64 = 600_000;
+
+//Times are in milliseconds
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum TimeControlConfig {
+    Incremental { time: u64, inc: u64 },
+    //The base time is added again after every period of `moves` moves
+    Tournament { time: u64, inc: u64, moves: usize },
+    MoveTime(u64),
+    Nodes(u64),
+    Depth(usize),
+}
+
+impl TimeControlConfig {
+    //Scales times and node limits, e.g. odds of 0.5 give half the time of the normal time control
+    pub fn with_odds(self, odds: f64) -> Self {
+        let scale = |value: u64| (value as f64 * odds).round() as u64;
+        match self {
+            TimeControlConfig::Incremental { time, inc } => TimeControlConfig::Incremental {
+                time: scale(time),
+                inc: scale(inc),
+            },
+            TimeControlConfig::Tournament { time, inc, moves } => TimeControlConfig::Tournament {
+                time: scale(time),
+                inc: scale(inc),
+                moves,
+            },
+            TimeControlConfig::MoveTime(time) => TimeControlConfig::MoveTime(scale(time)),
+            TimeControlConfig::Nodes(nodes) => TimeControlConfig::Nodes(scale(nodes)),
+            TimeControlConfig::Depth(depth) => TimeControlConfig::Depth(depth),
+        }
+    }
+
+    //Value of the PGN TimeControl tag, times are in seconds
+    pub fn pgn_tag(&self) -> String {
+        let seconds = |ms: u64| {
+            if ms % 1000 == 0 {
+                format!("{}", ms / 1000)
+            } else {
+                format!("{}", ms as f64 / 1000.)
+            }
+        };
+        match self {
+            TimeControlConfig::Incremental { time, inc } => {
+                format!("{}+{}", seconds(*time), seconds(*inc))
+            }
+            TimeControlConfig::Tournament { time, inc, moves } => {
+                if *inc == 0 {
+                    format!("{}/{}", moves, seconds(*time))
+                } else {
+                    format!("{}/{}+{}", moves, seconds(*time), seconds(*inc))
+                }
+            }
+            TimeControlConfig::MoveTime(time) => format!("1/{}", seconds(*time)),
+            TimeControlConfig::Nodes(_) | TimeControlConfig::Depth(_) => "-".to_owned(),
+        }
+    }
+}
+
+//The clock of an engine during a game
+#[derive(Clone, Copy)]
+pub struct Clock {
+    pub config: TimeControlConfig,
+    pub time_left: u64,
+    pub moves_to_go: usize,
+}
+
+impl Clock {
+    pub fn new(config: TimeControlConfig) -> Self {
+        let (time_left, moves_to_go) = match config {
+            TimeControlConfig::Incremental { time, .. } => (time, 0),
+            TimeControlConfig::Tournament { time, moves, .. } => (time, moves),
+            _ => (0, 0),
+        };
+        Clock {
+            config,
+            time_left,
+            moves_to_go,
+        }
+    }
+
+    pub fn is_clock_based(&self) -> bool {
+        matches!(
+            self.config,
+            TimeControlConfig::Incremental { .. } | TimeControlConfig::Tournament { .. }
+        )
+    }
+
+    fn clock_to_go(&self, white: bool) -> String {
+        let inc = match self.config {
+            TimeControlConfig::Incremental { inc, .. }
+            | TimeControlConfig::Tournament { inc, .. } => inc,
+            _ => 0,
+        };
+        if white {
+            format!("wtime {} winc {}", self.time_left, inc)
+        } else {
+            format!("btime {} binc {}", self.time_left, inc)
+        }
+    }
+
+    pub fn to_go(&self, white: bool) -> String {
+        match self.config {
+            TimeControlConfig::Incremental { .. } => self.clock_to_go(white),
+            TimeControlConfig::Tournament { .. } => {
+                format!("{} movestogo {}", self.clock_to_go(white), self.moves_to_go)
+            }
+            TimeControlConfig::MoveTime(time) => format!("movetime {}", time),
+            TimeControlConfig::Nodes(nodes) => format!("nodes {}", nodes),
+            TimeControlConfig::Depth(depth) => format!("depth {}", depth),
+        }
+    }
+
+    //Milliseconds the engine may take for its move
+    pub fn timeout(&self) -> u64 {
+        match self.config {
+            TimeControlConfig::Incremental { .. } | TimeControlConfig::Tournament { .. } => {
+                self.time_left
+            }
+            TimeControlConfig::MoveTime(time) => time + MOVETIME_TOLERANCE,
+            TimeControlConfig::Nodes(_) | TimeControlConfig::Depth(_) => FIXED_LIMIT_TIMEOUT,
+        }
+    }
+
+    pub fn update(&mut self, time_spent: u64) {
+        match self.config {
+            TimeControlConfig::Incremental { inc, .. } => {
+                assert!(self.time_left > time_spent);
+                self.time_left = self.time_left - time_spent + inc;
+            }
+            TimeControlConfig::Tournament { time, inc, moves } => {
+                assert!(self.time_left > time_spent);
+                self.time_left = self.time_left - time_spent + inc;
+                self.moves_to_go -= 1;
+                if self.moves_to_go == 0 {
+                    self.time_left += time;
+                    self.moves_to_go = moves;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+//Go command for the engine to move. The opponent's clock is only sent if it has one
+pub fn go_command(own: &Clock, opponent: &Clock, white: bool) -> String {
+    if own.is_clock_based() && opponent.is_clock_based() {
+        format!("go {} {}\n", own.to_go(white), opponent.clock_to_go(!white))
+    } else {
+        format!("go {}\n", own.to_go(white))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clock_test() {
+        let mut tournament = Clock::new(TimeControlConfig::Tournament {
+            time: 10000,
+            inc: 100,
+            moves: 2,
+        });
+        let incremental = Clock::new(TimeControlConfig::Incremental {
+            time: 5000,
+            inc: 50,
+        });
+        assert_eq!(
+            go_command(&tournament, &incremental, true),
+            "go wtime 10000 winc 100 movestogo 2 btime 5000 binc 50\n"
+        );
+        tournament.update(1000);
+        assert_eq!(tournament.time_left, 9100);
+        assert_eq!(tournament.moves_to_go, 1);
+        //New period
+        tournament.update(100);
+        assert_eq!(tournament.time_left, 19100);
+        assert_eq!(tournament.moves_to_go, 2);
+
+        let nodes = Clock::new(TimeControlConfig::Nodes(10000));
+        assert_eq!(go_command(&nodes, &incremental, false), "go nodes 10000\n");
+        assert_eq!(
+            go_command(&incremental, &nodes, false),
+            "go btime 5000 binc 50\n"
+        );
+        assert_eq!(nodes.timeout(), FIXED_LIMIT_TIMEOUT);
+    }
+
+    #[test]
+    fn odds_and_pgn_tag_test() {
+        let tc = TimeControlConfig::Incremental {
+            time: 10000,
+            inc: 100,
+        };
+        assert_eq!(tc.pgn_tag(), "10+0.1");
+        assert_eq!(tc.with_odds(0.5).pgn_tag(), "5+0.05");
+        let tournament = TimeControlConfig::Tournament {
+            time: 60000,
+            inc: 0,
+            moves: 40,
+        };
+        assert_eq!(tournament.pgn_tag(), "40/60");
+        assert_eq!(TimeControlConfig::MoveTime(500).pgn_tag(), "1/0.5");
+        assert_eq!(TimeControlConfig::Depth(8).with_odds(0.5).pgn_tag(), "-");
+        assert_eq!(
+            TimeControlConfig::Nodes(1000).with_odds(2.),
+            TimeControlConfig::Nodes(2000)
+        );
+    }
+}
@@ -0,0 +1,248 @@
+use crate::board_representation::game_state::{GameMove, GameState};
+use crate::board_representation::game_state_attack_container::GameStateAttackContainer;
+use crate::move_generation::movegen;
+use crate::pgn::pgn_reader::parse_move;
+use std::fs;
+
+//A position of an EPD test suite with its operations
+pub struct EpdPosition {
+    pub fen: String,
+    pub state: GameState,
+    pub id: Option<String>,
+    //bm: The position is solved by one of these moves
+    pub best_moves: Vec<GameMove>,
+    //am: The position is solved by any move except these
+    pub avoid_moves: Vec<GameMove>,
+    //dm: Mate in this many moves
+    pub direct_mate: Option<usize>,
+    //c0 to c9
+    pub comments: [Option<String>; 10],
+    //STS style points from c0, e.g. "f5=10, Be5+=2"
+    pub awarded_moves: Vec<(GameMove, usize)>,
+}
+
+impl EpdPosition {
+    pub fn is_solution(&self, mv: &GameMove) -> bool {
+        (self.best_moves.is_empty() || self.best_moves.contains(mv))
+            && !self.avoid_moves.contains(mv)
+    }
+
+    //A mate reported by the engine solves a dm position if it isn't longer than the given one
+    pub fn is_mate_solution(&self, mate: Option<isize>) -> bool {
+        match (self.direct_mate, mate) {
+            (Some(direct_mate), Some(mate)) => mate > 0 && mate as usize <= direct_mate,
+            _ => false,
+        }
+    }
+
+    //The best move, or the first move of the principal variation, and the mate the engine reported
+    pub fn solved_by(&self, mv: &GameMove, mate: Option<isize>) -> bool {
+        self.is_solution(mv) && (self.direct_mate.is_none() || self.is_mate_solution(mate))
+    }
+
+    pub fn points(&self, mv: &GameMove) -> usize {
+        self.awarded_moves
+            .iter()
+            .find(|awarded| awarded.0 == *mv)
+            .map_or(0, |awarded| awarded.1)
+    }
+
+    pub fn max_points(&self) -> usize {
+        self.awarded_moves
+            .iter()
+            .map(|awarded| awarded.1)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+pub fn load_epd(path: &str) -> Vec<EpdPosition> {
+    let content = fs::read_to_string(path).expect("Unable to read EPD file");
+    let res: Vec<EpdPosition> = content.lines().filter_map(parse_epd).collect();
+    println!("Loaded {} positions from {}!", res.len(), path);
+    res
+}
+
+//Splits the operations at semicolons which aren't inside a string
+fn split_operations(operations: &str) -> Vec<String> {
+    let mut res = Vec::new();
+    let mut current = String::new();
+    let mut in_string = false;
+    for c in operations.chars() {
+        match c {
+            '"' => {
+                in_string = !in_string;
+                current.push(c);
+            }
+            ';' if !in_string => {
+                res.push(current.trim().to_owned());
+                current.clear();
+            }
+            _ => current.push(c),
+        }
+    }
+    if !current.trim().is_empty() {
+        res.push(current.trim().to_owned());
+    }
+    res.retain(|operation| !operation.is_empty());
+    res
+}
+
+//Parses "FEN opcode operands; opcode operands; ..." with the four FEN fields of EPD. Empty lines are skipped
+pub fn parse_epd(line: &str) -> Option<EpdPosition> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    let mut fields = line.splitn(5, char::is_whitespace);
+    let fen_fields: Vec<&str> = (0..4).filter_map(|_| fields.next()).collect();
+    if fen_fields.len() < 4 {
+        panic!("Invalid EPD line: {}", line);
+    }
+    let fen = format!("{} 0 1", fen_fields.join(" "));
+    let state = GameState::from_fen(&fen);
+    let mut movelist = movegen::MoveList::default();
+    let mut attack_container = GameStateAttackContainer::default();
+    attack_container.write_state(&state);
+    let mut parse = |mv: &str| parse_move(&state, mv, &mut movelist, &attack_container).0;
+
+    let mut id = None;
+    let mut best_moves = Vec::new();
+    let mut avoid_moves = Vec::new();
+    let mut direct_mate = None;
+    let mut comments: [Option<String>; 10] = Default::default();
+    for operation in split_operations(fields.next().unwrap_or("")) {
+        let (opcode, operand) = match operation.find(char::is_whitespace) {
+            Some(index) => (&operation[..index], operation[index..].trim()),
+            None => (&operation[..], ""),
+        };
+        let operand_string = operand.trim_matches('"').to_owned();
+        match opcode {
+            "bm" => best_moves.extend(operand.split_whitespace().map(&mut parse)),
+            "am" => avoid_moves.extend(operand.split_whitespace().map(&mut parse)),
+            "id" => id = Some(operand_string),
+            "dm" => {
+                direct_mate = Some(
+                    operand
+                        .parse::<usize>()
+                        .unwrap_or_else(|_| panic!("Invalid dm operand in EPD line: {}", line)),
+                )
+            }
+            _ => {
+                if opcode.len() == 2 && opcode.starts_with('c') {
+                    if let Some(index) = opcode[1..].parse::<usize>().ok().filter(|i| *i < 10) {
+                        comments[index] = Some(operand_string);
+                    }
+                }
+            }
+        }
+    }
+    //c0 only awards points if every part is "move=points"
+    let mut awarded_moves = Vec::new();
+    if let Some(comment) = comments[0].as_ref() {
+        let parts: Vec<(&str, Option<usize>)> = comment
+            .split(',')
+            .map(|part| match part.rfind('=') {
+                Some(index) => (part[..index].trim(), part[index + 1..].trim().parse().ok()),
+                None => (part, None),
+            })
+            .collect();
+        if parts.iter().all(|part| part.1.is_some()) {
+            for (mv, points) in parts {
+                awarded_moves.push((parse(mv), points.unwrap()));
+            }
+        }
+    }
+    Some(EpdPosition {
+        fen,
+        state,
+        id,
+        best_moves,
+        avoid_moves,
+        direct_mate,
+        comments,
+        awarded_moves,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uci(mv: &GameMove) -> String {
+        format!("{:?}", mv)
+    }
+
+    #[test]
+    fn epd_test() {
+        let sts = parse_epd("1kr5/3n4/q3p2p/p2n2p1/PppB1P2/5BP1/1P2Q2P/3R2K1 w - - bm f5; id \"Undermine.001\"; c0 \"f5=10, Be5+=2, Bf2=3, Bg4=2\";").unwrap();
+        assert_eq!(sts.id.as_deref(), Some("Undermine.001"));
+        assert_eq!(sts.best_moves.len(), 1);
+        assert_eq!(uci(&sts.best_moves[0]), "f4f5");
+        assert_eq!(sts.awarded_moves.len(), 4);
+        assert_eq!(sts.points(&sts.awarded_moves[1].0), 2);
+        assert_eq!(uci(&sts.awarded_moves[1].0), "d4e5");
+        assert_eq!(sts.max_points(), 10);
+        assert!(sts.is_solution(&sts.best_moves[0]));
+        assert!(!sts.is_solution(&sts.awarded_moves[1].0));
+
+        //A comment with a semicolon inside and no points
+        let lct2 = parse_epd("r3kb1r/3n1pp1/p6p/2pPp2q/Pp2N3/3B2PP/1PQ2P2/R3K2R w KQkq - bm d6; id \"POS-01\";c0 \"Chernin - Miles; Tunis 1985, 1.d6!\";").unwrap();
+        assert_eq!(lct2.id.as_deref(), Some("POS-01"));
+        assert_eq!(
+            lct2.comments[0].as_deref(),
+            Some("Chernin - Miles; Tunis 1985, 1.d6!")
+        );
+        assert!(lct2.awarded_moves.is_empty());
+
+        let avoid =
+            parse_epd("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - am f3 g4; c9 \"x\"")
+                .unwrap();
+        assert_eq!(avoid.avoid_moves.len(), 2);
+        assert_eq!(avoid.comments[9].as_deref(), Some("x"));
+        assert!(avoid.is_solution(
+            &parse_epd("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - bm e4;")
+                .unwrap()
+                .best_moves[0]
+        ));
+        assert!(!avoid.is_solution(&avoid.avoid_moves[1]));
+
+        let mate = parse_epd("6k1/5ppp/8/8/8/8/8/R5K1 w - - dm 1; id \"mate\";").unwrap();
+        assert_eq!(mate.direct_mate, Some(1));
+        assert!(mate.is_mate_solution(Some(1)));
+        assert!(!mate.is_mate_solution(Some(2)));
+        assert!(!mate.is_mate_solution(None));
+        assert!(!mate.solved_by(&avoid.avoid_moves[0], None));
+        assert!(parse_epd("").is_none());
+    }
+
+    #[test]
+    fn load_suites_test() {
+        let lct2 = load_epd("testsuites/lct2.epd");
+        assert_eq!(lct2.len(), 35);
+        assert!(lct2.iter().all(|position| position.best_moves.len() == 1));
+        for index in 1..=15 {
+            let sts = load_epd(&format!("testsuites/sts{}.epd", index));
+            assert_eq!(sts.len(), 100);
+            //Some of the suites come without awarded moves
+            assert!(sts.iter().all(|position| position.best_moves.len() == 1
+                && (position.awarded_moves.is_empty()
+                    || position.max_points() == 10
+                        && position.points(&position.best_moves[0]) == 10)));
+        }
+        //Everything else in the directory is a suite as well
+        for entry in fs::read_dir("testsuites").unwrap() {
+            let path = entry.unwrap().path();
+            if path.ends_with("sts_results.txt") {
+                continue;
+            }
+            assert_eq!(path.extension().unwrap(), "epd");
+            let suite = load_epd(&path.to_string_lossy());
+            assert!(!suite.is_empty());
+            assert_eq!(
+                suite.len(),
+                fs::read_to_string(&path).unwrap().lines().count()
+            );
+        }
+    }
+}
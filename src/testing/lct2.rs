@@ -1,135 +1,27 @@
-use crate::write_to_buf;
-use core::board_representation::game_state::{GameMove, GameState};
-use core::board_representation::game_state_attack_container::GameStateAttackContainer;
-use core::move_generation::movegen;
-use core::pgn::pgn_reader::parse_move;
-use core::testing::queue::ThreadSafeQueue;
-use std::fs::File;
-use std::io::prelude::*;
-use std::io::{BufReader, BufWriter};
-use std::process::{Command, Stdio};
-use std::str::FromStr;
-use std::sync::atomic::AtomicUsize;
-use std::sync::atomic::Ordering;
-use std::sync::Arc;
-use std::thread;
-use std::time::{Duration, Instant};
+use crate::suit::{print_summary, run_suite};
+use core::testing::epd::load_epd;
+use core::testing::EngineConfig;
 
-pub fn start_lct2_thread(
-    queue: Arc<ThreadSafeQueue<Lct2Test>>,
-    p1: String,
-    points: Arc<AtomicUsize>,
-    thread_name: &str,
-) {
-    let mut child = Command::new(&p1.to_string())
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .spawn()
-        .expect("Failed to execute command!");
-    let mut child_in = BufWriter::new(child.stdin.as_mut().unwrap());
-    let mut child_out = BufReader::new(child.stdout.as_mut().unwrap());
-    let mut line = String::new();
-    while let Some(test) = queue.pop() {
-        println!(
-            "{}",
-            &format!(
-                "\n\n-----------------------------\nThread {} is looking at FEN: {}",
-                thread_name,
-                test.game_state.to_fen()
-            )
-        );
-        println!("Bestmove is actually: {:?}", test.optimal_move);
-        let str = format!(
-            "ucinewgame\nposition fen {}\n go movetime 600000\n",
-            test.game_state.to_fen()
-        );
-        write_to_buf(&mut child_in, &str);
-        let before = Instant::now();
-        let bm;
-        loop {
-            line.clear();
-            child_out.read_line(&mut line).unwrap();
-            let cmd: Vec<&str> = line.split(' ').collect();
-            if cmd[0] == "bestmove" {
-                bm = cmd[1].trim();
-                break;
-            } else {
-                //Find mv
-                let mut index = 0;
-                while index < cmd.len() {
-                    if cmd[index] == "depth" {
-                        break;
-                    }
-                    index += 1;
-                }
-                if index >= cmd.len() {
-                    continue;
-                }
-                let depth = cmd[index + 1].parse::<usize>().unwrap();
-                if depth <= 7 {
-                    continue;
-                }
-                index = 0;
-                while index < cmd.len() {
-                    if cmd[index] == "pv" {
-                        break;
-                    }
-                    index += 1;
-                }
-                if index < cmd.len() && *cmd[index + 1].trim() == format!("{:?}", test.optimal_move)
-                {
-                    write_to_buf(&mut child_in, "stop\n");
-                }
-            }
-        }
-        let now = Instant::now();
-        let dur = now.duration_since(before).as_millis();
-        println!(
-            "{}",
-            &format!(
-                "\n\n-----------------------------\nThread {} has looked at FEN: {}",
-                thread_name,
-                test.game_state.to_fen()
-            )
-        );
-        println!("Bestmove is actually: {:?}", test.optimal_move);
-        println!("Best move found after {} seconds", dur as f64 / 1000.0);
-        println!("Best move found was {}", bm);
-        if *bm == format!("{:?}", test.optimal_move) {
-            let award = award_points(dur);
-            let newpoints = points.load(Ordering::SeqCst) + award;
-            (*points).store(newpoints, Ordering::SeqCst);
-            println!("Best move is equal! Awarded Points: {}", award);
-            println!("New Points: {}", newpoints);
-        } else {
-            println!("Best move is wrong!")
-        }
-        write_to_buf(&mut child_in, "stop\nucinewgame\n");
-        thread::sleep(Duration::from_millis(100));
-    }
-
-    write_to_buf(&mut child_in, "quit\n");
-}
+//Every position is searched for at most 10 minutes and the search is stopped once the solution is found at depth 8
+const LCT2_MOVETIME: u64 = 600_000;
+const LCT2_STOP_DEPTH: usize = 8;
 
-pub fn lct2(p1: &str, processors: usize, path_to_lct2: &str) {
-    //Step 1: Parse suit
-    let mypoints = Arc::new(AtomicUsize::new(1900));
-    let suit = load_lct2suit(path_to_lct2);
-    //Step 2: Prepare Arcs
-    let queue = Arc::new(ThreadSafeQueue::new(suit));
-    let mut childs = Vec::with_capacity(processors);
-    for i in 0..processors {
-        let queue_clone = queue.clone();
-        let points_clone = mypoints.clone();
-        let name = String::from_str(p1).unwrap();
-        childs.push(thread::spawn(move || {
-            start_lct2_thread(queue_clone, name, points_clone, &format!("{}", i))
-        }))
+pub fn lct2(p1: &EngineConfig, processors: usize, path_to_lct2: &str) {
+    let results = run_suite(
+        p1,
+        processors,
+        load_epd(path_to_lct2),
+        LCT2_MOVETIME,
+        Some(LCT2_STOP_DEPTH),
+    );
+    let mut points = 1900;
+    for result in results.iter() {
+        let award = result.time_to_solution.map_or(0, award_points);
+        points += award;
+        println!("{}\tpoints {}", result, award);
     }
-    for child in childs {
-        child.join().expect("Couldn't join thread");
-    }
-    println!("LCT2 Finished! Points: {}", mypoints.load(Ordering::SeqCst));
+    print_summary(&results);
+    println!("LCT2 Finished! Points: {}", points);
 }
 
 fn award_points(dur: u128) -> usize {
@@ -148,35 +40,3 @@ fn award_points(dur: u128) -> usize {
     }
     0usize
 }
-
-fn load_lct2suit(path_to_lct2: &str) -> Vec<Lct2Test> {
-    let mut movelist = movegen::MoveList::default();
-    let mut attack_container = GameStateAttackContainer::default();
-    let mut res = Vec::with_capacity(30);
-    let mut file: File = File::open(path_to_lct2).expect("Unable to open file");
-    let mut contents = String::new();
-    file.read_to_string(&mut contents)
-        .expect("Unable to read the file");
-    let split = contents.split('\n');
-    for line in split {
-        let linevec = line.split("bm").collect::<Vec<&str>>();
-        if linevec.len() == 1 {
-            break;
-        }
-        let state = GameState::from_fen(linevec[0].trim_end());
-        let mv = linevec[1].trim().split(' ').collect::<Vec<&str>>()[0].replace(";", "");
-        attack_container.write_state(&state);
-        let (optimal_move, _) =
-            parse_move(&state, &mv.to_string(), &mut movelist, &attack_container);
-        res.push(Lct2Test {
-            game_state: state,
-            optimal_move,
-        });
-    }
-    res
-}
-
-pub struct Lct2Test {
-    game_state: GameState,
-    optimal_move: GameMove,
-}
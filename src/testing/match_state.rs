@@ -0,0 +1,365 @@
+use crate::board_representation::game_state::{GameMove, GameState};
+use crate::logging::Logger;
+use crate::misc::STD_FEN;
+use crate::testing::openings::replay_moves;
+use crate::testing::report::GameRecord;
+use crate::testing::transcript::merge_transcripts;
+use crate::testing::{Engine, EngineStats, PlayTask, TaskResult};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+pub const TASKS_FILE: &str = "tasks.jsonl";
+pub const RESULTS_FILE: &str = "results.jsonl";
+pub const PGN_FILE: &str = "pgns.pgn";
+pub const ERROR_LOG_FILE: &str = "referee_error_log.txt";
+pub const REPORT_FILE: &str = "match_report.json";
+pub const TRANSCRIPT_DIRECTORY: &str = "transcripts";
+
+//A scheduled game, so that a resumed match plays exactly the same games
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TaskRecord {
+    pub id: usize,
+    #[serde(default = "std_fen")]
+    pub start_fen: String,
+    //Moves in uci notation from the start position
+    pub opening_sequence: Vec<String>,
+    pub p1_is_white: bool,
+    pub engine1: usize,
+    pub engine2: usize,
+}
+
+fn std_fen() -> String {
+    STD_FEN.to_owned()
+}
+
+impl TaskRecord {
+    pub fn new(task: &PlayTask) -> Self {
+        TaskRecord {
+            id: task.id,
+            start_fen: task.start_fen.clone(),
+            opening_sequence: task
+                .opening_sequence
+                .iter()
+                .map(|mv| format!("{:?}", mv))
+                .collect(),
+            p1_is_white: task.p1_is_white,
+            engine1: task.engine1.id,
+            engine2: task.engine2.id,
+        }
+    }
+
+    //Rebuilds the opening position and its move sequence
+    pub fn opening(&self) -> (GameState, Vec<GameMove>) {
+        replay_moves(&self.start_fen, &self.opening_sequence).unwrap_or_else(|| {
+            panic!(
+                "Illegal move in stored opening {:?} from {}",
+                self.opening_sequence, self.start_fen
+            )
+        })
+    }
+}
+
+//Results and statistics of one engine in a finished game
+#[derive(Serialize, Deserialize, Clone)]
+pub struct EngineRecord {
+    pub id: usize,
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+    pub disqs: usize,
+    #[serde(default)]
+    pub time_losses: usize,
+    pub moves_played: usize,
+    pub avg_depth: f64,
+    pub avg_nps: f64,
+    pub avg_timeleft: f64,
+}
+
+impl EngineRecord {
+    pub fn new(engine: &Engine) -> Self {
+        EngineRecord {
+            id: engine.id,
+            wins: engine.wins,
+            draws: engine.draws,
+            losses: engine.losses,
+            disqs: engine.disqs,
+            time_losses: engine.time_losses,
+            moves_played: engine.stats.moves_played,
+            avg_depth: engine.stats.avg_depth,
+            avg_nps: engine.stats.avg_nps,
+            avg_timeleft: engine.stats.avg_timeleft,
+        }
+    }
+
+    //Adds the stored game to the results of engine
+    pub fn add_to(&self, engine: &mut Engine) {
+        let mut game = engine.clone_fresh();
+        game.wins = self.wins;
+        game.draws = self.draws;
+        game.losses = self.losses;
+        game.disqs = self.disqs;
+        game.time_losses = self.time_losses;
+        game.stats = EngineStats {
+            moves_played: self.moves_played,
+            avg_depth: self.avg_depth,
+            avg_nps: self.avg_nps,
+            avg_timeleft: self.avg_timeleft,
+        };
+        engine.add(&game);
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ResultRecord {
+    pub id: usize,
+    pub engine1: EngineRecord,
+    pub engine2: EngineRecord,
+    pub engine1_score: f64,
+    #[serde(default)]
+    pub game: Option<GameRecord>,
+}
+
+impl ResultRecord {
+    pub fn new(result: &TaskResult) -> Self {
+        ResultRecord {
+            id: result.task.id,
+            engine1: EngineRecord::new(&result.task.engine1),
+            engine2: EngineRecord::new(&result.task.engine2),
+            engine1_score: result.engine1_score(),
+            game: Some(GameRecord::new(result)),
+        }
+    }
+}
+
+//Directory holding the schedule, the finished games and the pgns of a match.
+//Starting the referee again with the same directory resumes the match
+pub struct MatchDirectory {
+    pub path: PathBuf,
+    pub tasks: HashMap<usize, TaskRecord>,
+    pub results: HashMap<usize, ResultRecord>,
+    task_log: Logger,
+    result_log: Logger,
+}
+
+impl MatchDirectory {
+    pub fn open(path: &str) -> Self {
+        fs::create_dir_all(path).expect("Unable to create match directory!");
+        let path = PathBuf::from(path);
+        let tasks: HashMap<usize, TaskRecord> = load_records(&path.join(TASKS_FILE))
+            .into_iter()
+            .map(|task: TaskRecord| (task.id, task))
+            .collect();
+        let results: HashMap<usize, ResultRecord> = load_records(&path.join(RESULTS_FILE))
+            .into_iter()
+            .map(|result: ResultRecord| (result.id, result))
+            .collect();
+        if !tasks.is_empty() {
+            println!(
+                "Resuming match in {}: {} of {} games already played",
+                path.display(),
+                results.len(),
+                tasks.len()
+            );
+        }
+        let task_log = Logger::new(&path.join(TASKS_FILE).to_string_lossy(), true);
+        let result_log = Logger::new(&path.join(RESULTS_FILE).to_string_lossy(), true);
+        MatchDirectory {
+            path,
+            tasks,
+            results,
+            task_log,
+            result_log,
+        }
+    }
+
+    pub fn file(&self, name: &str) -> String {
+        self.path.join(name).to_string_lossy().into_owned()
+    }
+
+    //Returns the stored task with the same id, or stores the new one
+    pub fn schedule(&mut self, mut task: PlayTask) -> PlayTask {
+        if let Some(record) = self.tasks.get(&task.id) {
+            if record.engine1 != task.engine1.id || record.engine2 != task.engine2.id {
+                panic!(
+                    "Game {} in match directory {} is between other engines, use a new directory for a new match!",
+                    task.id,
+                    self.path.display()
+                );
+            }
+            let (opening, opening_sequence) = record.opening();
+            task.start_fen = record.start_fen.clone();
+            task.opening = opening;
+            task.opening_sequence = opening_sequence;
+            task.p1_is_white = record.p1_is_white;
+        } else {
+            let record = TaskRecord::new(&task);
+            self.task_log.log(
+                &format!("{}\n", serde_json::to_string(&record).unwrap()),
+                false,
+            );
+            self.tasks.insert(task.id, record);
+        }
+        task
+    }
+
+    pub fn save_result(&mut self, result: &TaskResult) {
+        let record = ResultRecord::new(result);
+        self.result_log.log(
+            &format!("{}\n", serde_json::to_string(&record).unwrap()),
+            false,
+        );
+        self.results.insert(record.id, record);
+    }
+}
+
+//Error log and pgn file, inside the match directory if there is one
+pub fn open_logs(directory: Option<&MatchDirectory>) -> (Logger, Logger) {
+    match directory {
+        Some(directory) => (
+            Logger::new(&directory.file(ERROR_LOG_FILE), true),
+            Logger::new(&directory.file(PGN_FILE), true),
+        ),
+        None => (
+            Logger::new(ERROR_LOG_FILE, false),
+            Logger::new(PGN_FILE, true),
+        ),
+    }
+}
+
+//Path of the match report, inside the match directory if there is one
+pub fn report_path(directory: Option<&MatchDirectory>) -> String {
+    match directory {
+        Some(directory) => directory.file(REPORT_FILE),
+        None => REPORT_FILE.to_owned(),
+    }
+}
+
+//Writes the communication with both engines of a game in which an engine was disqualified
+//to the transcript directory next to the pgn file
+pub fn save_transcript(result: &TaskResult, directory: Option<&MatchDirectory>) {
+    let failure = match result.failure {
+        Some(failure) => failure,
+        None => return,
+    };
+    let transcripts = match directory {
+        Some(directory) => directory.path.join(TRANSCRIPT_DIRECTORY),
+        None => PathBuf::from(TRANSCRIPT_DIRECTORY),
+    };
+    fs::create_dir_all(&transcripts).expect("Unable to create transcript directory!");
+    let path = transcripts.join(format!("game_{}.txt", result.task.id));
+    let (white, black) = if result.task.p1_is_white {
+        (&result.task.engine1, &result.task.engine2)
+    } else {
+        (&result.task.engine2, &result.task.engine1)
+    };
+    let content = format!(
+        "Game {}: {} (white) vs {} (black), {} disqualified: {}\n{}",
+        result.task.id,
+        white.name,
+        black.name,
+        if white.disqs > 0 { "white" } else { "black" },
+        failure,
+        merge_transcripts(&[("white", &white.transcript), ("black", &black.transcript),])
+    );
+    fs::write(&path, content).expect("Unable to write transcript!");
+    println!(
+        "Wrote transcript of game {} to {}",
+        result.task.id,
+        path.display()
+    );
+}
+
+//A line which isn't complete because the referee was interrupted while writing it is skipped
+fn load_records<T: for<'de> Deserialize<'de>>(path: &PathBuf) -> Vec<T> {
+    match fs::read_to_string(path) {
+        Ok(content) => content
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::clock::TimeControlConfig;
+    use crate::testing::EngineConfig;
+
+    fn engine(id: usize) -> Engine {
+        Engine {
+            name: format!("Engine{}", id),
+            config: EngineConfig::from_path("engine"),
+            id,
+            wins: 0,
+            draws: 0,
+            losses: 0,
+            disqs: 0,
+            time_losses: 0,
+            time_control: crate::testing::clock::Clock::new(TimeControlConfig::MoveTime(100)),
+            stats: EngineStats::default(),
+            transcript: Default::default(),
+        }
+    }
+
+    #[test]
+    fn resume_test() {
+        let directory = std::env::temp_dir().join(format!("fabchess_match_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        let directory = directory.to_string_lossy().into_owned();
+        let opening = TaskRecord {
+            id: 0,
+            start_fen: STD_FEN.to_owned(),
+            opening_sequence: vec!["e2e4".to_owned(), "e7e5".to_owned(), "e1e2".to_owned()],
+            p1_is_white: true,
+            engine1: 0,
+            engine2: 1,
+        }
+        .opening();
+        let task = |id: usize, p1_is_white: bool| PlayTask {
+            start_fen: STD_FEN.to_owned(),
+            opening: opening.0.clone(),
+            opening_sequence: opening.1.clone(),
+            p1_is_white,
+            id,
+            pair_id: id / 2,
+            engine1: engine(0),
+            engine2: engine(1),
+        };
+
+        let mut match_directory = MatchDirectory::open(&directory);
+        let scheduled = match_directory.schedule(task(0, true));
+        assert_eq!(scheduled.opening.hash, opening.0.hash);
+        match_directory.schedule(task(1, false));
+        let mut finished = task(0, true);
+        finished.engine1.wins = 1;
+        finished.engine2.losses = 1;
+        match_directory.save_result(&TaskResult {
+            task: finished,
+            endcondition: None,
+            move_sequence: Vec::new(),
+            final_status: crate::board_representation::game_state::GameResult::WhiteWin,
+            failure: None,
+        });
+
+        //Resume with a different opening, the stored one is played
+        let mut resumed = MatchDirectory::open(&directory);
+        assert_eq!(resumed.tasks.len(), 2);
+        assert_eq!(resumed.results.len(), 1);
+        assert_eq!(resumed.results[&0].engine1_score, 1.);
+        let mut other = task(1, true);
+        other.opening = GameState::from_fen(STD_FEN);
+        other.opening_sequence = Vec::new();
+        let other = resumed.schedule(other);
+        assert!(!other.p1_is_white);
+        assert_eq!(other.opening.hash, opening.0.hash);
+        assert_eq!(other.opening_sequence.len(), 3);
+
+        let mut gauntlet = engine(0);
+        resumed.results[&0].engine1.add_to(&mut gauntlet);
+        assert_eq!(gauntlet.wins, 1);
+        let _ = fs::remove_dir_all(&directory);
+    }
+}
@@ -1,29 +1,35 @@
-use crate::search::timecontrol::TimeControl;
-
+pub mod adjudication;
 pub mod async_communication;
+pub mod clock;
+pub mod epd;
+pub mod match_state;
 pub mod openings;
 pub mod queue;
+pub mod report;
+pub mod sprt;
+pub mod spsa;
+pub mod tournament;
+pub mod transcript;
 
 use crate::board_representation::game_state::*;
 use crate::logging::Logger;
 use crate::move_generation::movegen::MoveList;
 use crate::testing::async_communication::{
-    expect_output, expect_output_and_listen_for_info, print_command, write_stderr_to_log,
+    expect_output, expect_output_and_listen_for_info, listen_to_stderr, print_command,
+    write_stderr_to_log,
 };
+use crate::testing::clock::{Clock, TimeControlConfig};
+use crate::testing::transcript::{Direction, EngineFailure, Transcript};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fmt::{Display, Formatter, Result};
+use std::path::Path;
 use std::process::{Command, Stdio};
 use std::sync::Arc;
 use tokio_process::{Child, ChildStderr, ChildStdin, ChildStdout, CommandExt};
 pub enum EngineReaction<T> {
     ContinueGame(T),
-    DisqualifyEngine,
-}
-pub enum EngineStatus {
-    ProclaimsWin,
-    ProclaimsLoss,
-    ProclaimsDraw,
-    ProclaimsNothing,
+    DisqualifyEngine(EngineFailure),
 }
 #[derive(Clone, Copy)]
 pub enum EndConditionInformation {
@@ -34,6 +40,21 @@ pub enum EndConditionInformation {
     StaleMate,
     Mate,
     MateByadjudication,
+    DrawByTablebase,
+    WinByTablebase,
+}
+
+impl EndConditionInformation {
+    //Value of the PGN Termination tag
+    pub fn termination(self) -> &'static str {
+        match self {
+            EndConditionInformation::DrawByadjudication => "adjudication: draw by score",
+            EndConditionInformation::MateByadjudication => "adjudication: resign by score",
+            EndConditionInformation::DrawByTablebase => "adjudication: tablebase draw",
+            EndConditionInformation::WinByTablebase => "adjudication: tablebase win",
+            _ => "normal",
+        }
+    }
 }
 
 impl Display for EndConditionInformation {
@@ -47,6 +68,8 @@ impl Display for EndConditionInformation {
             EndConditionInformation::StaleMate => "Draw by Stalemate",
             EndConditionInformation::Mate => "Win by Mate",
             EndConditionInformation::MateByadjudication => "Win by adjudication",
+            EndConditionInformation::DrawByTablebase => "Draw by tablebase adjudication",
+            EndConditionInformation::WinByTablebase => "Win by tablebase adjudication",
         });
         write!(formatter, "{}", res_str)
     }
@@ -90,18 +113,100 @@ impl Default for EngineStats {
     }
 }
 
+//Milliseconds an engine gets to answer uci and isready after startup
+pub const DEFAULT_INIT_TIMEOUT: u64 = 10000;
+//Milliseconds an engine gets to answer isready during the game
+pub const ISREADY_TIMEOUT: u64 = 10000;
+
+fn default_init_timeout() -> u64 {
+    DEFAULT_INIT_TIMEOUT
+}
+
+fn default_time_odds() -> f64 {
+    1.
+}
+
+//Everything needed to start an engine process. Relative paths are relative to the referee's working directory
+#[derive(Serialize, Deserialize, Clone)]
+pub struct EngineConfig {
+    pub path: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub working_directory: Option<String>,
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    #[serde(default)]
+    pub uci_options: HashMap<String, String>,
+    #[serde(default = "default_init_timeout")]
+    pub init_timeout: u64,
+    //Overrides the time control of the mode
+    #[serde(default)]
+    pub time_control: Option<TimeControlConfig>,
+    #[serde(default = "default_time_odds")]
+    pub time_odds: f64,
+}
+
+impl EngineConfig {
+    pub fn from_path(path: &str) -> Self {
+        EngineConfig {
+            path: path.to_owned(),
+            args: Vec::new(),
+            working_directory: None,
+            env: HashMap::new(),
+            uci_options: HashMap::new(),
+            init_timeout: DEFAULT_INIT_TIMEOUT,
+            time_control: None,
+            time_odds: 1.,
+        }
+    }
+
+    //The same config works on every platform: "./engine" falls back to "./engine.exe" on windows.
+    //Relative paths are made absolute, since the engine might run in another working directory
+    pub fn executable(&self) -> String {
+        let mut path = self.path.clone();
+        if cfg!(windows)
+            && !Path::new(&path).exists()
+            && Path::new(&format!("{}.exe", path)).exists()
+        {
+            path.push_str(".exe");
+        }
+        if self.working_directory.is_some()
+            && Path::new(&path).is_relative()
+            && Path::new(&path).exists()
+        {
+            if let Ok(absolute) = Path::new(&path).canonicalize() {
+                path = absolute.to_string_lossy().into_owned();
+            }
+        }
+        path
+    }
+
+    pub fn command(&self) -> Command {
+        let mut command = Command::new(self.executable());
+        command.args(&self.args).envs(&self.env);
+        if let Some(directory) = &self.working_directory {
+            command.current_dir(directory);
+        }
+        command
+    }
+}
+
 #[derive(Clone)]
 pub struct Engine {
     pub name: String,
-    pub path: String,
+    pub config: EngineConfig,
     pub id: usize,
     pub wins: usize,
     pub draws: usize,
     pub losses: usize,
     pub disqs: usize,
-    pub time_control: TimeControl,
+    //Disqualifications because the engine didn't send bestmove in time
+    pub time_losses: usize,
+    pub time_control: Clock,
     pub stats: EngineStats,
-    pub uci_options: HashMap<String, String>,
+    //Communication with the engine in the current game
+    pub transcript: Transcript,
 }
 
 impl Engine {
@@ -118,12 +223,30 @@ impl Engine {
         self.draws += other.draws;
         self.losses += other.losses;
         self.disqs += other.disqs;
+        self.time_losses += other.time_losses;
     }
-    pub fn get_elo_gain(&self) -> (String, String, f64) {
+    //Copy of the engine without any results or statistics, used for scheduling new games
+    pub fn clone_fresh(&self) -> Self {
+        Engine {
+            name: self.name.clone(),
+            config: self.config.clone(),
+            id: self.id,
+            wins: 0,
+            draws: 0,
+            losses: 0,
+            disqs: 0,
+            time_losses: 0,
+            time_control: Clock::new(self.time_control.config),
+            stats: EngineStats::default(),
+            transcript: Transcript::default(),
+        }
+    }
+    //Elo gain and its error margin (95% confidence)
+    pub fn elo(&self) -> (f64, f64) {
         //Derived from 1. E_A= 1/(1+10^(-DeltaElo/400)) and 2. |X/N-p|<=1.96*sqrt(N*p*(1-p))/n
         let n: f64 = (self.wins + self.draws + self.losses) as f64;
         let x_a: f64 = self.wins as f64 + self.draws as f64 / 2.0;
-        let (elo_gain, elo_bounds) = if n >= 1. || x_a >= 0. {
+        if n >= 1. {
             let p_a: f64 = x_a / n;
             let k: f64 = (1.96 * 1.96 + 2.0 * x_a) / (-1.0 * 1.96 * 1.96 - n);
             let q = -1.0 * x_a * x_a / (n * (-1.96 * 1.96 - n));
@@ -133,7 +256,10 @@ impl Engine {
             (curr, get_elo_gain(p_a_upper) - curr)
         } else {
             (0., 0.)
-        };
+        }
+    }
+    pub fn get_elo_gain(&self) -> (String, String, f64) {
+        let (elo_gain, elo_bounds) = self.elo();
         (
             format!(
                 "{}\t\t{:.2}   +/- {:.2}   +{}   ={}   -{}  sc {:.1}%",
@@ -158,39 +284,40 @@ impl Engine {
         )
     }
 
-    pub fn from_path(
-        path: &str,
-        id: usize,
-        tc: TimeControl,
-        options: HashMap<String, String>,
-    ) -> Self {
+    pub fn from_config(config: EngineConfig, id: usize, default_tc: TimeControlConfig) -> Self {
+        let tc = config
+            .time_control
+            .unwrap_or(default_tc)
+            .with_odds(config.time_odds);
         let mut res = Engine {
             name: "".to_owned(),
-            path: path.to_string(),
+            config,
             id,
             wins: 0,
             draws: 0,
             losses: 0,
             disqs: 0,
-            time_control: tc,
+            time_losses: 0,
+            time_control: Clock::new(tc),
             stats: EngineStats::default(),
-            uci_options: options,
+            transcript: Transcript::default(),
         };
         let (_child, input, output, _err) = res.get_handles();
         let mut runtime = tokio::runtime::Runtime::new().expect("Could not create tokio runtime!");
         let _input = print_command(&mut runtime, input, "uci\n".to_owned());
         let output = expect_output_and_listen_for_info(
             "uciok".to_owned(),
-            10000,
+            res.config.init_timeout,
             output,
             &mut runtime,
             "id name".to_owned(),
+            None,
         );
         if output.3.contains("id name") {
             let name = output.3.replace("id name ", "");
             res.name = name[..name.len() - 1].to_owned();
         } else {
-            panic!("Couldn't catch the name of engine {}", res.path);
+            panic!("Couldn't catch the name of engine {}", res.config.path);
         }
         res
     }
@@ -201,7 +328,6 @@ impl Engine {
         go_string: String,
         mut stdin: ChildStdin,
         mut stdout: ChildStdout,
-        mut stderr: ChildStderr,
         mut error_log: Arc<Logger>,
         runtime: &mut tokio::runtime::Runtime,
         task_id: usize,
@@ -210,29 +336,36 @@ impl Engine {
         GameMove,
         ChildStdin,
         ChildStdout,
-        ChildStderr,
         Arc<Logger>,
-        EngineStatus,
+        Option<isize>,
     )> {
-        stdin = print_command(runtime, stdin, position_description);
-        let reaction =
-            self.valid_isready_reaction(stdin, stdout, stderr, runtime, task_id, error_log);
+        stdin = self.send(runtime, stdin, position_description);
+        let reaction = self.valid_isready_reaction(
+            stdin,
+            stdout,
+            runtime,
+            task_id,
+            error_log,
+            ISREADY_TIMEOUT,
+        );
         match reaction {
-            EngineReaction::DisqualifyEngine => return EngineReaction::DisqualifyEngine,
+            EngineReaction::DisqualifyEngine(failure) => {
+                return EngineReaction::DisqualifyEngine(failure)
+            }
             EngineReaction::ContinueGame(temp) => {
                 stdin = temp.0;
                 stdout = temp.1;
-                stderr = temp.2;
-                error_log = temp.3;
+                error_log = temp.2;
             }
         }
-        stdin = print_command(runtime, stdin, go_string);
+        stdin = self.send(runtime, stdin, go_string);
         let output = expect_output_and_listen_for_info(
             "bestmove".to_owned(),
-            self.time_control.time_left(),
+            self.time_control.timeout(),
             stdout,
             runtime,
             "info".to_owned(),
+            Some(self.transcript.clone()),
         );
         if output.0.is_none() {
             error_log.log(
@@ -240,19 +373,23 @@ impl Engine {
                     "Engine {} didn't send bestmove in time in game {}! It had {}ms left!\n",
                     self.name,
                     task_id,
-                    self.time_control.time_left(),
+                    self.time_control.timeout(),
                 ),
                 true,
             );
-            write_stderr_to_log(error_log, stderr, runtime);
-            return EngineReaction::DisqualifyEngine;
+            let failure = self.failure(EngineFailure::Timeout);
+            if failure == EngineFailure::Timeout {
+                self.time_losses += 1;
+            }
+            write_stderr_to_log(error_log, &self.transcript);
+            return EngineReaction::DisqualifyEngine(failure);
         }
         stdout = output.1.unwrap();
-        if output.2 as u64 > self.time_control.time_left() {
-            error_log.log(&format!("Mistake in Referee! Bestmove found but it took longer than time still left ({}) for engine {}! Disqualifying engine illegitimately in game {}\n",self.time_control.time_left(),self.name ,task_id), true);
-            return EngineReaction::DisqualifyEngine;
+        if output.2 as u64 > self.time_control.timeout() {
+            error_log.log(&format!("Mistake in Referee! Bestmove found but it took longer than time still left ({}) for engine {}! Disqualifying engine illegitimately in game {}\n",self.time_control.timeout(),self.name ,task_id), true);
+            return EngineReaction::DisqualifyEngine(EngineFailure::Timeout);
         }
-        self.time_control.update(output.2 as u64, None);
+        self.time_control.update(output.2 as u64);
 
         //Parse the move
         let line = output.0.unwrap();
@@ -268,8 +405,8 @@ impl Engine {
                     ),
                     true,
                 );
-                write_stderr_to_log(error_log, stderr, runtime);
-                return EngineReaction::DisqualifyEngine;
+                write_stderr_to_log(error_log, &self.transcript);
+                return EngineReaction::DisqualifyEngine(EngineFailure::IllegalMove);
             }
             found_move.unwrap()
         } else {
@@ -277,29 +414,13 @@ impl Engine {
                     "Bestmove wasn't first argument after bestmove keyword! Disqualifiying engine {} in game {}\n",
                     self.name,task_id
                 ), true);
-            write_stderr_to_log(error_log, stderr, runtime);
-            return EngineReaction::DisqualifyEngine;
+            write_stderr_to_log(error_log, &self.transcript);
+            return EngineReaction::DisqualifyEngine(EngineFailure::ProtocolViolation);
         };
 
         //Get additional info about engine e.g. how deep it saw, nps, and its evaluation
-        let mut status = EngineStatus::ProclaimsNothing;
         self.stats.moves_played += 1;
         let info = fetch_info(output.3.clone());
-        if info.negative_mate_found {
-            status = EngineStatus::ProclaimsLoss;
-        } else if info.positive_mate_found {
-            status = EngineStatus::ProclaimsWin;
-        } else if info.cp_score.is_some() {
-            let score = info.cp_score.unwrap();
-            if score.abs() <= 10 {
-                status = EngineStatus::ProclaimsDraw;
-            }
-            if score < -1000 {
-                status = EngineStatus::ProclaimsLoss;
-            } else if score > 1000 {
-                status = EngineStatus::ProclaimsWin
-            }
-        }
 
         if let Some(dep) = info.depth {
             self.stats.avg_depth += dep as f64;
@@ -308,68 +429,115 @@ impl Engine {
             self.stats.avg_nps += nps as f64;
         }
 
-        EngineReaction::ContinueGame((game_move, stdin, stdout, stderr, error_log, status))
+        EngineReaction::ContinueGame((game_move, stdin, stdout, error_log, info.score()))
     }
 
     pub fn valid_isready_reaction(
         &self,
         stdin: ChildStdin,
         stdout: ChildStdout,
-        stderr: ChildStderr,
         runtime: &mut tokio::runtime::Runtime,
         task_id: usize,
         error_log: Arc<Logger>,
-    ) -> EngineReaction<(ChildStdin, ChildStdout, ChildStderr, Arc<Logger>)> {
-        let stdin = print_command(runtime, stdin, "isready\n".to_owned());
-        let output = expect_output("readyok".to_owned(), 10000, stdout, runtime);
+        timeout: u64,
+    ) -> EngineReaction<(ChildStdin, ChildStdout, Arc<Logger>)> {
+        let stdin = self.send(runtime, stdin, "isready\n".to_owned());
+        let output = expect_output(
+            "readyok".to_owned(),
+            timeout,
+            stdout,
+            runtime,
+            Some(self.transcript.clone()),
+        );
         if output.0.is_none() {
             error_log.log(
                 &format!("Engine {} didn't readyok in game {}!\n", self.name, task_id),
                 true,
             );
-            write_stderr_to_log(error_log, stderr, runtime);
-            return EngineReaction::DisqualifyEngine;
+            let failure = self.failure(EngineFailure::Timeout);
+            write_stderr_to_log(error_log, &self.transcript);
+            return EngineReaction::DisqualifyEngine(failure);
         }
         let stdout = output.1.unwrap();
-        EngineReaction::ContinueGame((stdin, stdout, stderr, error_log))
+        EngineReaction::ContinueGame((stdin, stdout, error_log))
     }
     pub fn valid_uci_isready_reaction(
         &self,
         stdin: ChildStdin,
         stdout: ChildStdout,
-        stderr: ChildStderr,
         runtime: &mut tokio::runtime::Runtime,
         task_id: usize,
         error_log: Arc<Logger>,
-    ) -> EngineReaction<(ChildStdin, ChildStdout, ChildStderr, Arc<Logger>)> {
-        let mut stdin = print_command(runtime, stdin, "uci\n".to_owned());
-        let output = expect_output("uciok".to_owned(), 10000, stdout, runtime);
+    ) -> EngineReaction<(ChildStdin, ChildStdout, Arc<Logger>)> {
+        let mut stdin = self.send(runtime, stdin, "uci\n".to_owned());
+        let output = expect_output(
+            "uciok".to_owned(),
+            self.config.init_timeout,
+            stdout,
+            runtime,
+            Some(self.transcript.clone()),
+        );
         if output.0.is_none() {
             error_log.log(
                 &format!("Engine {} didn't uciok in game {}!\n", self.name, task_id),
                 true,
             );
-            write_stderr_to_log(error_log, stderr, runtime);
-            return EngineReaction::DisqualifyEngine;
+            let failure = self.failure(EngineFailure::Timeout);
+            write_stderr_to_log(error_log, &self.transcript);
+            return EngineReaction::DisqualifyEngine(failure);
         }
         let stdout = output.1.unwrap();
-        for pair in &self.uci_options {
-            stdin = print_command(
+        for pair in &self.config.uci_options {
+            stdin = self.send(
                 runtime,
                 stdin,
                 format!("setoption name {} value {}\n", pair.0, pair.1),
             );
         }
-        self.valid_isready_reaction(stdin, stdout, stderr, runtime, task_id, error_log)
+        self.valid_isready_reaction(
+            stdin,
+            stdout,
+            runtime,
+            task_id,
+            error_log,
+            self.config.init_timeout,
+        )
+    }
+
+    //Records everything the engine writes to stderr in the transcript until the process ends
+    pub fn record_stderr(&self, runtime: &mut tokio::runtime::Runtime, stderr: ChildStderr) {
+        listen_to_stderr(stderr, runtime, self.transcript.clone());
+    }
+
+    //Writes the command to the engine and records it in the transcript
+    pub fn send(
+        &self,
+        runtime: &mut tokio::runtime::Runtime,
+        stdin: ChildStdin,
+        command: String,
+    ) -> ChildStdin {
+        self.transcript.record(Direction::ToEngine, &command);
+        print_command(runtime, stdin, command)
+    }
+
+    //A missing answer is a crash if the engine closed its output
+    fn failure(&self, failure: EngineFailure) -> EngineFailure {
+        if self.transcript.stdout_closed() {
+            EngineFailure::Crash
+        } else {
+            failure
+        }
     }
 
     pub fn get_handles(&self) -> (Child, ChildStdin, ChildStdout, ChildStderr) {
-        let mut process = Command::new(self.path.clone())
+        let mut process = self
+            .config
+            .command()
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
             .spawn_async()
-            .unwrap_or_else(|_| panic!("Failed to start engine {}!", self.path));
+            .unwrap_or_else(|_| panic!("Failed to start engine {}!", self.config.path));
         let input = process.stdin().take().unwrap();
         let output = process.stdout().take().unwrap();
         let stderr = process.stderr().take().unwrap();
@@ -408,6 +576,9 @@ pub fn fetch_info(info: String) -> UCIInfo {
     let split_line: Vec<&str> = info.split_whitespace().collect();
     let mut depth = None;
     let mut nps = None;
+    let mut nodes = None;
+    let mut mate = None;
+    let mut pv_move = None;
     let mut cp_score = None;
     let mut positive_mate_found = false;
     let mut negative_mate_found = false;
@@ -426,11 +597,20 @@ pub fn fetch_info(info: String) -> UCIInfo {
                 nps = split_line[index + 1].parse::<usize>().ok();
                 index += 1;
             }
+            "nodes" => {
+                nodes = split_line[index + 1].parse::<u64>().ok();
+                index += 1;
+            }
+            "pv" => {
+                pv_move = split_line.get(index + 1).map(|mv| (*mv).to_owned());
+                index += 1;
+            }
             "mate" => {
                 let mate_score = match split_line[index + 1].parse::<isize>() {
                     Ok(s) => s,
                     _ => 0,
                 };
+                mate = Some(mate_score);
                 if mate_score < 0 {
                     negative_mate_found = true;
                 } else if mate_score > 0 {
@@ -444,6 +624,9 @@ pub fn fetch_info(info: String) -> UCIInfo {
     UCIInfo {
         depth,
         nps,
+        nodes,
+        mate,
+        pv_move,
         cp_score,
         positive_mate_found,
         negative_mate_found,
@@ -451,18 +634,40 @@ pub fn fetch_info(info: String) -> UCIInfo {
 }
 
 pub struct UCIInfo {
-    depth: Option<usize>,
-    nps: Option<usize>,
-    cp_score: Option<isize>,
-    positive_mate_found: bool,
-    negative_mate_found: bool,
+    pub depth: Option<usize>,
+    pub nps: Option<usize>,
+    pub nodes: Option<u64>,
+    //Moves to mate, negative if the engine gets mated
+    pub mate: Option<isize>,
+    //First move of the principal variation
+    pub pv_move: Option<String>,
+    pub cp_score: Option<isize>,
+    pub positive_mate_found: bool,
+    pub negative_mate_found: bool,
+}
+
+impl UCIInfo {
+    //Score from the engine's point of view, mate announcements are mapped to MATE_REPORTED_SCORE
+    pub fn score(&self) -> Option<isize> {
+        if self.negative_mate_found {
+            Some(-adjudication::MATE_REPORTED_SCORE)
+        } else if self.positive_mate_found {
+            Some(adjudication::MATE_REPORTED_SCORE)
+        } else {
+            self.cp_score
+        }
+    }
 }
 
 pub struct PlayTask {
+    //Position the opening sequence is played from
+    pub start_fen: String,
     pub opening: GameState,
     pub opening_sequence: Vec<GameMove>,
     pub p1_is_white: bool,
     pub id: usize,
+    //Games with the same pair id play the same opening against the same enemy with colors reversed
+    pub pair_id: usize,
     pub engine1: Engine,
     pub engine2: Engine,
 }
@@ -472,12 +677,49 @@ pub struct TaskResult {
     pub endcondition: Option<EndConditionInformation>,
     pub move_sequence: Vec<GameMove>,
     pub final_status: GameResult,
+    //Why the disqualified engine was disqualified
+    pub failure: Option<EngineFailure>,
 }
 
 impl TaskResult {
+    //1 for a win, 0.5 for a draw and 0 for a loss of engine1. A disqualification counts as a loss
+    pub fn engine1_score(&self) -> f64 {
+        if self.task.engine1.disqs > 0 {
+            0.
+        } else if self.task.engine2.disqs > 0 {
+            1.
+        } else {
+            self.task.engine1.wins as f64 + self.task.engine1.draws as f64 / 2.
+        }
+    }
+
+    //The end condition, or which engine was disqualified and why
+    pub fn reason(&self) -> String {
+        match (self.endcondition, self.failure) {
+            (Some(reason), _) => format!("{}", reason),
+            (None, Some(failure)) => format!(
+                "Disqualification of {}: {}",
+                self.disqualified().map_or("", |engine| &engine.name),
+                failure
+            ),
+            (None, None) => "Disqualification".to_owned(),
+        }
+    }
+
+    pub fn disqualified(&self) -> Option<&Engine> {
+        if self.task.engine1.disqs > 0 {
+            Some(&self.task.engine1)
+        } else if self.task.engine2.disqs > 0 {
+            Some(&self.task.engine2)
+        } else {
+            None
+        }
+    }
+
     pub fn disq(
         mut task: PlayTask,
         p1: bool,
+        failure: EngineFailure,
         move_sequence: Vec<GameMove>,
         final_status: GameResult,
     ) -> Self {
@@ -493,6 +735,7 @@ impl TaskResult {
             endcondition: None,
             move_sequence,
             final_status,
+            failure: Some(failure),
         }
     }
 }
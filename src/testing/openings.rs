@@ -1,11 +1,280 @@
-use super::queue::ThreadSafeQueue;
 use crate::board_representation::game_state::*;
+use crate::board_representation::game_state_attack_container::GameStateAttackContainer;
+use crate::evaluation::eval_game_state_from_null;
+use crate::misc::STD_FEN;
+use crate::move_generation::makemove::make_move;
 use crate::move_generation::movegen;
 use crate::pgn::pgn_reader::{GameParser, PGNParser};
-use crate::testing::{Engine, PlayTask};
+use crate::search::cache::Cache;
+use crate::search::searcher::{search_move, InterThreadCommunicationSystem};
+use crate::search::timecontrol::TimeControl;
+use crate::testing::{find_move, Engine, PlayTask};
 use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
 use std::fs::File;
 use std::io::BufReader;
+use std::sync::Arc;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+pub enum OpeningOrder {
+    #[default]
+    Random,
+    Sequential,
+}
+
+//Openings whose absolute score in centipawns is outside [min_score, max_score] are rejected.
+//A depth of 0 uses the static evaluation, otherwise a search of that depth
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub struct BalanceFilter {
+    pub min_score: i16,
+    pub max_score: i16,
+    pub depth: i16,
+}
+
+fn default_repeat() -> bool {
+    true
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct OpeningConfig {
+    //PGN databases, cut at load_untilply
+    #[serde(default)]
+    pub databases: Vec<String>,
+    #[serde(default)]
+    pub load_untilply: usize,
+    //EPD or FEN files with one position per line, optionally followed by "moves" and moves in uci notation
+    #[serde(default)]
+    pub suites: Vec<String>,
+    #[serde(default)]
+    pub order: OpeningOrder,
+    //Play every opening twice with colors reversed
+    #[serde(default = "default_repeat")]
+    pub repeat: bool,
+    #[serde(default)]
+    pub balance: Option<BalanceFilter>,
+}
+
+#[derive(Clone)]
+pub struct Opening {
+    pub start_fen: String,
+    pub sequence: Vec<GameMove>,
+    pub state: GameState,
+}
+
+pub struct OpeningBook {
+    pub openings: Vec<Opening>,
+    pub order: OpeningOrder,
+    next: usize,
+}
+
+impl OpeningBook {
+    pub fn load(config: &OpeningConfig) -> Self {
+        let mut openings = Vec::with_capacity(100_000);
+        for database in config.databases.iter() {
+            let (states, sequences) = load_db_until(database, config.load_untilply);
+            for (state, sequence) in states.into_iter().zip(sequences) {
+                openings.push(Opening {
+                    start_fen: STD_FEN.to_owned(),
+                    sequence,
+                    state,
+                });
+            }
+        }
+        for suite in config.suites.iter() {
+            openings.append(&mut load_suite(suite));
+        }
+        //Step 1. Remove duplicate positions, most openings of a database share their first moves
+        let mut seen = HashSet::new();
+        openings.retain(|opening| seen.insert(opening.state.hash));
+        //Step 2. Remove unbalanced openings
+        if let Some(filter) = config.balance {
+            let before = openings.len();
+            let itcs = Arc::new(InterThreadCommunicationSystem::new());
+            InterThreadCommunicationSystem::update_thread_count(&itcs, 1);
+            itcs.replace_cache(Cache::with_size(8));
+            openings.retain(|opening| {
+                let score = opening_score(&opening.state, filter.depth, &itcs).abs();
+                score >= filter.min_score && score <= filter.max_score
+            });
+            println!(
+                "Balance filter rejected {} of {} openings",
+                before - openings.len(),
+                before
+            );
+        }
+        OpeningBook {
+            openings,
+            order: config.order,
+            next: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self.order {
+            OpeningOrder::Random => self.openings.len(),
+            OpeningOrder::Sequential => self.openings.len() - self.next,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn next_opening(&mut self) -> Opening {
+        if self.is_empty() {
+            panic!("There are not enough different openings in database! Use bigger database or load until higher ply!");
+        }
+        match self.order {
+            OpeningOrder::Random => {
+                let index = rand::thread_rng().gen_range(0, self.openings.len());
+                self.openings.swap_remove(index)
+            }
+            OpeningOrder::Sequential => {
+                self.next += 1;
+                self.openings[self.next - 1].clone()
+            }
+        }
+    }
+}
+
+//Score in centipawns from white's point of view
+fn opening_score(state: &GameState, depth: i16, itcs: &Arc<InterThreadCommunicationSystem>) -> i16 {
+    if depth == 0 {
+        return eval_game_state_from_null(state).final_eval;
+    }
+    let score = search_move(
+        Arc::clone(itcs),
+        depth,
+        state.clone(),
+        Vec::new(),
+        TimeControl::Infinite,
+    );
+    itcs.cache().clear();
+    match score {
+        Some(score) if state.color_to_move == WHITE => score,
+        Some(score) => -score,
+        //Only one legal move, the position is judged by the static evaluation
+        None => eval_game_state_from_null(state).final_eval,
+    }
+}
+
+pub fn load_suite(path: &str) -> Vec<Opening> {
+    let content = fs::read_to_string(path).expect("Unable to read opening suite");
+    content
+        .lines()
+        .filter_map(|line| {
+            parse_suite_line(line)
+                .unwrap_or_else(|| panic!("Invalid line in opening suite {}: {}", path, line))
+        })
+        .collect()
+}
+
+//Parses "FEN [moves m1 m2 ...]" or "EPD [moves m1 m2 ...;] [operations]". Returns Some(None) for empty lines
+pub fn parse_suite_line(line: &str) -> Option<Option<Opening>> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Some(None);
+    }
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    if tokens.len() < 4 {
+        return None;
+    }
+    let mut index = 4;
+    let counters = if tokens.len() >= 6
+        && tokens[4].parse::<usize>().is_ok()
+        && tokens[5].trim_end_matches(';').parse::<usize>().is_ok()
+    {
+        index = 6;
+        format!("{} {}", tokens[4], tokens[5].trim_end_matches(';'))
+    } else {
+        "0 1".to_owned()
+    };
+    let start_fen = format!(
+        "{} {} {} {} {}",
+        tokens[0],
+        tokens[1],
+        tokens[2],
+        tokens[3].trim_end_matches(';'),
+        counters
+    );
+    let mut moves = Vec::new();
+    if tokens.get(index) == Some(&"moves") {
+        for token in tokens[index + 1..].iter() {
+            let mv = token.trim_end_matches(';');
+            if !mv.is_empty() {
+                moves.push(mv.to_owned());
+            }
+            if token.ends_with(';') {
+                break;
+            }
+        }
+    }
+    let (state, sequence) = replay_moves(&start_fen, &moves)?;
+    Some(Some(Opening {
+        start_fen,
+        sequence,
+        state,
+    }))
+}
+
+//Plays the moves in uci notation from the given position. None if a move is illegal
+pub fn replay_moves(start_fen: &str, moves: &[String]) -> Option<(GameState, Vec<GameMove>)> {
+    let mut movelist = movegen::MoveList::default();
+    let mut attack_container = GameStateAttackContainer::default();
+    let mut state = GameState::from_fen(start_fen);
+    let mut sequence = Vec::with_capacity(moves.len());
+    for mv in moves.iter() {
+        attack_container.write_state(&state);
+        movegen::generate_moves(&state, false, &mut movelist, &attack_container);
+        let (from, to, promotion) = GameMove::string_to_move(mv);
+        let game_move = find_move(from, to, promotion, &movelist)?;
+        state = make_move(&state, &game_move);
+        sequence.push(game_move);
+    }
+    Some((state, sequence))
+}
+
+//Every opening is played against every enemy, twice with colors reversed if repeat is set.
+//Without repeat every opening is played once and each game gets its own pair id
+pub fn load_openings_into_tasks(
+    n: usize,
+    book: &mut OpeningBook,
+    repeat: bool,
+    gauntlet_engine: &Engine,
+    enemies: &[Engine],
+) -> Vec<PlayTask> {
+    let openings_needed = if repeat { n } else { 2 * n };
+    let mut res: Vec<PlayTask> = Vec::with_capacity(2 * n * enemies.len());
+    let mut id = 0;
+    for index in 0..openings_needed {
+        let opening = book.next_opening();
+        for (enemy_index, enemy_engine) in enemies.iter().enumerate() {
+            let colors: &[bool] = if repeat {
+                &[true, false]
+            } else if index % 2 == 0 {
+                &[true]
+            } else {
+                &[false]
+            };
+            for p1_is_white in colors.iter() {
+                res.push(PlayTask {
+                    start_fen: opening.start_fen.clone(),
+                    opening: opening.state.clone(),
+                    opening_sequence: opening.sequence.clone(),
+                    p1_is_white: *p1_is_white,
+                    id,
+                    pair_id: index * enemies.len() + enemy_index,
+                    engine1: gauntlet_engine.clone(),
+                    engine2: enemy_engine.clone(),
+                });
+                id += 1;
+            }
+        }
+    }
+    res
+}
 
 pub fn load_db_until(db: &str, until: usize) -> (Vec<GameState>, Vec<Vec<GameMove>>) {
     let movelist = movegen::MoveList::default();
@@ -33,57 +302,122 @@ pub fn load_db_until(db: &str, until: usize) -> (Vec<GameState>, Vec<Vec<GameMov
     (res, res_mvs)
 }
 
-pub fn load_openings_into_queue(
-    n: usize,
-    mut db: Vec<GameState>,
-    mut db_sequences: Vec<Vec<GameMove>>,
-    gauntlet_engine: &Engine,
-    enemies: &[Engine],
-) -> ThreadSafeQueue<PlayTask> {
-    let mut rng = rand::thread_rng();
-    let mut res: Vec<PlayTask> = Vec::with_capacity(n);
-    let mut id = 0;
-    for _ in 0..n {
-        loop {
-            if db.is_empty() {
-                panic!("There are not enough different openings in database! Use bigger database or load until higher ply!");
-            }
-            let index: usize = rng.gen_range(0, db.len());
-            let state = db.remove(index);
-            let sequence = db_sequences.remove(index);
-            if !contains(&res, &state) {
-                for enemy_engine in enemies {
-                    res.push(PlayTask {
-                        opening: state.clone(),
-                        opening_sequence: sequence.clone(),
-                        p1_is_white: true,
-                        id,
-                        engine1: gauntlet_engine.clone(),
-                        engine2: enemy_engine.clone(),
-                    });
-                    id += 1;
-                    res.push(PlayTask {
-                        opening: state.clone(),
-                        opening_sequence: sequence.clone(),
-                        p1_is_white: false,
-                        id,
-                        engine1: gauntlet_engine.clone(),
-                        engine2: enemy_engine.clone(),
-                    });
-                    id += 1;
-                }
-                break;
-            }
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::clock::{Clock, TimeControlConfig};
+    use crate::testing::{EngineConfig, EngineStats};
+
+    #[test]
+    fn suite_test() {
+        let fen = parse_suite_line("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1")
+            .unwrap()
+            .unwrap();
+        assert_eq!(fen.state.color_to_move, BLACK);
+        assert!(fen.sequence.is_empty());
+        let epd = parse_suite_line(
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - moves e2e4 e7e5 g1f3; id \"test\";",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(epd.sequence.len(), 3);
+        assert_eq!(epd.start_fen, STD_FEN);
+        assert_eq!(
+            epd.state.hash,
+            GameState::from_fen("rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2")
+                .hash
+        );
+        let with_counters =
+            parse_suite_line("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 moves d2d4")
+                .unwrap()
+                .unwrap();
+        assert_eq!(with_counters.sequence.len(), 1);
+        assert!(parse_suite_line("").unwrap().is_none());
+        //Illegal move
+        assert!(parse_suite_line(
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - moves e2e5"
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn opening_book_test() {
+        let path = std::env::temp_dir().join(format!("fabchess_suite_{}.epd", std::process::id()));
+        fs::write(
+            &path,
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - moves e2e4;\n\
+             rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - moves e2e4;\n\
+             rnbqkbnr/pppppppp/8/8/3P4/8/PPP1PPPP/RNBQKBNR b KQkq -\n\
+             rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - moves g2g4 e7e5 f2f3;\n",
+        )
+        .unwrap();
+        let config = OpeningConfig {
+            databases: Vec::new(),
+            load_untilply: 0,
+            suites: vec![path.to_string_lossy().into_owned()],
+            order: OpeningOrder::Sequential,
+            repeat: true,
+            balance: None,
+        };
+        let mut book = OpeningBook::load(&config);
+        //The duplicate is removed
+        assert_eq!(book.len(), 3);
+        assert_eq!(book.next_opening().sequence.len(), 1);
+        assert!(book.next_opening().sequence.is_empty());
+        assert_eq!(book.len(), 1);
+
+        //After g4 e5 f3 black is winning
+        let balanced = OpeningConfig {
+            balance: Some(BalanceFilter {
+                min_score: 0,
+                max_score: 100,
+                depth: 0,
+            }),
+            ..config
+        };
+        let book = OpeningBook::load(&balanced);
+        assert_eq!(book.len(), 2);
+        let _ = fs::remove_file(&path);
     }
-    ThreadSafeQueue::new(res)
-}
 
-pub fn contains(queue: &[PlayTask], state: &GameState) -> bool {
-    for other in queue {
-        if other.opening.hash == state.hash {
-            return true;
+    #[test]
+    fn pair_id_test() {
+        let engine = |id| Engine {
+            name: format!("Engine{}", id),
+            config: EngineConfig::from_path("engine"),
+            id,
+            wins: 0,
+            draws: 0,
+            losses: 0,
+            disqs: 0,
+            time_losses: 0,
+            time_control: Clock::new(TimeControlConfig::MoveTime(100)),
+            stats: EngineStats::default(),
+            transcript: Default::default(),
+        };
+        let enemies = vec![engine(0), engine(1)];
+        let mut book = OpeningBook {
+            openings: [
+                "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+                STD_FEN,
+            ]
+            .iter()
+            .map(|fen| parse_suite_line(fen).unwrap().unwrap())
+            .collect(),
+            order: OpeningOrder::Sequential,
+            next: 0,
+        };
+        let tasks = load_openings_into_tasks(2, &mut book, true, &engine(999), &enemies);
+        assert_eq!(tasks.len(), 8);
+        for task in tasks.iter() {
+            let pair: Vec<&PlayTask> = tasks
+                .iter()
+                .filter(|other| other.pair_id == task.pair_id)
+                .collect();
+            assert_eq!(pair.len(), 2);
+            assert_eq!(pair[0].opening.hash, pair[1].opening.hash);
+            assert_eq!(pair[0].engine2.id, pair[1].engine2.id);
+            assert_ne!(pair[0].p1_is_white, pair[1].p1_is_white);
         }
     }
-    false
 }
@@ -5,8 +5,14 @@ extern crate tokio;
 extern crate tokio_io;
 extern crate tokio_process;
 
+use core::testing::adjudication::AdjudicationConfig;
+use core::testing::clock::TimeControlConfig;
+use core::testing::openings::OpeningConfig;
+use core::testing::sprt::SPRTConfig;
+use core::testing::spsa::SPSAConfig;
+use core::testing::tournament::TournamentConfig;
+use core::testing::EngineConfig;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
 use std::env;
 use std::fs;
 use std::io::BufWriter;
@@ -15,7 +21,9 @@ use std::io::Write;
 pub mod lct2;
 pub mod selfplay;
 pub mod selfplay_splitter;
+pub mod spsa_splitter;
 pub mod suit;
+pub mod tournament_splitter;
 
 //STS
 pub const STS_SUB_SUITS: [&str; 15] = [
@@ -39,22 +47,40 @@ pub const STS_SUB_SUITS: [&str; 15] = [
 #[derive(Serialize, Deserialize)]
 pub struct Config {
     pub processors: usize,
-    selfplaytests: bool,
+    pub engine: EngineConfig,
+    pub selfplay: Option<SelfplayConfig>,
+    pub sprt: Option<SPRTConfig>,
+    pub spsa: Option<SPSAConfig>,
+    pub tournament: Option<TournamentConfig>,
+    pub suite: Option<SuiteConfig>,
+    pub lct2: Option<Lct2Config>,
+    //Games are stored there and an interrupted match is resumed when started again
+    pub match_directory: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SelfplayConfig {
     pub games: usize,
-    pub engine_path: (String, HashMap<String, String>),
-    pub enemies_paths: Vec<(String, HashMap<String, String>)>,
-    pub opening_databases: Vec<String>,
-    pub opening_load_untilply: usize,
-    pub timecontrol_engine_time: u64,
-    pub timecontrol_engine_inc: u64,
-    pub timecontrol_enemies_time: u64,
-    pub timecontrol_enemies_inc: u64,
-    testsuitetests: bool,
-    suite_path: String,
-    suite_movetime: u64,
-    lct2tests: bool,
-    lct2_path: String,
+    pub enemies: Vec<EngineConfig>,
+    pub openings: OpeningConfig,
+    //Time control of every engine which doesn't specify its own
+    pub time_control: TimeControlConfig,
+    pub adjudication: Option<AdjudicationConfig>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SuiteConfig {
+    pub path: String,
+    pub movetime: u64,
 }
+
+#[derive(Serialize, Deserialize)]
+pub struct Lct2Config {
+    pub path: String,
+}
+
+pub const USAGE: &str = "Usage: referee selfplay|sprt|spsa|tournament|suite|lct2 [config CONFIG_FILE] [directory MATCH_DIRECTORY]";
+
 /*
 Error-Margin in +/- (95% Confidence)
 Games   :    100    200    400    600    1000    1500    2000    3000    4000     10000
@@ -73,6 +99,8 @@ Win/Elo Gain:-------------------------------------------------------------------
 */
 fn main() {
     let mut config_path = "REFEREE_CONFIG.json";
+    let mut mode = None;
+    let mut match_directory = None;
     let args: Vec<String> = env::args().collect();
     let mut index: usize = 1;
     while index < args.len() {
@@ -82,28 +110,75 @@ fn main() {
                 index += 2;
                 continue;
             }
+            "directory" => {
+                match_directory = Some(args[index + 1].clone());
+                index += 2;
+                continue;
+            }
+            "selfplay" | "sprt" | "spsa" | "tournament" | "suite" | "lct2" => {
+                mode = Some(args[index].clone());
+            }
             _ => {
-                println!(
-                    "Invalid argument {}, use config CONFIG_FILE to specify",
-                    &args[index]
-                );
-                index += 1;
+                println!("Invalid argument {}. {}", &args[index], USAGE);
             }
         }
+        index += 1;
     }
+    let mode = match mode {
+        Some(mode) => mode,
+        None => {
+            println!("{}", USAGE);
+            return;
+        }
+    };
     let config_content = fs::read_to_string(config_path).expect("Unable to read config file!");
-    let config: Config = serde_json::from_str(&config_content).unwrap();
-    if config.selfplaytests {
-        selfplay_splitter::start_self_play(config);
-    } else if config.lct2tests {
-        lct2::lct2(&config.engine_path.0, config.processors, &config.lct2_path);
-    } else if config.testsuitetests {
-        suit::start_suit(
-            &config.engine_path.0,
+    let mut config: Config = serde_json::from_str(&config_content).unwrap();
+    if match_directory.is_some() {
+        config.match_directory = match_directory;
+    }
+    match &mode[..] {
+        "selfplay" => selfplay_splitter::start_self_play(
             config.processors,
-            &config.suite_path,
-            config.suite_movetime,
-        );
+            config.engine,
+            config.selfplay.expect("Config has no selfplay section!"),
+            None,
+            config.match_directory,
+        ),
+        "sprt" => selfplay_splitter::start_self_play(
+            config.processors,
+            config.engine,
+            config.selfplay.expect("Config has no selfplay section!"),
+            Some(config.sprt.expect("Config has no sprt section!")),
+            config.match_directory,
+        ),
+        "spsa" => spsa_splitter::start_spsa(
+            config.processors,
+            config.engine,
+            config.selfplay.expect("Config has no selfplay section!"),
+            config.spsa.expect("Config has no spsa section!"),
+            config.match_directory,
+        ),
+        "tournament" => tournament_splitter::start_tournament(
+            config.processors,
+            config
+                .tournament
+                .expect("Config has no tournament section!"),
+            config.match_directory,
+        ),
+        "suite" => {
+            let suite = config.suite.expect("Config has no suite section!");
+            suit::start_suit(
+                &config.engine,
+                config.processors,
+                &suite.path,
+                suite.movetime,
+            );
+        }
+        "lct2" => {
+            let lct2 = config.lct2.expect("Config has no lct2 section!");
+            lct2::lct2(&config.engine, config.processors, &lct2.path);
+        }
+        _ => unreachable!(),
     }
 }
 
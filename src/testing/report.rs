@@ -0,0 +1,400 @@
+use crate::testing::sprt::{SPRTConfig, SPRT};
+use crate::testing::transcript::EngineFailure;
+use crate::testing::{Engine, TaskResult};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Display, Formatter, Result};
+use std::fs;
+use std::time::{Duration, Instant};
+
+//Seconds between two status lines
+pub const STATUS_INTERVAL: u64 = 10;
+
+//A finished game as stored in the match report
+#[derive(Serialize, Deserialize, Clone)]
+pub struct GameRecord {
+    pub id: usize,
+    pub white: String,
+    pub black: String,
+    pub engine1: usize,
+    pub engine2: usize,
+    pub p1_is_white: bool,
+    //Position after the opening sequence
+    pub opening: String,
+    pub result: String,
+    pub termination: String,
+    pub engine1_score: f64,
+    pub plies: usize,
+    //Name of the disqualified engine and why it was disqualified
+    #[serde(default)]
+    pub disqualified: Option<String>,
+    #[serde(default)]
+    pub failure: Option<EngineFailure>,
+}
+
+impl GameRecord {
+    pub fn new(result: &TaskResult) -> Self {
+        let (white, black) = if result.task.p1_is_white {
+            (&result.task.engine1, &result.task.engine2)
+        } else {
+            (&result.task.engine2, &result.task.engine1)
+        };
+        GameRecord {
+            id: result.task.id,
+            white: white.name.clone(),
+            black: black.name.clone(),
+            engine1: result.task.engine1.id,
+            engine2: result.task.engine2.id,
+            p1_is_white: result.task.p1_is_white,
+            opening: result.task.opening.to_fen(),
+            result: result.final_status.to_string(),
+            termination: result.reason(),
+            engine1_score: result.engine1_score(),
+            plies: result.move_sequence.len(),
+            disqualified: result.disqualified().map(|engine| engine.name.clone()),
+            failure: result.failure,
+        }
+    }
+
+    pub fn white_score(&self) -> f64 {
+        if self.p1_is_white {
+            self.engine1_score
+        } else {
+            1. - self.engine1_score
+        }
+    }
+}
+
+//Results of all games played from the same opening
+#[derive(Serialize, Deserialize, Clone)]
+pub struct OpeningReport {
+    pub opening: String,
+    pub games: usize,
+    pub white_score: f64,
+    pub engine1_score: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct EngineReport {
+    pub id: usize,
+    pub name: String,
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+    pub disqs: usize,
+    pub time_losses: usize,
+    pub elo: f64,
+    pub elo_error: f64,
+    pub moves_played: usize,
+    pub avg_depth: f64,
+    pub avg_nps: f64,
+    pub avg_timeleft: f64,
+}
+
+impl EngineReport {
+    pub fn new(engine: &Engine) -> Self {
+        let (elo, elo_error) = engine.elo();
+        EngineReport {
+            id: engine.id,
+            name: engine.name.clone(),
+            wins: engine.wins,
+            draws: engine.draws,
+            losses: engine.losses,
+            disqs: engine.disqs,
+            time_losses: engine.time_losses,
+            elo,
+            elo_error,
+            moves_played: engine.stats.moves_played,
+            avg_depth: engine.stats.avg_depth,
+            avg_nps: engine.stats.avg_nps,
+            avg_timeleft: engine.stats.avg_timeleft,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SPRTReport {
+    pub config: SPRTConfig,
+    pub llr: f64,
+    pub lower_bound: f64,
+    pub upper_bound: f64,
+    pub pentanomial: [usize; 5],
+    pub status: String,
+}
+
+impl SPRTReport {
+    pub fn new(sprt: &SPRT) -> Self {
+        let (lower_bound, upper_bound) = sprt.bounds();
+        SPRTReport {
+            config: sprt.config,
+            llr: sprt.llr(),
+            lower_bound,
+            upper_bound,
+            pentanomial: sprt.pentanomial,
+            status: format!("{}", sprt.status()),
+        }
+    }
+}
+
+//Games in which an engine was disqualified for the same reason
+#[derive(Serialize, Deserialize, Clone)]
+pub struct FailureReport {
+    pub engine: String,
+    pub failure: EngineFailure,
+    pub games: Vec<usize>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct MatchReport {
+    pub event: String,
+    pub games_played: usize,
+    pub duration_seconds: f64,
+    pub games: Vec<GameRecord>,
+    pub openings: Vec<OpeningReport>,
+    pub engines: Vec<EngineReport>,
+    pub sprt: Option<SPRTReport>,
+    #[serde(default)]
+    pub failures: Vec<FailureReport>,
+}
+
+impl MatchReport {
+    //Games are sorted by id and openings in order of their first game
+    pub fn new(
+        event: &str,
+        mut games: Vec<GameRecord>,
+        engines: &[&Engine],
+        sprt: Option<&SPRT>,
+        duration: Duration,
+    ) -> Self {
+        games.sort_by_key(|game| game.id);
+        let mut openings: Vec<OpeningReport> = Vec::new();
+        let mut index: HashMap<String, usize> = HashMap::new();
+        for game in games.iter() {
+            let position = *index.entry(game.opening.clone()).or_insert_with(|| {
+                openings.push(OpeningReport {
+                    opening: game.opening.clone(),
+                    games: 0,
+                    white_score: 0.,
+                    engine1_score: 0.,
+                });
+                openings.len() - 1
+            });
+            let opening = &mut openings[position];
+            opening.games += 1;
+            opening.white_score += game.white_score();
+            opening.engine1_score += game.engine1_score;
+        }
+        let mut failures: BTreeMap<(String, EngineFailure), Vec<usize>> = BTreeMap::new();
+        for game in games.iter() {
+            if let (Some(engine), Some(failure)) = (game.disqualified.as_ref(), game.failure) {
+                failures
+                    .entry((engine.clone(), failure))
+                    .or_default()
+                    .push(game.id);
+            }
+        }
+        MatchReport {
+            event: event.to_owned(),
+            games_played: games.len(),
+            duration_seconds: duration.as_secs_f64(),
+            games,
+            openings,
+            engines: engines
+                .iter()
+                .map(|engine| EngineReport::new(engine))
+                .collect(),
+            sprt: sprt.map(SPRTReport::new),
+            failures: failures
+                .into_iter()
+                .map(|((engine, failure), games)| FailureReport {
+                    engine,
+                    failure,
+                    games,
+                })
+                .collect(),
+        }
+    }
+
+    pub fn write(&self, path: &str) {
+        fs::write(path, serde_json::to_string_pretty(self).unwrap())
+            .expect("Unable to write match report!");
+        println!("Wrote match report to {}", path);
+    }
+}
+
+//Summary of all disqualifications
+impl Display for MatchReport {
+    fn fmt(&self, formatter: &mut Formatter) -> Result {
+        if self.failures.is_empty() {
+            return write!(formatter, "No engine failures");
+        }
+        let mut res_str = String::from("Engine failures:");
+        for failure in self.failures.iter() {
+            res_str.push_str(&format!(
+                "\n{}\t{}: {} games {:?}",
+                failure.engine,
+                failure.failure,
+                failure.games.len(),
+                failure.games
+            ));
+        }
+        write!(formatter, "{}", res_str)
+    }
+}
+
+//Prints a summary of the running match every STATUS_INTERVAL seconds
+pub struct StatusLine {
+    start: Instant,
+    last_print: Option<Instant>,
+    pub played_before: usize,
+}
+
+impl StatusLine {
+    //Games played before the match was resumed don't count for the games per minute
+    pub fn new(played_before: usize) -> Self {
+        StatusLine {
+            start: Instant::now(),
+            last_print: None,
+            played_before,
+        }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    pub fn update(
+        &mut self,
+        played: usize,
+        total: usize,
+        main: Option<&Engine>,
+        sprt: Option<&SPRT>,
+        engines: &[&Engine],
+    ) {
+        let due = match self.last_print {
+            Some(last) => last.elapsed() >= Duration::from_secs(STATUS_INTERVAL),
+            None => true,
+        };
+        if due || played == total {
+            self.last_print = Some(Instant::now());
+            println!("{}", self.format(played, total, main, sprt, engines));
+        }
+    }
+
+    pub fn format(
+        &self,
+        played: usize,
+        total: usize,
+        main: Option<&Engine>,
+        sprt: Option<&SPRT>,
+        engines: &[&Engine],
+    ) -> String {
+        let minutes = self.elapsed().as_secs_f64() / 60.;
+        let games_per_minute = if minutes > 0. {
+            played.saturating_sub(self.played_before) as f64 / minutes
+        } else {
+            0.
+        };
+        let mut res_str = format!("[{}/{}]", played, total);
+        if let Some(engine) = main {
+            let (elo, elo_error) = engine.elo();
+            res_str.push_str(&format!(
+                " +{} ={} -{} Elo {:.1} +/- {:.1}",
+                engine.wins, engine.draws, engine.losses, elo, elo_error
+            ));
+        }
+        if let Some(sprt) = sprt {
+            let (lower, upper) = sprt.bounds();
+            res_str.push_str(&format!(
+                " LLR {:.2} ({:.2}, {:.2})",
+                sprt.llr(),
+                lower,
+                upper
+            ));
+        }
+        res_str.push_str(&format!(" {:.1} games/min", games_per_minute));
+        for engine in engines {
+            if engine.time_losses > 0 || engine.disqs > 0 {
+                res_str.push_str(&format!(
+                    " | {}: time losses {} disqs {}",
+                    engine.name, engine.time_losses, engine.disqs
+                ));
+            }
+        }
+        res_str
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::clock::{Clock, TimeControlConfig};
+    use crate::testing::{EngineConfig, EngineStats};
+
+    fn game(id: usize, opening: &str, p1_is_white: bool, engine1_score: f64) -> GameRecord {
+        GameRecord {
+            id,
+            white: "A".to_owned(),
+            black: "B".to_owned(),
+            engine1: 0,
+            engine2: 1,
+            p1_is_white,
+            opening: opening.to_owned(),
+            result: "*".to_owned(),
+            termination: "Win by Mate".to_owned(),
+            engine1_score,
+            plies: 40,
+            disqualified: None,
+            failure: None,
+        }
+    }
+
+    #[test]
+    fn report_test() {
+        let engine = Engine {
+            name: "A".to_owned(),
+            config: EngineConfig::from_path("engine"),
+            id: 0,
+            wins: 2,
+            draws: 1,
+            losses: 1,
+            disqs: 1,
+            time_losses: 1,
+            time_control: Clock::new(TimeControlConfig::MoveTime(100)),
+            stats: EngineStats::default(),
+            transcript: Default::default(),
+        };
+        let mut timeout = game(3, "b", false, 0.);
+        timeout.disqualified = Some("A".to_owned());
+        timeout.failure = Some(EngineFailure::Timeout);
+        let games = vec![
+            game(2, "b", true, 0.5),
+            game(0, "a", true, 1.),
+            game(1, "a", false, 1.),
+            timeout,
+        ];
+        let report = MatchReport::new("test", games, &[&engine], None, Duration::from_secs(60));
+        assert_eq!(report.games_played, 4);
+        assert_eq!(report.games[0].id, 0);
+        assert_eq!(report.openings.len(), 2);
+        assert_eq!(report.openings[0].opening, "a");
+        assert_eq!(report.openings[0].engine1_score, 2.);
+        assert_eq!(report.openings[0].white_score, 1.);
+        assert_eq!(report.openings[1].white_score, 1.5);
+        assert_eq!(report.engines[0].time_losses, 1);
+        assert!(report.engines[0].elo > 0.);
+        assert_eq!(report.failures.len(), 1);
+        assert_eq!(report.failures[0].games, vec![3]);
+        assert_eq!(
+            format!("{}", report),
+            "Engine failures:\nA\tTimeout: 1 games [3]"
+        );
+        let json = serde_json::to_string(&report).unwrap();
+        let parsed: MatchReport = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.games.len(), 4);
+
+        let status = StatusLine::new(0).format(4, 10, Some(&engine), None, &[&engine]);
+        assert!(status.starts_with("[4/10] +2 =1 -1 Elo"));
+        assert!(status.ends_with("| A: time losses 1 disqs 1"));
+    }
+}
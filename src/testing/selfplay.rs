@@ -3,13 +3,19 @@ use core::board_representation::game_state_attack_container::GameStateAttackCont
 use core::logging::Logger;
 use core::move_generation::makemove::make_move;
 use core::move_generation::movegen;
-use core::testing::async_communication::print_command;
+use core::testing::adjudication::{AdjudicationConfig, Adjudicator};
+use core::testing::clock::go_command;
+use core::testing::transcript::Transcript;
 use core::testing::*;
 use std::sync::Arc;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-pub fn play_game(mut task: PlayTask, mut error_log: Arc<Logger>) -> TaskResult {
+pub fn play_game(
+    mut task: PlayTask,
+    mut error_log: Arc<Logger>,
+    adjudication: AdjudicationConfig,
+) -> TaskResult {
     let mut movelist = movegen::MoveList::default();
     let mut attack_container = GameStateAttackContainer::default();
     //-------------------------------------------------------------
@@ -30,58 +36,59 @@ pub fn play_game(mut task: PlayTask, mut error_log: Arc<Logger>) -> TaskResult {
     let mut move_history: Vec<GameMove> = Vec::with_capacity(100);
     let mut endcondition = None;
     //-------------------------------------------------------------
+    //Both transcripts start now, so that they can be merged
+    let start = Instant::now();
+    task.engine1.transcript = Transcript::new(start);
+    task.engine2.transcript = Transcript::new(start);
+    //-------------------------------------------------------------
     //Set tokio runtime up
     let mut runtime = tokio::runtime::Runtime::new().expect("Could not create tokio runtime!");
     //-------------------------------------------------------------
     //Set players up
 
     //Check uci and isready
-    let (_e1_process, mut e1_input, mut e1_output, mut e1_err) = task.engine1.get_handles();
+    let (_e1_process, mut e1_input, mut e1_output, e1_err) = task.engine1.get_handles();
+    task.engine1.record_stderr(&mut runtime, e1_err);
     let reaction = task.engine1.valid_uci_isready_reaction(
         e1_input,
         e1_output,
-        e1_err,
         &mut runtime,
         task.id,
         error_log,
     );
     match reaction {
-        EngineReaction::DisqualifyEngine => {
-            return TaskResult::disq(task, true, move_history, status)
+        EngineReaction::DisqualifyEngine(failure) => {
+            return TaskResult::disq(task, true, failure, move_history, status)
         }
         EngineReaction::ContinueGame(temp) => {
             e1_input = temp.0;
             e1_output = temp.1;
-            e1_err = temp.2;
-            error_log = temp.3;
+            error_log = temp.2;
         }
     }
 
-    let (_e2_process, mut e2_input, mut e2_output, mut e2_err) = task.engine2.get_handles();
+    let (_e2_process, mut e2_input, mut e2_output, e2_err) = task.engine2.get_handles();
+    task.engine2.record_stderr(&mut runtime, e2_err);
     let reaction = task.engine2.valid_uci_isready_reaction(
         e2_input,
         e2_output,
-        e2_err,
         &mut runtime,
         task.id,
         error_log,
     );
     match reaction {
-        EngineReaction::DisqualifyEngine => {
-            return TaskResult::disq(task, false, move_history, status)
+        EngineReaction::DisqualifyEngine(failure) => {
+            return TaskResult::disq(task, false, failure, move_history, status)
         }
         EngineReaction::ContinueGame(temp) => {
             e2_input = temp.0;
             e2_output = temp.1;
-            e2_err = temp.2;
-            error_log = temp.3;
+            error_log = temp.2;
         }
     }
     //-------------------------------------------------------------
     //Adjudications
-    let mut draw_adjudication = 0;
-    let mut win_adjudication = 0;
-    let mut win_adjudication_for_p1 = true;
+    let mut adjudicator = Adjudicator::new(adjudication);
 
     while let GameResult::Ingame = status {
         //Request move
@@ -100,69 +107,43 @@ pub fn play_game(mut task: PlayTask, mut error_log: Arc<Logger>) -> TaskResult {
         }
         position_string.push_str("\n");
         //Prepare go command
-        let mut go_string = String::new();
-        go_string.push_str(&format!(
-            "go {} {}\n",
-            if task.p1_is_white {
-                task.engine1.time_control.to_go(true)
-            } else {
-                task.engine2.time_control.to_go(true)
-            },
-            if task.p1_is_white {
-                task.engine2.time_control.to_go(false)
-            } else {
-                task.engine1.time_control.to_go(false)
-            }
-        ));
+        let go_string = if player1_move {
+            go_command(
+                &task.engine1.time_control,
+                &task.engine2.time_control,
+                latest_state.color_to_move == WHITE,
+            )
+        } else {
+            go_command(
+                &task.engine2.time_control,
+                &task.engine1.time_control,
+                latest_state.color_to_move == WHITE,
+            )
+        };
         let game_move: GameMove;
+        let engine_score: Option<isize>;
         if player1_move {
             let reaction = task.engine1.request_move(
                 position_string,
                 go_string,
                 e1_input,
                 e1_output,
-                e1_err,
                 error_log,
                 &mut runtime,
                 task.id,
                 &movelist,
             );
-            let engine_status;
             match reaction {
-                EngineReaction::DisqualifyEngine => {
-                    return TaskResult::disq(task, true, move_history, status)
+                EngineReaction::DisqualifyEngine(failure) => {
+                    return TaskResult::disq(task, true, failure, move_history, status)
                 }
                 EngineReaction::ContinueGame(temp) => {
                     game_move = temp.0;
                     e1_input = temp.1;
                     e1_output = temp.2;
-                    e1_err = temp.3;
-                    error_log = temp.4;
-                    engine_status = temp.5;
-                }
-            }
-            if let EngineStatus::ProclaimsNothing = &engine_status {
-                draw_adjudication = 0;
-                win_adjudication = 0;
-            } else if let EngineStatus::ProclaimsDraw = &engine_status {
-                win_adjudication = 0;
-                draw_adjudication += 1;
-            } else if let EngineStatus::ProclaimsWin = &engine_status {
-                draw_adjudication = 0;
-                if !win_adjudication_for_p1 {
-                    win_adjudication = 1;
-                } else {
-                    win_adjudication += 1;
-                }
-                win_adjudication_for_p1 = true;
-            } else if let EngineStatus::ProclaimsLoss = &engine_status {
-                draw_adjudication = 0;
-                if win_adjudication_for_p1 {
-                    win_adjudication = 1;
-                } else {
-                    win_adjudication += 1;
+                    error_log = temp.3;
+                    engine_score = temp.4;
                 }
-                win_adjudication_for_p1 = false;
             }
         } else {
             let reaction = task.engine2.request_move(
@@ -170,57 +151,29 @@ pub fn play_game(mut task: PlayTask, mut error_log: Arc<Logger>) -> TaskResult {
                 go_string,
                 e2_input,
                 e2_output,
-                e2_err,
                 error_log,
                 &mut runtime,
                 task.id,
                 &movelist,
             );
-            let engine_status;
             match reaction {
-                EngineReaction::DisqualifyEngine => {
-                    return TaskResult::disq(task, false, move_history, status)
+                EngineReaction::DisqualifyEngine(failure) => {
+                    return TaskResult::disq(task, false, failure, move_history, status)
                 }
                 EngineReaction::ContinueGame(temp) => {
                     game_move = temp.0;
                     e2_input = temp.1;
                     e2_output = temp.2;
-                    e2_err = temp.3;
-                    error_log = temp.4;
-                    engine_status = temp.5;
+                    error_log = temp.3;
+                    engine_score = temp.4;
                 }
             }
-            if let EngineStatus::ProclaimsNothing = &engine_status {
-                draw_adjudication = 0;
-                win_adjudication = 0;
-            } else if let EngineStatus::ProclaimsDraw = &engine_status {
-                win_adjudication = 0;
-                draw_adjudication += 1;
-            } else if let EngineStatus::ProclaimsWin = &engine_status {
-                draw_adjudication = 0;
-                if win_adjudication_for_p1 {
-                    win_adjudication = 1;
-                } else {
-                    win_adjudication += 1;
-                }
-                win_adjudication_for_p1 = false;
-            } else if let EngineStatus::ProclaimsLoss = &engine_status {
-                draw_adjudication = 0;
-                if !win_adjudication_for_p1 {
-                    win_adjudication = 1;
-                } else {
-                    win_adjudication += 1;
-                }
-                win_adjudication_for_p1 = true;
-            }
         }
+        adjudicator.add_score(engine_score, latest_state.color_to_move == WHITE);
 
         //Make new state with move
         move_history.push(game_move);
         let state = make_move(latest_state, &game_move);
-        if state.full_moves < 35 {
-            draw_adjudication = 0;
-        }
         attack_container.write_state(&state);
         let agsi = movegen::generate_moves(&state, false, &mut movelist, &attack_container);
         let check = check_end_condition(&state, agsi.stm_haslegalmove, agsi.stm_incheck, &history);
@@ -229,31 +182,19 @@ pub fn play_game(mut task: PlayTask, mut error_log: Arc<Logger>) -> TaskResult {
         endcondition = check.1;
         //Check for adjudication
         if let GameResult::Ingame = status {
-            //Check adjudication values
-            if draw_adjudication >= 20 {
-                status = GameResult::Draw;
-                endcondition = Some(EndConditionInformation::DrawByadjudication);
-            } else if win_adjudication >= 10 {
-                endcondition = Some(EndConditionInformation::MateByadjudication);
-                if win_adjudication_for_p1 {
-                    if task.p1_is_white {
-                        status = GameResult::WhiteWin;
-                    } else {
-                        status = GameResult::BlackWin;
-                    }
-                } else if task.p1_is_white {
-                    status = GameResult::BlackWin;
-                } else {
-                    status = GameResult::WhiteWin;
-                }
+            if let Some((result, reason)) = adjudicator.adjudicate(&history[history.len() - 1]) {
+                status = result;
+                endcondition = Some(reason);
             }
         }
     }
 
     //-------------------------------------------------------------
     //Cleanup players' processes
-    print_command(&mut runtime, e1_input, "quit\n".to_owned());
-    print_command(&mut runtime, e2_input, "quit\n".to_owned());
+    task.engine1
+        .send(&mut runtime, e1_input, "quit\n".to_owned());
+    task.engine2
+        .send(&mut runtime, e2_input, "quit\n".to_owned());
     thread::sleep(Duration::from_millis(20));
     let draw = status == GameResult::Draw;
     let p1_win = status == GameResult::WhiteWin && task.p1_is_white
@@ -271,13 +212,14 @@ pub fn play_game(mut task: PlayTask, mut error_log: Arc<Logger>) -> TaskResult {
 
     task.engine1.stats.divide(); //Make the mean of nps and deepth
     task.engine2.stats.divide();
-    task.engine1.stats.avg_timeleft = task.engine1.time_control.time_left() as f64; //Set the time left
-    task.engine2.stats.avg_timeleft = task.engine2.time_control.time_left() as f64;
+    task.engine1.stats.avg_timeleft = task.engine1.time_control.time_left as f64; //Set the time left
+    task.engine2.stats.avg_timeleft = task.engine2.time_control.time_left as f64;
     TaskResult {
         task,
         endcondition,
         move_sequence: move_history,
         final_status: status,
+        failure: None,
     }
 }
 
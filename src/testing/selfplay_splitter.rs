@@ -1,173 +1,264 @@
 use crate::selfplay::play_game;
-use crate::Config;
-use core::board_representation::game_state::*;
+use crate::SelfplayConfig;
 use core::logging::Logger;
 use core::pgn::pgn_writer::*;
-use core::search::timecontrol::TimeControl;
-use core::testing::openings::{load_db_until, load_openings_into_queue};
+use core::testing::adjudication::AdjudicationConfig;
+use core::testing::match_state::{open_logs, report_path, save_transcript, MatchDirectory};
+use core::testing::openings::{load_openings_into_tasks, OpeningBook};
 use core::testing::queue::ThreadSafeQueue;
-use core::testing::{EndConditionInformation, Engine};
+use core::testing::report::{GameRecord, MatchReport, StatusLine};
+use core::testing::sprt::{SPRTConfig, SPRTStatus, SPRT};
+use core::testing::{Engine, EngineConfig};
 use core::testing::{PlayTask, TaskResult};
 use std::cmp::Ordering;
 use std::sync::Arc;
 use std::thread;
 use std::time::Duration;
 
-pub fn start_self_play(config: Config) {
-    let tcp1 = TimeControl::Incremental(
-        config.timecontrol_engine_time,
-        config.timecontrol_engine_inc,
-    );
-    let mut gauntlet_engine = Engine::from_path(
-        &config.engine_path.0,
-        999,
-        tcp1,
-        config.engine_path.1.clone(),
-    );
-    let tcp2 = TimeControl::Incremental(
-        config.timecontrol_enemies_time,
-        config.timecontrol_enemies_inc,
-    );
-    let mut engines: Vec<Engine> = Vec::new();
-    for (index, path) in config.enemies_paths.into_iter().enumerate() {
-        engines.push(Engine::from_path(&path.0, index, tcp2.clone(), path.1));
+pub fn start_self_play(
+    processors: usize,
+    engine: EngineConfig,
+    config: SelfplayConfig,
+    sprt: Option<SPRTConfig>,
+    match_directory: Option<String>,
+) {
+    //The pentanomial model needs every opening played with both colors
+    if sprt.is_some() && !config.openings.repeat {
+        panic!("SPRT needs repeated openings, set repeat in the openings section!");
     }
-    let mut db: Vec<GameState> = Vec::with_capacity(100_000);
-    let mut db_sequences: Vec<Vec<GameMove>> = Vec::with_capacity(100_000);
-    for database in config.opening_databases {
-        let mut database_loaded = load_db_until(&database, config.opening_load_untilply);
-        db.append(&mut database_loaded.0);
-        db_sequences.append(&mut database_loaded.1);
+    let mut gauntlet_engine = Engine::from_config(engine, 999, config.time_control);
+    let mut engines: Vec<Engine> = Vec::new();
+    for (index, enemy) in config.enemies.into_iter().enumerate() {
+        engines.push(Engine::from_config(enemy, index, config.time_control));
     }
+    let mut book = OpeningBook::load(&config.openings);
     println!(
         "{}",
         &format!(
-            "Loaded database with {} games found! Preparing games...",
-            db.len()
+            "Loaded {} different openings! Preparing games...",
+            book.len()
         )
     );
-    let queue: Arc<ThreadSafeQueue<PlayTask>> = Arc::new(load_openings_into_queue(
+    let tasks = load_openings_into_tasks(
         config.games / 2,
-        db,
-        db_sequences,
+        &mut book,
+        config.openings.repeat,
         &gauntlet_engine,
         &engines,
-    ));
-    let games = queue.len();
+    );
+    let mut sprt = sprt.map(SPRT::new);
+    let adjudication = config.adjudication.unwrap_or_default();
+
+    //Games which have been played before the match was interrupted only count
+    let mut match_directory = match_directory.map(|path| MatchDirectory::open(&path));
+    let mut tasks_to_play = Vec::with_capacity(tasks.len());
+    let mut game_records: Vec<GameRecord> = Vec::new();
+    for task in tasks {
+        if let Some(directory) = match_directory.as_mut() {
+            let task = directory.schedule(task);
+            if let Some(record) = directory.results.get(&task.id) {
+                game_records.extend(record.game.clone());
+                record.engine1.add_to(&mut gauntlet_engine);
+                record.engine2.add_to(&mut engines[record.engine2.id]);
+                if let Some(sprt) = sprt.as_mut() {
+                    sprt.add_game_result(task.pair_id, record.engine1_score);
+                }
+                continue;
+            }
+            tasks_to_play.push(task);
+        } else {
+            tasks_to_play.push(task);
+        }
+    }
+    if let Some(sprt) = sprt.as_ref() {
+        if sprt.status() != SPRTStatus::Continue {
+            println!("SPRT already finished: {}!", sprt.status());
+            tasks_to_play.clear();
+        }
+    }
+    let mut games = tasks_to_play.len();
     println!("Prepared {} games! Starting...", games);
+    let queue: Arc<ThreadSafeQueue<PlayTask>> = Arc::new(ThreadSafeQueue::new(tasks_to_play));
 
     let result_queue: Arc<ThreadSafeQueue<TaskResult>> =
         Arc::new(ThreadSafeQueue::new(Vec::with_capacity(100)));
-    let error_log = Arc::new(Logger::new("referee_error_log.txt", false));
-    let fen_log = Logger::new("pgns.pgn", true);
+    let (error_log, fen_log) = open_logs(match_directory.as_ref());
+    let error_log = Arc::new(error_log);
 
     //Start all childs
-    let mut childs = Vec::with_capacity(config.processors);
-    for _ in 0..config.processors {
+    let mut childs = Vec::with_capacity(processors);
+    for _ in 0..processors {
         let queue_clone = queue.clone();
         let res_clone = result_queue.clone();
         let log_clone = error_log.clone();
         childs.push(thread::spawn(move || {
-            start_self_play_thread(queue_clone, res_clone, log_clone);
+            start_self_play_thread(queue_clone, res_clone, log_clone, adjudication);
         }));
     }
 
     //Collect results
+    let played_before = game_records.len();
+    let mut status_line = StatusLine::new(played_before);
     let mut results_collected = 0;
     while results_collected < games {
         thread::sleep(Duration::from_millis(50));
-        if let Some(mut result) = result_queue.pop() {
+        if let Some(result) = result_queue.pop() {
             results_collected += 1;
-            println!("*-*-*-*-*-*-*-*-*-*-*-*-*-*-*-*-*-*-*-*-*-*-*-*-*");
-            println!("Game {} finished!", result.task.id);
-            if let Some(reason) = result.endcondition {
-                println!("Reason: {}", reason);
-            } else {
-                println!("Reason: Disqualification");
-            }
-            println!("*-*-*-*-*-*-*-*-*-*-*-*-*-*-*-*-*-*-*-*-*-*-*-*-*");
+            println!(
+                "Game {} finished: {} vs {} {} ({})",
+                result.task.id,
+                result.task.engine1.name,
+                result.task.engine2.name,
+                result.final_status,
+                result.reason()
+            );
             //Add engines
             gauntlet_engine.add(&result.task.engine1);
             engines[result.task.engine2.id].add(&result.task.engine2);
+            game_records.push(GameRecord::new(&result));
 
-            println!("-------------------------------------------------");
-            let (rank, descr, _) = gauntlet_engine.get_elo_gain();
-            println!("{}", rank);
-            let mut other: Vec<(String, String, f64)> = Vec::with_capacity(engines.len());
-            for engine in &engines {
-                other.push(engine.get_elo_gain());
-            }
-            other.sort_by(|a, b| {
-                if a.2 > b.2 {
-                    Ordering::Less
-                } else if (a.2 - b.2).abs() < std::f64::EPSILON {
-                    Ordering::Equal
-                } else {
-                    Ordering::Greater
+            if let Some(sprt) = sprt.as_mut() {
+                sprt.add_game_result(result.task.pair_id, result.engine1_score());
+                if sprt.status() != SPRTStatus::Continue && !queue.is_empty() {
+                    let mut skipped = 0;
+                    while queue.pop().is_some() {
+                        skipped += 1;
+                    }
+                    games -= skipped;
+                    println!(
+                        "SPRT finished: {}! Skipping {} games, waiting for {} running games",
+                        sprt.status(),
+                        skipped,
+                        games - results_collected
+                    );
                 }
-            });
-            for desc in &other {
-                println!("{}", desc.0);
             }
-            println!("-------------------------------------------------");
-            if (results_collected + 1) % 5 == 0 {
-                println!("+++++++++++++++++++++++++++++++++++++++++++++++++");
-                println!("{}", descr);
-                for desc in &other {
-                    println!("{}", desc.1);
-                }
-                println!("+++++++++++++++++++++++++++++++++++++++++++++++++");
+            let mut all_engines = vec![&gauntlet_engine];
+            all_engines.extend(engines.iter());
+            status_line.update(
+                played_before + results_collected,
+                played_before + games,
+                Some(&gauntlet_engine),
+                sprt.as_ref(),
+                &all_engines,
+            );
+            if results_collected % 25 == 0 {
+                print_standings(&gauntlet_engine, &engines);
             }
 
-            //Write all fens of game to pgn
-            let opening_moves = Some(result.task.opening_sequence.len());
-            let mut moves = result.task.opening_sequence;
-            if !result.move_sequence.is_empty() {
-                moves.append(&mut result.move_sequence);
-                let mut metadata = PGNMetadata::default();
-                metadata.fill_systemdata();
-                metadata.event_name = Some("FabChess local gauntlet".to_owned());
-                metadata.round = Some(format!("{}", result.task.id));
-                metadata.result = Some(result.final_status.to_string());
-                metadata.termination = Some(if result.endcondition.is_none() {
-                    "rules infraction".to_owned()
-                } else {
-                    let temp = result.endcondition.unwrap();
-                    match temp {
-                        EndConditionInformation::DrawByadjudication
-                        | EndConditionInformation::MateByadjudication => "adjudication",
-                        _ => "normal",
-                    }
-                    .to_owned()
-                });
-                metadata.white = Some(if result.task.p1_is_white {
-                    result.task.engine1.name.clone()
-                } else {
-                    result.task.engine2.name.clone()
-                });
-                metadata.black = Some(if result.task.p1_is_white {
-                    result.task.engine2.name.clone()
-                } else {
-                    result.task.engine1.name.clone()
-                });
-                fen_log.log(&get_pgn_string(&metadata, moves, opening_moves), false);
+            if let Some(directory) = match_directory.as_mut() {
+                directory.save_result(&result);
             }
+            save_transcript(&result, match_directory.as_ref());
+            //Write all fens of game to pgn
+            write_pgn(result, "FabChess local gauntlet", &fen_log);
         }
     }
     for child in childs {
         child.join().expect("Couldn't join thread");
     }
+    print_standings(&gauntlet_engine, &engines);
+    if let Some(sprt) = sprt.as_ref() {
+        println!("{}", sprt);
+    }
+    let mut all_engines = vec![&gauntlet_engine];
+    all_engines.extend(engines.iter());
+    let report = MatchReport::new(
+        "FabChess local gauntlet",
+        game_records,
+        &all_engines,
+        sprt.as_ref(),
+        status_line.elapsed(),
+    );
+    println!("{}", report);
+    report.write(&report_path(match_directory.as_ref()));
     println!("Testing finished!");
 }
 
+//Elo of all engines, enemies sorted by Elo, and their statistics
+fn print_standings(gauntlet_engine: &Engine, engines: &[Engine]) {
+    println!("+++++++++++++++++++++++++++++++++++++++++++++++++");
+    let (rank, descr, _) = gauntlet_engine.get_elo_gain();
+    println!("{}", rank);
+    let mut other: Vec<(String, String, f64)> = Vec::with_capacity(engines.len());
+    for engine in engines {
+        other.push(engine.get_elo_gain());
+    }
+    other.sort_by(|a, b| {
+        if a.2 > b.2 {
+            Ordering::Less
+        } else if (a.2 - b.2).abs() < std::f64::EPSILON {
+            Ordering::Equal
+        } else {
+            Ordering::Greater
+        }
+    });
+    for desc in &other {
+        println!("{}", desc.0);
+    }
+    println!("{}", descr);
+    for desc in &other {
+        println!("{}", desc.1);
+    }
+    println!("+++++++++++++++++++++++++++++++++++++++++++++++++");
+}
+
+pub fn write_pgn(mut result: TaskResult, event_name: &str, fen_log: &Logger) {
+    let opening_moves = Some(result.task.opening_sequence.len());
+    let mut moves = result.task.opening_sequence;
+    if !result.move_sequence.is_empty() {
+        moves.append(&mut result.move_sequence);
+        let mut metadata = PGNMetadata::default();
+        metadata.fill_systemdata();
+        metadata.event_name = Some(event_name.to_owned());
+        metadata.round = Some(format!("{}", result.task.id));
+        metadata.starting_position = result.task.start_fen.clone();
+        metadata.result = Some(result.final_status.to_string());
+        metadata.termination = Some(match (result.endcondition, result.failure) {
+            (Some(endcondition), _) => endcondition.termination().to_owned(),
+            (None, Some(failure)) => failure.termination().to_owned(),
+            (None, None) => "rules infraction".to_owned(),
+        });
+        metadata.white = Some(if result.task.p1_is_white {
+            result.task.engine1.name.clone()
+        } else {
+            result.task.engine2.name.clone()
+        });
+        metadata.black = Some(if result.task.p1_is_white {
+            result.task.engine2.name.clone()
+        } else {
+            result.task.engine1.name.clone()
+        });
+        let (white_tc, black_tc) = if result.task.p1_is_white {
+            (
+                result.task.engine1.time_control.config,
+                result.task.engine2.time_control.config,
+            )
+        } else {
+            (
+                result.task.engine2.time_control.config,
+                result.task.engine1.time_control.config,
+            )
+        };
+        if white_tc == black_tc {
+            metadata.time_control = Some(white_tc.pgn_tag());
+        } else {
+            metadata.white_time_control = Some(white_tc.pgn_tag());
+            metadata.black_time_control = Some(black_tc.pgn_tag());
+        }
+        fen_log.log(&get_pgn_string(&metadata, moves, opening_moves), false);
+    }
+}
+
 pub fn start_self_play_thread(
     queue: Arc<ThreadSafeQueue<PlayTask>>,
     result_queue: Arc<ThreadSafeQueue<TaskResult>>,
     error_log: Arc<Logger>,
+    adjudication: AdjudicationConfig,
 ) {
     while let Some(task) = queue.pop() {
         println!("Starting game {}", task.id);
-        let res = play_game(task, error_log.clone());
+        let res = play_game(task, error_log.clone(), adjudication);
         if res.endcondition.is_none() {
             thread::sleep(Duration::from_millis(150));
         }
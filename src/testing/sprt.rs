@@ -0,0 +1,183 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter, Result};
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub struct SPRTConfig {
+    pub elo0: f64,
+    pub elo1: f64,
+    pub alpha: f64,
+    pub beta: f64,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SPRTStatus {
+    Continue,
+    AcceptH0,
+    AcceptH1,
+}
+
+impl Display for SPRTStatus {
+    fn fmt(&self, formatter: &mut Formatter) -> Result {
+        write!(
+            formatter,
+            "{}",
+            match *self {
+                SPRTStatus::Continue => "Continue",
+                SPRTStatus::AcceptH0 => "H0 accepted",
+                SPRTStatus::AcceptH1 => "H1 accepted",
+            }
+        )
+    }
+}
+
+//Sequential probability ratio test on game pairs (same opening, colors reversed).
+//The pentanomial counts are indexed by the score of the first engine in the pair: 0, 0.5, 1, 1.5, 2
+pub struct SPRT {
+    pub config: SPRTConfig,
+    pub pentanomial: [usize; 5],
+    pending: HashMap<usize, f64>,
+}
+
+pub fn expected_score(elo: f64) -> f64 {
+    1. / (1. + 10f64.powf(-elo / 400.))
+}
+
+impl SPRT {
+    pub fn new(config: SPRTConfig) -> Self {
+        SPRT {
+            config,
+            pentanomial: [0; 5],
+            pending: HashMap::new(),
+        }
+    }
+
+    //Score is 1 for a win, 0.5 for a draw and 0 for a loss of the first engine
+    pub fn add_game_result(&mut self, pair_id: usize, score: f64) {
+        if let Some(other) = self.pending.remove(&pair_id) {
+            self.pentanomial[((other + score) * 2.).round() as usize] += 1;
+        } else {
+            self.pending.insert(pair_id, score);
+        }
+    }
+
+    pub fn pairs(&self) -> usize {
+        self.pentanomial.iter().sum()
+    }
+
+    pub fn bounds(&self) -> (f64, f64) {
+        (
+            (self.config.beta / (1. - self.config.alpha)).ln(),
+            ((1. - self.config.beta) / self.config.alpha).ln(),
+        )
+    }
+
+    //Generalized SPRT approximation of the log-likelihood ratio using the mean and variance of the pair scores
+    pub fn llr(&self) -> f64 {
+        let pairs = self.pairs() as f64;
+        if pairs == 0. {
+            return 0.;
+        }
+        let mut mean = 0.;
+        for (index, count) in self.pentanomial.iter().enumerate() {
+            mean += index as f64 / 4. * *count as f64 / pairs;
+        }
+        let mut variance = 0.;
+        for (index, count) in self.pentanomial.iter().enumerate() {
+            variance += (index as f64 / 4. - mean).powi(2) * *count as f64 / pairs;
+        }
+        if variance <= 0. {
+            return 0.;
+        }
+        let s0 = expected_score(self.config.elo0);
+        let s1 = expected_score(self.config.elo1);
+        pairs * (s1 - s0) * (2. * mean - s0 - s1) / (2. * variance)
+    }
+
+    pub fn status(&self) -> SPRTStatus {
+        let llr = self.llr();
+        let (lower, upper) = self.bounds();
+        if llr >= upper {
+            SPRTStatus::AcceptH1
+        } else if llr <= lower {
+            SPRTStatus::AcceptH0
+        } else {
+            SPRTStatus::Continue
+        }
+    }
+}
+
+impl Display for SPRT {
+    fn fmt(&self, formatter: &mut Formatter) -> Result {
+        let (lower, upper) = self.bounds();
+        write!(
+            formatter,
+            "SPRT elo0 {} elo1 {} alpha {} beta {}\nLLR {:.2} ({:.2}, {:.2}) Pairs {} Pentanomial [{}, {}, {}, {}, {}] Status: {}",
+            self.config.elo0,
+            self.config.elo1,
+            self.config.alpha,
+            self.config.beta,
+            self.llr(),
+            lower,
+            upper,
+            self.pairs(),
+            self.pentanomial[0],
+            self.pentanomial[1],
+            self.pentanomial[2],
+            self.pentanomial[3],
+            self.pentanomial[4],
+            self.status()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sprt() -> SPRT {
+        SPRT::new(SPRTConfig {
+            elo0: 0.,
+            elo1: 5.,
+            alpha: 0.05,
+            beta: 0.05,
+        })
+    }
+
+    #[test]
+    fn pentanomial_test() {
+        let mut sprt = sprt();
+        sprt.add_game_result(0, 1.);
+        sprt.add_game_result(1, 0.5);
+        assert_eq!(sprt.pairs(), 0);
+        sprt.add_game_result(0, 0.5);
+        sprt.add_game_result(1, 0.);
+        sprt.add_game_result(2, 1.);
+        sprt.add_game_result(2, 1.);
+        assert_eq!(sprt.pentanomial, [0, 1, 0, 1, 1]);
+    }
+
+    #[test]
+    fn sprt_test() {
+        let (lower, upper) = sprt().bounds();
+        assert!((lower + 2.944).abs() < 0.001);
+        assert!((upper - 2.944).abs() < 0.001);
+
+        //Clearly stronger engine
+        let mut sprt_h1 = sprt();
+        sprt_h1.pentanomial = [100, 1000, 3000, 1400, 200];
+        assert!(sprt_h1.llr() > upper);
+        assert_eq!(sprt_h1.status(), SPRTStatus::AcceptH1);
+
+        //Equally strong engines
+        let mut sprt_h0 = sprt();
+        sprt_h0.pentanomial = [200, 1200, 3000, 1200, 200];
+        assert!(sprt_h0.llr() < lower);
+        assert_eq!(sprt_h0.status(), SPRTStatus::AcceptH0);
+
+        //Not enough information
+        let mut sprt_continue = sprt();
+        sprt_continue.pentanomial = [2, 12, 30, 13, 2];
+        assert_eq!(sprt_continue.status(), SPRTStatus::Continue);
+    }
+}
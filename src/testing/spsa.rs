@@ -0,0 +1,208 @@
+use crate::search::params::{SearchParams, TUNABLE_PARAMETERS};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter, Result};
+
+//Exponents of the gain sequences recommended by Spall
+pub const SPSA_ALPHA: f64 = 0.602;
+pub const SPSA_GAMMA: f64 = 0.101;
+//Stability constant of the learning rate, relative to the amount of iterations
+pub const SPSA_STABILITY: f64 = 0.1;
+
+fn default_learning_rate() -> f64 {
+    0.002
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct SPSAParameter {
+    //Name of the engine's UCI option
+    pub name: String,
+    pub value: f64,
+    pub min: f64,
+    pub max: f64,
+    //Perturbation in the last iteration, it is larger in the first ones
+    pub step: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SPSAConfig {
+    pub iterations: usize,
+    //Game pairs played with the same perturbation
+    pub pairs_per_iteration: usize,
+    //Learning rate in the last iteration, relative to step^2
+    #[serde(default = "default_learning_rate")]
+    pub learning_rate: f64,
+    //All tunable search parameters of FabChess if empty
+    #[serde(default)]
+    pub parameters: Vec<SPSAParameter>,
+}
+
+//Simultaneous perturbation stochastic approximation. Every iteration one engine plays with all parameters
+//shifted by a random sign times the step and the other one with the opposite shift. The parameters move
+//towards the winner proportionally to the score difference
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SPSA {
+    pub config: SPSAConfig,
+    pub parameters: Vec<SPSAParameter>,
+    pub iteration: usize,
+}
+
+pub fn default_parameters() -> Vec<SPSAParameter> {
+    let mut params = SearchParams::default();
+    TUNABLE_PARAMETERS
+        .iter()
+        .map(|parameter| SPSAParameter {
+            name: parameter.name.to_owned(),
+            value: f64::from(*(parameter.field)(&mut params)),
+            min: f64::from(parameter.min),
+            max: f64::from(parameter.max),
+            step: f64::from(parameter.step),
+        })
+        .collect()
+}
+
+impl SPSA {
+    pub fn new(config: SPSAConfig) -> Self {
+        let parameters = if config.parameters.is_empty() {
+            default_parameters()
+        } else {
+            config.parameters.clone()
+        };
+        SPSA {
+            config,
+            parameters,
+            iteration: 0,
+        }
+    }
+
+    pub fn finished(&self) -> bool {
+        self.iteration >= self.config.iterations
+    }
+
+    fn step(&self, parameter: &SPSAParameter) -> f64 {
+        let iterations = self.config.iterations as f64;
+        parameter.step * (iterations / (self.iteration + 1) as f64).powf(SPSA_GAMMA)
+    }
+
+    fn learning_rate(&self, parameter: &SPSAParameter) -> f64 {
+        let iterations = self.config.iterations as f64;
+        let stability = SPSA_STABILITY * iterations;
+        self.config.learning_rate
+            * parameter.step
+            * parameter.step
+            * ((stability + iterations) / (stability + (self.iteration + 1) as f64))
+                .powf(SPSA_ALPHA)
+    }
+
+    //Random shift of every parameter in the current iteration
+    pub fn perturbation(&self) -> Vec<f64> {
+        let mut rng = rand::thread_rng();
+        self.parameters
+            .iter()
+            .map(|parameter| {
+                let sign = if rng.gen::<bool>() { 1. } else { -1. };
+                sign * self.step(parameter)
+            })
+            .collect()
+    }
+
+    //UCI options of the engine playing with the parameters shifted by sign times the perturbation
+    pub fn uci_options(&self, perturbation: &[f64], sign: f64) -> HashMap<String, String> {
+        self.parameters
+            .iter()
+            .zip(perturbation.iter())
+            .map(|(parameter, shift)| {
+                let value = (parameter.value + sign * shift)
+                    .max(parameter.min)
+                    .min(parameter.max);
+                (parameter.name.clone(), format!("{}", value.round()))
+            })
+            .collect()
+    }
+
+    //Result is the score of the engine with the positive shift minus the score of the other one
+    pub fn update(&mut self, perturbation: &[f64], result: f64) {
+        let changes: Vec<f64> = self
+            .parameters
+            .iter()
+            .zip(perturbation.iter())
+            .map(|(parameter, shift)| {
+                let step = self.step(parameter);
+                self.learning_rate(parameter) * result * shift / (step * step)
+            })
+            .collect();
+        for (parameter, change) in self.parameters.iter_mut().zip(changes) {
+            parameter.value = (parameter.value + change)
+                .max(parameter.min)
+                .min(parameter.max);
+        }
+        self.iteration += 1;
+    }
+}
+
+impl Display for SPSA {
+    fn fmt(&self, formatter: &mut Formatter) -> Result {
+        writeln!(
+            formatter,
+            "SPSA iteration {} of {}",
+            self.iteration, self.config.iterations
+        )?;
+        for parameter in self.parameters.iter() {
+            writeln!(formatter, "{:<25} {:.2}", parameter.name, parameter.value)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spsa() -> SPSA {
+        SPSA::new(SPSAConfig {
+            iterations: 100,
+            pairs_per_iteration: 4,
+            learning_rate: default_learning_rate(),
+            parameters: vec![SPSAParameter {
+                name: "FutilityMargin".to_owned(),
+                value: 90.,
+                min: 0.,
+                max: 300.,
+                step: 10.,
+            }],
+        })
+    }
+
+    #[test]
+    fn default_parameters_test() {
+        let parameters = default_parameters();
+        assert_eq!(parameters.len(), TUNABLE_PARAMETERS.len());
+        assert_eq!(parameters[0].name, "FutilityMargin");
+        assert_eq!(parameters[0].value, 90.);
+    }
+
+    #[test]
+    fn spsa_test() {
+        let mut spsa = spsa();
+        let perturbation = spsa.perturbation();
+        assert!(perturbation[0].abs() > 10.);
+        let plus = spsa.uci_options(&perturbation, 1.);
+        let minus = spsa.uci_options(&perturbation, -1.);
+        assert_ne!(plus["FutilityMargin"], minus["FutilityMargin"]);
+
+        //The parameters follow the winning engine
+        while !spsa.finished() {
+            spsa.update(&[10.], 4.);
+        }
+        assert!(spsa.parameters[0].value > 90.);
+        let mut spsa_minus = self::spsa();
+        spsa_minus.update(&[10.], -4.);
+        assert!(spsa_minus.parameters[0].value < 90.);
+
+        //And stay in bounds
+        let mut spsa_bounded = self::spsa();
+        spsa_bounded.update(&[10.], 1e9);
+        assert_eq!(spsa_bounded.parameters[0].value, 300.);
+    }
+}
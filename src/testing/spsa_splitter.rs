@@ -0,0 +1,108 @@
+use crate::selfplay_splitter::{start_self_play_thread, write_pgn};
+use crate::SelfplayConfig;
+use core::testing::match_state::open_logs;
+use core::testing::openings::{load_openings_into_tasks, OpeningBook};
+use core::testing::queue::ThreadSafeQueue;
+use core::testing::spsa::{SPSAConfig, SPSA};
+use core::testing::{Engine, EngineConfig};
+use core::testing::{PlayTask, TaskResult};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+//The tuning state after the last iteration, the tuning is resumed from it in a match directory
+pub const SPSA_FILE: &str = "spsa.json";
+
+//Tunes the UCI options of the engine with games against itself. Openings, time control and adjudication
+//are taken from the selfplay config
+pub fn start_spsa(
+    processors: usize,
+    engine: EngineConfig,
+    selfplay: SelfplayConfig,
+    config: SPSAConfig,
+    match_directory: Option<String>,
+) {
+    let state_path = match &match_directory {
+        Some(directory) => {
+            fs::create_dir_all(directory).expect("Unable to create match directory!");
+            PathBuf::from(directory).join(SPSA_FILE)
+        }
+        None => PathBuf::from(SPSA_FILE),
+    };
+    let mut spsa = if match_directory.is_some() && state_path.exists() {
+        let content = fs::read_to_string(&state_path).expect("Unable to read SPSA state!");
+        let spsa: SPSA = serde_json::from_str(&content).expect("Invalid SPSA state!");
+        println!("Resuming SPSA from {}", state_path.display());
+        spsa
+    } else {
+        SPSA::new(config)
+    };
+    println!("{}", spsa);
+
+    let engine = Engine::from_config(engine, 999, selfplay.time_control);
+    let mut book = OpeningBook::load(&selfplay.openings);
+    let adjudication = selfplay.adjudication.unwrap_or_default();
+    let (error_log, fen_log) = open_logs(None);
+    let error_log = Arc::new(error_log);
+
+    while !spsa.finished() {
+        let perturbation = spsa.perturbation();
+        let mut plus = engine.clone_fresh();
+        plus.config
+            .uci_options
+            .extend(spsa.uci_options(&perturbation, 1.));
+        let mut minus = engine.clone_fresh();
+        minus.id = 0;
+        minus
+            .config
+            .uci_options
+            .extend(spsa.uci_options(&perturbation, -1.));
+        if book.len() < spsa.config.pairs_per_iteration {
+            book = OpeningBook::load(&selfplay.openings);
+        }
+        let tasks = load_openings_into_tasks(
+            spsa.config.pairs_per_iteration,
+            &mut book,
+            true,
+            &plus,
+            &[minus],
+        );
+        let games = tasks.len();
+        let queue: Arc<ThreadSafeQueue<PlayTask>> = Arc::new(ThreadSafeQueue::new(tasks));
+        let result_queue: Arc<ThreadSafeQueue<TaskResult>> =
+            Arc::new(ThreadSafeQueue::new(Vec::with_capacity(games)));
+        let mut childs = Vec::with_capacity(processors);
+        for _ in 0..processors {
+            let queue_clone = queue.clone();
+            let res_clone = result_queue.clone();
+            let log_clone = error_log.clone();
+            childs.push(thread::spawn(move || {
+                start_self_play_thread(queue_clone, res_clone, log_clone, adjudication);
+            }));
+        }
+
+        //Score of the engine with the positive shift minus the score of the other one
+        let mut score = 0.;
+        let mut results_collected = 0;
+        while results_collected < games {
+            thread::sleep(Duration::from_millis(50));
+            if let Some(result) = result_queue.pop() {
+                results_collected += 1;
+                score += 2. * result.engine1_score() - 1.;
+                write_pgn(result, "FabChess SPSA", &fen_log);
+            }
+        }
+        for child in childs {
+            child.join().expect("Couldn't join thread");
+        }
+
+        spsa.update(&perturbation, score);
+        println!("Iteration score {:+}", score);
+        println!("{}", spsa);
+        fs::write(&state_path, serde_json::to_string_pretty(&spsa).unwrap())
+            .expect("Unable to write SPSA state!");
+    }
+    println!("Tuning finished!");
+}
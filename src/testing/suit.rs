@@ -1,26 +1,51 @@
 use crate::write_to_buf;
 use crate::STS_SUB_SUITS;
-use core::board_representation::game_state::{GameMove, GameState};
+use core::board_representation::game_state::GameMove;
 use core::board_representation::game_state_attack_container::GameStateAttackContainer;
 use core::move_generation::movegen;
-use core::pgn::pgn_reader::parse_move;
+use core::testing::epd::{load_epd, EpdPosition};
 use core::testing::queue::ThreadSafeQueue;
+use core::testing::{fetch_info, find_move, EngineConfig};
 use std::fmt::{Display, Formatter, Result};
-use std::fs::File;
 use std::io::prelude::*;
 use std::io::{BufReader, BufWriter};
-use std::process::{Command, Stdio};
+use std::process::Stdio;
 use std::sync::Arc;
 use std::thread;
+use std::time::Instant;
 
-pub struct TestSuitResult {
-    suit: SuitTest,
-    mv: GameMove,
+pub struct PositionResult {
+    pub index: usize,
+    pub position: EpdPosition,
+    pub best_move: Option<GameMove>,
+    pub solved: bool,
+    //Milliseconds and nodes after which the engine found the solution and didn't change its mind anymore
+    pub time_to_solution: Option<u128>,
+    pub nodes_to_solution: Option<u64>,
 }
 
-pub struct AwardedMove {
-    mv: GameMove,
-    score: f64,
+impl PositionResult {
+    pub fn name(&self) -> String {
+        match self.position.id.as_ref() {
+            Some(id) => id.clone(),
+            None => format!("#{}", self.index + 1),
+        }
+    }
+}
+
+impl Display for PositionResult {
+    fn fmt(&self, formatter: &mut Formatter) -> Result {
+        let optional = |value: Option<String>| value.unwrap_or_else(|| "-".to_owned());
+        write!(
+            formatter,
+            "{}\t{}\tbestmove {}\ttime {}\tnodes {}",
+            self.name(),
+            if self.solved { "solved" } else { "failed" },
+            optional(self.best_move.map(|mv| format!("{:?}", mv))),
+            optional(self.time_to_solution.map(|time| format!("{}ms", time))),
+            optional(self.nodes_to_solution.map(|nodes| format!("{}", nodes))),
+        )
+    }
 }
 
 pub struct SuitInfos {
@@ -74,167 +99,216 @@ impl Display for SuitInfos {
     }
 }
 
-pub struct SuitTest {
-    game_state: GameState,
-    optimal_moves: Vec<AwardedMove>,
-    subsuit: isize,
-}
-
-impl SuitTest {
-    pub fn award_points(&self, mv: &GameMove) -> usize {
-        for other_mv in &self.optimal_moves {
-            if other_mv.mv == *mv {
-                return other_mv.score as usize;
+pub fn start_suit(p1: &EngineConfig, processors: usize, path_to_suit: &str, move_time: u64) {
+    let results = run_suite(p1, processors, load_epd(path_to_suit), move_time, None);
+    for result in results.iter() {
+        println!("{}", result);
+    }
+    print_summary(&results);
+    //STS style points
+    if results
+        .iter()
+        .any(|result| !result.position.awarded_moves.is_empty())
+    {
+        let mut infos = SuitInfos::new(move_time);
+        for result in results.iter() {
+            infos.positions += 1;
+            let points = result.best_move.map_or(0, |mv| result.position.points(&mv));
+            infos.points += points;
+            let subsuit = result
+                .position
+                .id
+                .as_ref()
+                .and_then(|id| match_sub_suit(id));
+            if let Some(subsuit) = subsuit {
+                infos.subsuit_positions[subsuit] += 1;
+                infos.subsuit_points[subsuit] += points;
+            }
+            if points == 10 {
+                infos.optimal_moves_found += 1;
+                if let Some(subsuit) = subsuit {
+                    infos.subsuit_optimal_moves_found[subsuit] += 1;
+                }
             }
         }
-        0
+        //Format SuitInfos
+        println!("{}", infos);
     }
+    println!("Suit Testing finished!");
 }
 
-pub fn start_suit(p1: &str, processors: usize, path_to_suit: &str, move_time: u64) {
-    let queue = Arc::new(ThreadSafeQueue::new(load_suit(path_to_suit)));
-    let resultqueue: Arc<ThreadSafeQueue<TestSuitResult>> =
-        Arc::new(ThreadSafeQueue::new(Vec::new()));
+pub fn print_summary(results: &[PositionResult]) {
+    let solved: Vec<&PositionResult> = results.iter().filter(|result| result.solved).collect();
+    println!("Solved: {}/{}", solved.len(), results.len());
+    if !solved.is_empty() {
+        let time: u128 = solved
+            .iter()
+            .map(|result| result.time_to_solution.unwrap_or(0))
+            .sum();
+        let nodes: u64 = solved
+            .iter()
+            .map(|result| result.nodes_to_solution.unwrap_or(0))
+            .sum();
+        println!(
+            "Average time to solution: {}ms, average nodes to solution: {}",
+            time / solved.len() as u128,
+            nodes / solved.len() as u64
+        );
+    }
+}
 
-    let mut infos = SuitInfos::new(move_time);
+//Plays all positions with the given movetime. With stop_depth the search is stopped as soon as
+//the engine has the solution at that depth. The results are in the order of the positions
+pub fn run_suite(
+    p1: &EngineConfig,
+    processors: usize,
+    positions: Vec<EpdPosition>,
+    move_time: u64,
+    stop_depth: Option<usize>,
+) -> Vec<PositionResult> {
+    let queue = Arc::new(ThreadSafeQueue::new(
+        positions.into_iter().enumerate().collect(),
+    ));
+    let resultqueue: Arc<ThreadSafeQueue<PositionResult>> =
+        Arc::new(ThreadSafeQueue::new(Vec::new()));
 
     let mut childs = Vec::with_capacity(processors);
     for _ in 0..processors {
         let queue_clone = queue.clone();
         let resultqueue_clone = resultqueue.clone();
-        let engine = p1.to_owned();
+        let engine = p1.clone();
         childs.push(thread::spawn(move || {
-            suit_thread(engine, queue_clone, resultqueue_clone, move_time)
+            suit_thread(
+                engine,
+                queue_clone,
+                resultqueue_clone,
+                move_time,
+                stop_depth,
+            )
         }));
     }
     for child in childs {
         child.join().expect("Couldn't join thread");
     }
+    let mut results = Vec::new();
     while let Some(res) = resultqueue.pop() {
-        infos.positions += 1;
-        let points = res.suit.award_points(&res.mv);
-        infos.points += points;
-        if res.suit.subsuit != -1 {
-            infos.subsuit_positions[res.suit.subsuit as usize] += 1;
-            infos.subsuit_points[res.suit.subsuit as usize] += points;
-        }
-        if points == 10 {
-            infos.optimal_moves_found += 1;
-            if res.suit.subsuit != -1 {
-                infos.subsuit_optimal_moves_found[res.suit.subsuit as usize] += 1;
-            }
-        }
+        results.push(res);
     }
-    //Format SuitInfos
-    println!("{}", infos);
-    println!("Suit Testing finished!");
+    results.sort_by_key(|result| result.index);
+    results
 }
 
 fn suit_thread(
-    p1: String,
-    queue: Arc<ThreadSafeQueue<SuitTest>>,
-    resultqueue: Arc<ThreadSafeQueue<TestSuitResult>>,
+    p1: EngineConfig,
+    queue: Arc<ThreadSafeQueue<(usize, EpdPosition)>>,
+    resultqueue: Arc<ThreadSafeQueue<PositionResult>>,
     move_time: u64,
+    stop_depth: Option<usize>,
 ) {
     let mut movelist = movegen::MoveList::default();
     let mut attack_container = GameStateAttackContainer::default();
-    let mut child = Command::new(p1.to_string())
+    let mut child = p1
+        .command()
         .stdin(Stdio::piped())
         .stdout(Stdio::piped())
         .spawn()
         .expect("Failed to execute command!");
     let mut child_in = BufWriter::new(child.stdin.as_mut().unwrap());
     let mut child_out = BufReader::new(child.stdout.as_mut().unwrap());
+    let mut buffer = String::new();
+    let mut wait_for = |child_out: &mut BufReader<_>, token: &str| loop {
+        buffer.clear();
+        if child_out.read_line(&mut buffer).unwrap() == 0 {
+            panic!("Engine {} quit unexpectedly!", p1.path);
+        }
+        if buffer.trim() == token {
+            break;
+        }
+    };
+    write_to_buf(&mut child_in, "uci\n");
+    wait_for(&mut child_out, "uciok");
+    for (name, value) in &p1.uci_options {
+        write_to_buf(
+            &mut child_in,
+            &format!("setoption name {} value {}\n", name, value),
+        );
+    }
     let mut line = String::new();
-    while let Some(test) = queue.pop() {
-        let str = format!(
-            "ucinewgame\nposition fen {}\n go movetime {}\n",
-            test.game_state.to_fen(),
-            move_time
+    while let Some((index, position)) = queue.pop() {
+        write_to_buf(&mut child_in, "ucinewgame\nisready\n");
+        wait_for(&mut child_out, "readyok");
+        attack_container.write_state(&position.state);
+        movegen::generate_moves(&position.state, false, &mut movelist, &attack_container);
+        let parse = |mv: &str| {
+            let (from, to, promotion) = GameMove::string_to_move(mv);
+            find_move(from, to, promotion, &movelist)
+        };
+        write_to_buf(
+            &mut child_in,
+            &format!("position fen {}\ngo movetime {}\n", position.fen, move_time),
         );
-        write_to_buf(&mut child_in, &str);
-        loop {
+        let before = Instant::now();
+        //Time and nodes when the current principal variation started with a solution
+        let mut solved_since: Option<(u128, Option<u64>)> = None;
+        let mut mate = None;
+        let mut nodes = None;
+        let mut stopped = false;
+        let best_move = loop {
             line.clear();
-            child_out.read_line(&mut line).unwrap();
-            let cmd: Vec<&str> = line.split(' ').collect();
-            if cmd[0] == "bestmove" {
-                let bm = cmd[1].trim();
-                attack_container.write_state(&test.game_state);
-                let (mv, _) = parse_move(
-                    &test.game_state,
-                    &bm.to_owned(),
-                    &mut movelist,
-                    &attack_container,
-                );
-                resultqueue.push(TestSuitResult { suit: test, mv });
-                break;
+            if child_out.read_line(&mut line).unwrap() == 0 {
+                panic!("Engine {} quit unexpectedly!", p1.path);
             }
-        }
-    }
-    write_to_buf(&mut child_in, "quit\n");
-}
-
-fn load_suit(path_to_suit: &str) -> Vec<SuitTest> {
-    let mut movelist = movegen::MoveList::default();
-    let mut attack_container = GameStateAttackContainer::default();
-    let mut res = Vec::with_capacity(30);
-    let mut file: File = File::open(path_to_suit).expect("Unable to open file");
-    let mut contents = String::new();
-    file.read_to_string(&mut contents)
-        .expect("Unable to read the file");
-    let split = contents.split('\n');
-    for line in split {
-        let linevec = line.split(';').collect::<Vec<&str>>();
-        if linevec.len() == 1 {
-            break;
-        }
-        let fenbmvec = linevec[0].split("bm").collect::<Vec<&str>>();
-        let state = GameState::from_fen(fenbmvec[0].trim_end());
-        let mut optimal_moves: Vec<AwardedMove> = Vec::with_capacity(4);
-        let mut subsuit_index = -1;
-        for otherlines in linevec {
-            if otherlines.trim().starts_with("c0") {
-                let comment_line = otherlines.trim();
-                let comment_line = comment_line.replace("c0", "");
-                let comment_line = comment_line.replace("\"", "");
-                let comment_line = comment_line.split(',').collect::<Vec<&str>>();
-                for optimal_move_desc in comment_line {
-                    let move_desc_split = optimal_move_desc.split('=').collect::<Vec<&str>>();
-                    //println!("MoveDesc: {:?}", move_desc_split);
-                    attack_container.write_state(&state);
-                    let (move_desc, _) = parse_move(
-                        &state,
-                        &move_desc_split[0].trim().to_owned(),
-                        &mut movelist,
-                        &attack_container,
-                    );
-                    let score = move_desc_split[1].parse::<u64>().unwrap();
-                    optimal_moves.push(AwardedMove {
-                        mv: move_desc,
-                        score: score as f64,
-                    });
+            let cmd: Vec<&str> = line.split_whitespace().collect();
+            if cmd.first() == Some(&"bestmove") {
+                break cmd.get(1).and_then(|mv| parse(mv));
+            } else if cmd.first() == Some(&"info") {
+                let info = fetch_info(line.clone());
+                if info.nodes.is_some() {
+                    nodes = info.nodes;
+                }
+                let pv_move = match info.pv_move.as_ref().and_then(|mv| parse(mv)) {
+                    Some(mv) => mv,
+                    None => continue,
+                };
+                mate = info.mate;
+                if position.solved_by(&pv_move, mate) {
+                    if solved_since.is_none() {
+                        solved_since = Some((before.elapsed().as_millis(), nodes));
+                    }
+                    if let (Some(stop_depth), Some(depth)) = (stop_depth, info.depth) {
+                        if depth >= stop_depth && !stopped {
+                            write_to_buf(&mut child_in, "stop\n");
+                            stopped = true;
+                        }
+                    }
+                } else {
+                    solved_since = None;
                 }
-            } else if otherlines.trim().starts_with("id") {
-                subsuit_index = match_sub_suit(otherlines);
             }
-        }
-        res.push(SuitTest {
-            game_state: state,
-            optimal_moves,
-            subsuit: subsuit_index,
+        };
+        let solved = best_move.map_or(false, |mv| position.solved_by(&mv, mate));
+        let (time_to_solution, nodes_to_solution) = if solved {
+            match solved_since {
+                Some((time, solved_nodes)) => (Some(time), solved_nodes),
+                None => (Some(before.elapsed().as_millis()), nodes),
+            }
+        } else {
+            (None, None)
+        };
+        resultqueue.push(PositionResult {
+            index,
+            position,
+            best_move,
+            solved,
+            time_to_solution,
+            nodes_to_solution,
         });
     }
-    println!("Loaded {} positions from Suite!", res.len());
-    res
+    write_to_buf(&mut child_in, "quit\n");
 }
 
-fn match_sub_suit(desc: &str) -> isize {
-    let mut res = -1;
-    for (i, subsuits) in STS_SUB_SUITS.iter().enumerate() {
-        if desc.contains(subsuits) {
-            res = i as isize;
-            break;
-        }
-    }
-    res
+fn match_sub_suit(desc: &str) -> Option<usize> {
+    STS_SUB_SUITS
+        .iter()
+        .position(|subsuit| desc.contains(subsuit))
 }
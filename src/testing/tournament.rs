@@ -0,0 +1,388 @@
+use crate::testing::adjudication::AdjudicationConfig;
+use crate::testing::clock::TimeControlConfig;
+use crate::testing::openings::OpeningConfig;
+use crate::testing::EngineConfig;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter, Result};
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum TournamentMode {
+    RoundRobin,
+    DoubleRoundRobin,
+    Swiss,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TournamentConfig {
+    pub mode: TournamentMode,
+    pub engines: Vec<EngineConfig>,
+    //Round robin: how often the whole schedule is repeated
+    pub cycles: usize,
+    //Swiss: amount of rounds, every pairing plays one opening with both colors
+    pub rounds: usize,
+    pub openings: OpeningConfig,
+    //Time control of every engine which doesn't specify its own
+    pub time_control: TimeControlConfig,
+    pub adjudication: Option<AdjudicationConfig>,
+}
+
+//A pairing of (white, black) engine indices. Pairings with the same opening_group share their opening
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Pairing {
+    pub white: usize,
+    pub black: usize,
+    pub opening_group: usize,
+}
+
+pub fn round_robin_pairings(engines: usize, cycles: usize, double: bool) -> Vec<Pairing> {
+    let mut res = Vec::new();
+    let mut opening_group = 0;
+    for cycle in 0..cycles {
+        for i in 0..engines {
+            for j in i + 1..engines {
+                if double {
+                    res.push(Pairing {
+                        white: i,
+                        black: j,
+                        opening_group,
+                    });
+                    res.push(Pairing {
+                        white: j,
+                        black: i,
+                        opening_group,
+                    });
+                } else if (i + j + cycle) % 2 == 0 {
+                    res.push(Pairing {
+                        white: i,
+                        black: j,
+                        opening_group,
+                    });
+                } else {
+                    res.push(Pairing {
+                        white: j,
+                        black: i,
+                        opening_group,
+                    });
+                }
+                opening_group += 1;
+            }
+        }
+    }
+    res
+}
+
+//Pairs engines with similar scores that haven't played each other yet. With an odd amount of engines,
+//the lowest ranked engine without a bye gets one. Returns the pairings (each with both colors) and the bye
+pub fn swiss_pairings(
+    crosstable: &Crosstable,
+    byes: &[usize],
+    first_opening_group: usize,
+) -> (Vec<Pairing>, Option<usize>) {
+    let mut standings: Vec<usize> = (0..crosstable.names.len()).collect();
+    standings.sort_by(|a, b| {
+        crosstable
+            .points(*b)
+            .partial_cmp(&crosstable.points(*a))
+            .unwrap()
+    });
+    let mut bye = None;
+    if standings.len() % 2 == 1 {
+        let index = standings
+            .iter()
+            .rposition(|engine| !byes.contains(engine))
+            .unwrap_or(standings.len() - 1);
+        bye = Some(standings.remove(index));
+    }
+    let mut res = Vec::new();
+    let mut opening_group = first_opening_group;
+    while !standings.is_empty() {
+        let first = standings.remove(0);
+        //Prefer the highest ranked opponent which hasn't been played yet
+        let index = standings
+            .iter()
+            .position(|other| crosstable.games_between(first, *other) == 0)
+            .unwrap_or(0);
+        let second = standings.remove(index);
+        res.push(Pairing {
+            white: first,
+            black: second,
+            opening_group,
+        });
+        res.push(Pairing {
+            white: second,
+            black: first,
+            opening_group,
+        });
+        opening_group += 1;
+    }
+    (res, bye)
+}
+
+pub struct Crosstable {
+    pub names: Vec<String>,
+    //results[i][j] = (wins, draws, losses) of engine i against engine j
+    pub results: Vec<Vec<(usize, usize, usize)>>,
+    pub bye_points: Vec<f64>,
+}
+
+impl Crosstable {
+    pub fn new(names: Vec<String>) -> Self {
+        let n = names.len();
+        Crosstable {
+            names,
+            results: vec![vec![(0, 0, 0); n]; n],
+            bye_points: vec![0.; n],
+        }
+    }
+
+    //Score is 1 for a win, 0.5 for a draw and 0 for a loss of the first engine
+    pub fn add_result(&mut self, engine: usize, other: usize, score: f64) {
+        if score > 0.75 {
+            self.results[engine][other].0 += 1;
+            self.results[other][engine].2 += 1;
+        } else if score > 0.25 {
+            self.results[engine][other].1 += 1;
+            self.results[other][engine].1 += 1;
+        } else {
+            self.results[engine][other].2 += 1;
+            self.results[other][engine].0 += 1;
+        }
+    }
+
+    pub fn games_between(&self, engine: usize, other: usize) -> usize {
+        let (w, d, l) = self.results[engine][other];
+        w + d + l
+    }
+
+    pub fn games(&self, engine: usize) -> usize {
+        (0..self.names.len())
+            .map(|other| self.games_between(engine, other))
+            .sum()
+    }
+
+    pub fn score_against(&self, engine: usize, other: usize) -> f64 {
+        let (w, d, _) = self.results[engine][other];
+        w as f64 + d as f64 / 2.
+    }
+
+    pub fn points(&self, engine: usize) -> f64 {
+        (0..self.names.len())
+            .map(|other| self.score_against(engine, other))
+            .sum::<f64>()
+            + self.bye_points[engine]
+    }
+
+    //Maximum likelihood fit of a Bradley-Terry model where a draw counts as half a win and half a loss.
+    //Returns (elo, 95% error margin) per engine, the elos are normalized to an average of 0
+    pub fn ml_elo(&self) -> Vec<(f64, f64)> {
+        let n = self.names.len();
+        let mut gamma = vec![1.; n];
+        //Step 1. Minorization-maximization iterations
+        for _ in 0..10_000 {
+            let mut max_change: f64 = 0.;
+            let mut new_gamma = vec![1.; n];
+            for i in 0..n {
+                let score: f64 = (0..n).map(|j| self.score_against(i, j)).sum();
+                let mut denominator = 0.;
+                for j in 0..n {
+                    if i != j {
+                        denominator += self.games_between(i, j) as f64 / (gamma[i] + gamma[j]);
+                    }
+                }
+                //Engines without any game or without any point can't be fitted, clamp them
+                new_gamma[i] = if denominator == 0. {
+                    1.
+                } else {
+                    (score.max(0.01)) / denominator
+                };
+            }
+            //Normalize geometric mean to 1
+            let log_mean = new_gamma.iter().map(|g: &f64| g.ln()).sum::<f64>() / n as f64;
+            for g in new_gamma.iter_mut() {
+                *g /= log_mean.exp();
+            }
+            for i in 0..n {
+                max_change = max_change.max((new_gamma[i].ln() - gamma[i].ln()).abs());
+            }
+            gamma = new_gamma;
+            if max_change < 1e-10 {
+                break;
+            }
+        }
+        //Step 2. Error margins from the diagonal of the fisher information
+        let elo_per_ln = 400. / 10f64.ln();
+        (0..n)
+            .map(|i| {
+                let mut information = 0.;
+                for j in 0..n {
+                    if i != j {
+                        let p = gamma[i] / (gamma[i] + gamma[j]);
+                        information += self.games_between(i, j) as f64 * p * (1. - p);
+                    }
+                }
+                let error = if information > 0. {
+                    1.96 * elo_per_ln / information.sqrt()
+                } else {
+                    f64::INFINITY
+                };
+                (gamma[i].ln() * elo_per_ln, error)
+            })
+            .collect()
+    }
+
+    pub fn pair_results(&self) -> String {
+        let mut res_str = String::new();
+        for i in 0..self.names.len() {
+            for j in i + 1..self.names.len() {
+                let (w, d, l) = self.results[i][j];
+                if w + d + l > 0 {
+                    res_str.push_str(&format!(
+                        "{} vs {}: +{} ={} -{} ({:.1}/{})\n",
+                        self.names[i],
+                        self.names[j],
+                        w,
+                        d,
+                        l,
+                        self.score_against(i, j),
+                        w + d + l
+                    ));
+                }
+            }
+        }
+        res_str
+    }
+}
+
+impl Display for Crosstable {
+    fn fmt(&self, formatter: &mut Formatter) -> Result {
+        let elos = self.ml_elo();
+        let mut order: Vec<usize> = (0..self.names.len()).collect();
+        order.sort_by(|a, b| {
+            self.points(*b)
+                .partial_cmp(&self.points(*a))
+                .unwrap()
+                .then(elos[*b].0.partial_cmp(&elos[*a].0).unwrap())
+        });
+        let name_width = self
+            .names
+            .iter()
+            .map(|name| name.len())
+            .max()
+            .unwrap_or(4)
+            .max(4);
+        let mut res_str = String::new();
+        res_str.push_str(&format!(
+            "{:>3} {:<width$} {:>8} {:>7} {:>7} {:>6}",
+            "#",
+            "Name",
+            "Elo",
+            "+/-",
+            "Points",
+            "Games",
+            width = name_width
+        ));
+        for rank in 0..order.len() {
+            res_str.push_str(&format!(" {:>7}", rank + 1));
+        }
+        res_str.push('\n');
+        for (rank, engine) in order.iter().enumerate() {
+            res_str.push_str(&format!(
+                "{:>3} {:<width$} {:>8.1} {:>7.1} {:>7.1} {:>6}",
+                rank + 1,
+                self.names[*engine],
+                elos[*engine].0,
+                elos[*engine].1,
+                self.points(*engine),
+                self.games(*engine),
+                width = name_width
+            ));
+            for other in order.iter() {
+                if other == engine {
+                    res_str.push_str(&format!(" {:>7}", "---"));
+                } else {
+                    res_str.push_str(&format!(
+                        " {:>7}",
+                        format!(
+                            "{:.1}/{}",
+                            self.score_against(*engine, *other),
+                            self.games_between(*engine, *other)
+                        )
+                    ));
+                }
+            }
+            res_str.push('\n');
+        }
+        write!(formatter, "{}", res_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_robin_test() {
+        let single = round_robin_pairings(4, 1, false);
+        assert_eq!(single.len(), 6);
+        let double = round_robin_pairings(4, 2, true);
+        assert_eq!(double.len(), 24);
+        for i in 0..4 {
+            assert_eq!(double.iter().filter(|p| p.white == i).count(), 6);
+            assert_eq!(double.iter().filter(|p| p.black == i).count(), 6);
+        }
+        //Both colors of a double round robin game share the opening
+        assert_eq!(double[0].opening_group, double[1].opening_group);
+        assert_ne!(double[1].opening_group, double[2].opening_group);
+        //Colors alternate between cycles in a single round robin
+        let two_cycles = round_robin_pairings(3, 2, false);
+        assert_eq!(two_cycles[0].white, two_cycles[3].black);
+    }
+
+    #[test]
+    fn swiss_test() {
+        let mut crosstable = Crosstable::new(vec![
+            "A".to_owned(),
+            "B".to_owned(),
+            "C".to_owned(),
+            "D".to_owned(),
+            "E".to_owned(),
+        ]);
+        let (pairings, bye) = swiss_pairings(&crosstable, &[], 0);
+        assert_eq!(pairings.len(), 4);
+        assert_eq!(bye, Some(4));
+        crosstable.add_result(0, 1, 1.);
+        crosstable.add_result(1, 0, 0.5);
+        crosstable.add_result(2, 3, 1.);
+        crosstable.add_result(3, 2, 1.);
+        crosstable.bye_points[4] += 1.;
+        let (pairings, bye) = swiss_pairings(&crosstable, &[4], 2);
+        //A has 1.5, the engine with the bye has 1 point but can't get a second bye
+        assert_ne!(bye, Some(4));
+        for pairing in pairings.iter() {
+            assert_eq!(crosstable.games_between(pairing.white, pairing.black), 0);
+        }
+        assert_eq!(pairings[0].opening_group, 2);
+    }
+
+    #[test]
+    fn ml_elo_test() {
+        let mut crosstable = Crosstable::new(vec!["A".to_owned(), "B".to_owned(), "C".to_owned()]);
+        //A scores 76% against B and B scores 76% against C, which is about 200 elo each
+        for _ in 0..76 {
+            crosstable.add_result(0, 1, 1.);
+            crosstable.add_result(1, 2, 1.);
+        }
+        for _ in 0..24 {
+            crosstable.add_result(0, 1, 0.);
+            crosstable.add_result(1, 2, 0.);
+        }
+        let elos = crosstable.ml_elo();
+        assert!((elos[0].0 - 200.).abs() < 5.);
+        assert!(elos[1].0.abs() < 5.);
+        assert!((elos[2].0 + 200.).abs() < 5.);
+        //B played twice as many games and has the smallest error margin
+        assert!(elos[1].1 < elos[0].1);
+        assert!(elos[0].1 > 50. && elos[0].1 < 150.);
+        assert_eq!(crosstable.points(1), 100.);
+    }
+}
@@ -0,0 +1,242 @@
+use crate::selfplay_splitter::{start_self_play_thread, write_pgn};
+use core::logging::Logger;
+use core::testing::adjudication::AdjudicationConfig;
+use core::testing::match_state::{open_logs, report_path, save_transcript, MatchDirectory};
+use core::testing::openings::{Opening, OpeningBook};
+use core::testing::queue::ThreadSafeQueue;
+use core::testing::report::{GameRecord, MatchReport, StatusLine};
+use core::testing::tournament::{
+    round_robin_pairings, swiss_pairings, Crosstable, Pairing, TournamentConfig, TournamentMode,
+};
+use core::testing::Engine;
+use core::testing::{PlayTask, TaskResult};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+//Points for a swiss round without opponent, the same as drawing both games
+const SWISS_BYE_POINTS: f64 = 1.;
+
+pub struct TournamentState {
+    pub engines: Vec<Engine>,
+    pub crosstable: Crosstable,
+    pub processors: usize,
+    pub adjudication: AdjudicationConfig,
+    pub error_log: Arc<Logger>,
+    pub fen_log: Logger,
+    pub openings: OpeningBook,
+    //Play every opening with colors reversed
+    pub repeat: bool,
+    pub next_id: usize,
+    pub match_directory: Option<MatchDirectory>,
+    pub game_records: Vec<GameRecord>,
+    pub status_line: StatusLine,
+}
+
+pub fn start_tournament(
+    processors: usize,
+    tournament: TournamentConfig,
+    match_directory: Option<String>,
+) {
+    let mut engines: Vec<Engine> = Vec::with_capacity(tournament.engines.len());
+    for (index, engine_config) in tournament.engines.iter().enumerate() {
+        engines.push(Engine::from_config(
+            engine_config.clone(),
+            index,
+            tournament.time_control,
+        ));
+    }
+    let openings = OpeningBook::load(&tournament.openings);
+    println!("Loaded {} different openings!", openings.len());
+    let names = engines.iter().map(|engine| engine.name.clone()).collect();
+    let match_directory = match_directory.map(|path| MatchDirectory::open(&path));
+    let (error_log, fen_log) = open_logs(match_directory.as_ref());
+    let mut state = TournamentState {
+        engines,
+        crosstable: Crosstable::new(names),
+        processors,
+        adjudication: tournament.adjudication.unwrap_or_default(),
+        error_log: Arc::new(error_log),
+        fen_log,
+        openings,
+        repeat: tournament.openings.repeat,
+        next_id: 0,
+        match_directory,
+        game_records: Vec::new(),
+        status_line: StatusLine::new(0),
+    };
+
+    match tournament.mode {
+        TournamentMode::RoundRobin | TournamentMode::DoubleRoundRobin => {
+            let pairings = round_robin_pairings(
+                state.engines.len(),
+                tournament.cycles,
+                tournament.mode == TournamentMode::DoubleRoundRobin,
+            );
+            let tasks = create_tasks(&pairings, &mut state);
+            println!("Prepared {} games! Starting...", tasks.len());
+            play_tasks(tasks, &mut state);
+        }
+        TournamentMode::Swiss => {
+            let mut byes = Vec::new();
+            let mut opening_group = 0;
+            for round in 0..tournament.rounds {
+                let (pairings, bye) = swiss_pairings(&state.crosstable, &byes, opening_group);
+                opening_group += pairings.len() / 2;
+                println!("Round {}", round + 1);
+                for pairing in pairings.iter().step_by(2) {
+                    println!(
+                        "{} - {}",
+                        state.crosstable.names[pairing.white],
+                        state.crosstable.names[pairing.black]
+                    );
+                }
+                if let Some(bye) = bye {
+                    println!("Bye: {}", state.crosstable.names[bye]);
+                    state.crosstable.bye_points[bye] += SWISS_BYE_POINTS;
+                    byes.push(bye);
+                }
+                let tasks = create_tasks(&pairings, &mut state);
+                play_tasks(tasks, &mut state);
+            }
+        }
+    }
+
+    println!("+++++++++++++++++++++++++++++++++++++++++++++++++");
+    println!("{}", state.crosstable);
+    println!("{}", state.crosstable.pair_results());
+    for engine in state.engines.iter() {
+        println!("{}", engine.get_elo_gain().1);
+    }
+    println!("+++++++++++++++++++++++++++++++++++++++++++++++++");
+    let engines: Vec<&Engine> = state.engines.iter().collect();
+    let report = MatchReport::new(
+        "FabChess local tournament",
+        state.game_records,
+        &engines,
+        None,
+        state.status_line.elapsed(),
+    );
+    println!("{}", report);
+    report.write(&report_path(state.match_directory.as_ref()));
+    println!("Tournament finished!");
+}
+
+//Pairings of the same opening group get the same opening, unless openings aren't repeated
+pub fn create_tasks(pairings: &[Pairing], state: &mut TournamentState) -> Vec<PlayTask> {
+    let mut openings: HashMap<usize, Opening> = HashMap::new();
+    let mut res = Vec::with_capacity(pairings.len());
+    for pairing in pairings {
+        let key = if state.repeat {
+            pairing.opening_group
+        } else {
+            state.next_id
+        };
+        let book = &mut state.openings;
+        let opening = openings
+            .entry(key)
+            .or_insert_with(|| book.next_opening())
+            .clone();
+        let task = PlayTask {
+            start_fen: opening.start_fen,
+            opening: opening.state,
+            opening_sequence: opening.sequence,
+            p1_is_white: true,
+            id: state.next_id,
+            pair_id: key,
+            engine1: state.engines[pairing.white].clone_fresh(),
+            engine2: state.engines[pairing.black].clone_fresh(),
+        };
+        res.push(match state.match_directory.as_mut() {
+            Some(directory) => directory.schedule(task),
+            None => task,
+        });
+        state.next_id += 1;
+    }
+    res
+}
+
+pub fn play_tasks(mut tasks: Vec<PlayTask>, state: &mut TournamentState) {
+    //Games which have been played before the tournament was interrupted only count
+    if let Some(directory) = state.match_directory.as_ref() {
+        let scheduled = tasks.len();
+        let (crosstable, engines, game_records) = (
+            &mut state.crosstable,
+            &mut state.engines,
+            &mut state.game_records,
+        );
+        tasks.retain(|task| match directory.results.get(&task.id) {
+            Some(record) => {
+                game_records.extend(record.game.clone());
+                crosstable.add_result(record.engine1.id, record.engine2.id, record.engine1_score);
+                record.engine1.add_to(&mut engines[record.engine1.id]);
+                record.engine2.add_to(&mut engines[record.engine2.id]);
+                false
+            }
+            None => true,
+        });
+        state.status_line.played_before += scheduled - tasks.len();
+    }
+    let games = tasks.len();
+    let played_before = state.game_records.len();
+    let queue: Arc<ThreadSafeQueue<PlayTask>> = Arc::new(ThreadSafeQueue::new(tasks));
+    let result_queue: Arc<ThreadSafeQueue<TaskResult>> =
+        Arc::new(ThreadSafeQueue::new(Vec::with_capacity(100)));
+
+    //Start all childs
+    let mut childs = Vec::with_capacity(state.processors);
+    for _ in 0..state.processors.min(games) {
+        let queue_clone = queue.clone();
+        let res_clone = result_queue.clone();
+        let log_clone = state.error_log.clone();
+        let adjudication = state.adjudication;
+        childs.push(thread::spawn(move || {
+            start_self_play_thread(queue_clone, res_clone, log_clone, adjudication);
+        }));
+    }
+
+    //Collect results
+    let mut results_collected = 0;
+    while results_collected < games {
+        thread::sleep(Duration::from_millis(50));
+        if let Some(result) = result_queue.pop() {
+            results_collected += 1;
+            let (engine1, engine2) = (result.task.engine1.id, result.task.engine2.id);
+            let score = result.engine1_score();
+            state.crosstable.add_result(engine1, engine2, score);
+            state.engines[engine1].add(&result.task.engine1);
+            state.engines[engine2].add(&result.task.engine2);
+            state.game_records.push(GameRecord::new(&result));
+            println!(
+                "Game {} finished: {} - {} {} ({})",
+                result.task.id,
+                result.task.engine1.name,
+                result.task.engine2.name,
+                result.final_status,
+                result.reason()
+            );
+            let engines: Vec<&Engine> = state.engines.iter().collect();
+            state.status_line.update(
+                played_before + results_collected,
+                played_before + games,
+                None,
+                None,
+                &engines,
+            );
+            if results_collected % 5 == 0 {
+                println!("-------------------------------------------------");
+                println!("{}", state.crosstable);
+                println!("-------------------------------------------------");
+            }
+            if let Some(directory) = state.match_directory.as_mut() {
+                directory.save_result(&result);
+            }
+            save_transcript(&result, state.match_directory.as_ref());
+            write_pgn(result, "FabChess local tournament", &state.fen_log);
+        }
+    }
+    for child in childs {
+        child.join().expect("Couldn't join thread");
+    }
+}
@@ -0,0 +1,166 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter, Result};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+//Why an engine was disqualified
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub enum EngineFailure {
+    //The engine closed its output, usually because the process died
+    Crash,
+    Timeout,
+    IllegalMove,
+    ProtocolViolation,
+}
+
+impl EngineFailure {
+    //Value of the PGN Termination tag
+    pub fn termination(self) -> &'static str {
+        match self {
+            EngineFailure::Crash => "abandoned",
+            EngineFailure::Timeout => "time forfeit",
+            EngineFailure::IllegalMove | EngineFailure::ProtocolViolation => "rules infraction",
+        }
+    }
+}
+
+impl Display for EngineFailure {
+    fn fmt(&self, formatter: &mut Formatter) -> Result {
+        write!(
+            formatter,
+            "{}",
+            match *self {
+                EngineFailure::Crash => "Crash",
+                EngineFailure::Timeout => "Timeout",
+                EngineFailure::IllegalMove => "Illegal move",
+                EngineFailure::ProtocolViolation => "Protocol violation",
+            }
+        )
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Direction {
+    ToEngine,
+    FromEngine,
+    Stderr,
+}
+
+#[derive(Clone)]
+pub struct TranscriptLine {
+    //Milliseconds since the start of the game
+    pub time: u128,
+    pub direction: Direction,
+    pub line: String,
+}
+
+//Everything sent to and received from an engine during a game. Clones share the same lines
+#[derive(Clone)]
+pub struct Transcript {
+    start: Instant,
+    lines: Arc<Mutex<Vec<TranscriptLine>>>,
+    stdout_closed: Arc<AtomicBool>,
+    stderr_closed: Arc<AtomicBool>,
+}
+
+impl Default for Transcript {
+    fn default() -> Self {
+        Transcript::new(Instant::now())
+    }
+}
+
+impl Transcript {
+    //Transcripts of both engines in a game share the start, so that they can be merged
+    pub fn new(start: Instant) -> Self {
+        Transcript {
+            start,
+            lines: Arc::new(Mutex::new(Vec::new())),
+            stdout_closed: Arc::new(AtomicBool::new(false)),
+            stderr_closed: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn record(&self, direction: Direction, line: &str) {
+        let mut lines = self.lines.lock().unwrap();
+        for line in line.lines() {
+            lines.push(TranscriptLine {
+                time: self.start.elapsed().as_millis(),
+                direction,
+                line: line.to_owned(),
+            });
+        }
+    }
+
+    pub fn close_stdout(&self) {
+        self.stdout_closed.store(true, Ordering::Relaxed);
+    }
+
+    pub fn stdout_closed(&self) -> bool {
+        self.stdout_closed.load(Ordering::Relaxed)
+    }
+
+    pub fn close_stderr(&self) {
+        self.stderr_closed.store(true, Ordering::Relaxed);
+    }
+
+    pub fn stderr_closed(&self) -> bool {
+        self.stderr_closed.load(Ordering::Relaxed)
+    }
+
+    pub fn lines(&self) -> Vec<TranscriptLine> {
+        self.lines.lock().unwrap().clone()
+    }
+}
+
+//Lines of all transcripts in the order they happened, each prefixed by the time and the engine's name
+pub fn merge_transcripts(transcripts: &[(&str, &Transcript)]) -> String {
+    let mut lines: Vec<(&str, TranscriptLine)> = Vec::new();
+    for (name, transcript) in transcripts {
+        lines.extend(transcript.lines().into_iter().map(|line| (*name, line)));
+    }
+    //Stable, so lines of the same engine keep their order
+    lines.sort_by_key(|line| line.1.time);
+    let mut res_str = String::new();
+    for (name, line) in lines {
+        res_str.push_str(&format!(
+            "[{:>8}ms] {} {} {}\n",
+            line.time,
+            name,
+            match line.direction {
+                Direction::ToEngine => "<",
+                Direction::FromEngine => ">",
+                Direction::Stderr => "!",
+            },
+            line.line
+        ));
+    }
+    res_str
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transcript_test() {
+        let start = Instant::now();
+        let engine1 = Transcript::new(start);
+        let engine2 = Transcript::new(start);
+        engine1.record(Direction::ToEngine, "uci\n");
+        engine1.record(Direction::FromEngine, "id name A");
+        let shared = engine1.clone();
+        shared.record(Direction::FromEngine, "uciok");
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        engine2.record(Direction::Stderr, "segmentation fault\nexiting");
+        engine2.close_stdout();
+        assert!(!engine1.stdout_closed());
+        assert!(engine2.stdout_closed());
+        let merged = merge_transcripts(&[("A", &engine1), ("B", &engine2)]);
+        let lines: Vec<&str> = merged.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert!(lines[0].ends_with("A < uci"));
+        assert!(lines[2].ends_with("A > uciok"));
+        assert!(lines[3].ends_with("B ! segmentation fault"));
+    }
+}
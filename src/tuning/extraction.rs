@@ -0,0 +1,145 @@
+extern crate core;
+extern crate rand;
+
+use core::board_representation::game_state::{GameState, WHITE};
+use core::board_representation::game_state_attack_container::GameStateAttackContainer;
+use core::move_generation::movegen;
+use core::pgn::pgn_reader::{GameParser, PGNParser};
+use core::search::history::History;
+use core::search::in_check;
+use core::search::reserved_memory::{ReservedAttackContainer, ReservedMoveList};
+use core::search::timecontrol::TimeControl;
+use core::search::{MATED_IN_MAX, MAX_SEARCH_DEPTH};
+use core::tuning::labelling::label_search_scores;
+use core::tuning::loading::{save_positions, LabelledGameState, Statistics};
+use core::tuning::qsearch::stripped_q_search;
+use rand::Rng;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::BufReader;
+
+//PGN files written by the referee's selfplay
+const PGN_FILES: [&str; 1] = ["pgns.pgn"];
+const POSITION_FILE: &str = "extracted_positions.txt";
+//Plies at the start of each game that are never sampled (opening book moves)
+const SKIP_OPENING_PLIES: usize = 16;
+//Maximum amount of positions sampled from every game
+const POSITIONS_PER_GAME: usize = 10;
+//Additionally label every position with the score of a fixed depth or fixed node search
+const LABEL_WITH_SEARCH: bool = false;
+const SEARCH_LABEL_DEPTH: i16 = 8;
+const SEARCH_LABEL_NODES: Option<u64> = None;
+const SEARCH_LABEL_HASH_SIZE: usize = 16;
+
+fn main() {
+    let mut positions: Vec<LabelledGameState> = Vec::with_capacity(1_000_000);
+    let mut seen_hashes: HashSet<u64> = HashSet::with_capacity(1_000_000);
+    let mut stats = Statistics::default();
+    let mut rng = rand::thread_rng();
+
+    let mut history = History::default();
+    let mut move_list = ReservedMoveList::default();
+    let mut attack_container = ReservedAttackContainer::default();
+    let mut see_buffer = vec![0i16; MAX_SEARCH_DEPTH];
+
+    let mut filtered_check = 0;
+    let mut filtered_capture = 0;
+    let mut filtered_mate = 0;
+    let mut filtered_duplicate = 0;
+
+    for pgn_file in PGN_FILES.iter() {
+        let file = File::open(pgn_file).expect("Unable to open pgn file");
+        let parser = GameParser {
+            pgn_parser: PGNParser {
+                reader: BufReader::new(file),
+            },
+            is_opening: false,
+            opening_load_untilply: 0,
+            move_list: movegen::MoveList::default(),
+            attack_container: GameStateAttackContainer::default(),
+        };
+        for (_, states, result) in parser {
+            //Step 1. Label game
+            let label = match result {
+                1 => 1.0,
+                0 => 0.5,
+                -1 => 0.0,
+                _ => continue,
+            };
+            stats.games += 1;
+            if result == 1 {
+                stats.white_wins += 1;
+            } else if result == -1 {
+                stats.black_wins += 1;
+            } else {
+                stats.draws += 1;
+            }
+
+            //Step 2. Collect all quiet candidates of this game
+            let mut candidates: Vec<GameState> = Vec::new();
+            for state in states.iter().skip(SKIP_OPENING_PLIES) {
+                attack_container.attack_containers[0].write_state(state);
+                if in_check(state, &attack_container.attack_containers[0]) {
+                    filtered_check += 1;
+                    continue;
+                }
+                let (score, quiet_state) = stripped_q_search(
+                    -16000,
+                    16000,
+                    state.clone(),
+                    if state.color_to_move == WHITE { 1 } else { -1 },
+                    0,
+                    0,
+                    &mut history,
+                    &mut move_list,
+                    &mut attack_container,
+                    &mut see_buffer,
+                );
+                if quiet_state.hash != state.hash {
+                    filtered_capture += 1;
+                    continue;
+                }
+                if score.abs() >= -MATED_IN_MAX {
+                    filtered_mate += 1;
+                    continue;
+                }
+                candidates.push(quiet_state);
+            }
+
+            //Step 3. Sample from candidates and deduplicate
+            let mut sampled = 0;
+            while sampled < POSITIONS_PER_GAME && !candidates.is_empty() {
+                let index = rng.gen_range(0, candidates.len());
+                let state = candidates.swap_remove(index);
+                if !seen_hashes.insert(state.hash) {
+                    filtered_duplicate += 1;
+                    continue;
+                }
+                positions.push(LabelledGameState {
+                    game_state: state,
+                    label,
+                    search_score: None,
+                });
+                sampled += 1;
+            }
+        }
+    }
+    println!("{}", stats);
+    println!("Filtered in check: {}", filtered_check);
+    println!("Filtered pending captures: {}", filtered_capture);
+    println!("Filtered mate scores: {}", filtered_mate);
+    println!("Filtered duplicates: {}", filtered_duplicate);
+    println!("Positions: {}", positions.len());
+    if LABEL_WITH_SEARCH {
+        label_search_scores(
+            &mut positions,
+            SEARCH_LABEL_DEPTH,
+            match SEARCH_LABEL_NODES {
+                Some(nodes) => TimeControl::Nodes(nodes),
+                None => TimeControl::Infinite,
+            },
+            SEARCH_LABEL_HASH_SIZE,
+        );
+    }
+    save_positions(POSITION_FILE, &positions);
+}
@@ -0,0 +1,36 @@
+use crate::board_representation::game_state::WHITE;
+use crate::search::cache::Cache;
+use crate::search::searcher::{search_move, InterThreadCommunicationSystem};
+use crate::search::timecontrol::TimeControl;
+use crate::tuning::loading::LabelledGameState;
+use std::sync::Arc;
+
+//Searches every position with the given depth and time control (e.g. TimeControl::Nodes for fixed node searches)
+//and stores the score from white's point of view. Positions with only one legal move stay unlabelled.
+pub fn label_search_scores(
+    positions: &mut [LabelledGameState],
+    depth: i16,
+    tc: TimeControl,
+    hash_size: usize,
+) {
+    let itcs = Arc::new(InterThreadCommunicationSystem::new());
+    InterThreadCommunicationSystem::update_thread_count(&itcs, 1);
+    itcs.replace_cache(Cache::with_size(hash_size));
+    for position in positions.iter_mut() {
+        let score = search_move(
+            Arc::clone(&itcs),
+            depth,
+            position.game_state.clone(),
+            Vec::new(),
+            tc,
+        );
+        position.search_score = score.map(|score| {
+            if position.game_state.color_to_move == WHITE {
+                score
+            } else {
+                -score
+            }
+        });
+        itcs.cache().clear();
+    }
+}
@@ -11,6 +11,8 @@ pub enum FileFormatSupported {
 pub struct LabelledGameState {
     pub game_state: GameState,
     pub label: f64,
+    //Score of a search on this position in centipawns from white's point of view
+    pub search_score: Option<i16>,
 }
 
 pub struct Statistics {
@@ -46,7 +48,7 @@ pub fn save_positions(to_file: &str, positions: &[LabelledGameState]) {
     let mut res_str = String::new();
     for pos in positions {
         res_str.push_str(&format!(
-            "{} |{}\n",
+            "{} |{}",
             pos.game_state.to_fen(),
             if (pos.label - 1.0).abs() < std::f64::EPSILON {
                 "White"
@@ -56,6 +58,10 @@ pub fn save_positions(to_file: &str, positions: &[LabelledGameState]) {
                 "Draw"
             }
         ));
+        if let Some(score) = pos.search_score {
+            res_str.push_str(&format!(" |{}", score));
+        }
+        res_str.push_str("\n");
     }
     fs::write(to_file, res_str).expect("Unable to write positions");
 }
@@ -100,9 +106,21 @@ pub fn load_positions(
                     }
                 }
 
+                let search_score = if fen_split.len() > 2 {
+                    Some(
+                        fen_split[2]
+                            .trim()
+                            .parse::<i16>()
+                            .expect("Invalid search score"),
+                    )
+                } else {
+                    None
+                };
+
                 buf.push(LabelledGameState {
                     game_state: GameState::from_fen(fen_split[0]),
                     label: game_result,
+                    search_score,
                 });
             }
         }
@@ -127,6 +145,7 @@ pub fn load_positions(
             buf.push(LabelledGameState {
                 game_state: GameState::from_fen(fen),
                 label: game_result,
+                search_score: None,
             });
         }
         return;
@@ -1,3 +1,5 @@
+pub mod labelling;
 pub mod loading;
 pub mod parameters;
+pub mod qsearch;
 pub mod trace;
@@ -1,5 +1,10 @@
 use super::parameters::Parameters;
+#[cfg(feature = "texel-tuning")]
+use crate::board_representation::game_state::GameState;
 use crate::board_representation::game_state::{BLACK, WHITE};
+use crate::evaluation::endgame::SCALE_NORMAL;
+#[cfg(feature = "texel-tuning")]
+use crate::evaluation::eval_breakdown;
 use crate::evaluation::{EG, MG};
 
 pub struct Trace {
@@ -58,6 +63,7 @@ pub struct Trace {
     pub psqt_queen: [[i8; 8]; 8],
     pub psqt_king: [[i8; 8]; 8],
     pub phase: f64,
+    pub scale_factor: f64,
 }
 
 pub fn evaluate_psqt(
@@ -85,6 +91,19 @@ pub fn evaluate_single2(score: &mut (f64, f64), trace: i8, param_mg: f64, param_
 
 impl Trace {
     pub fn evaluate(&self, params: &Parameters) -> f64 {
+        let terms = self.evaluate_terms(params);
+        let mut res = (0., 0.);
+        for term in terms.iter() {
+            res.0 += term.0;
+            res.1 += term.1;
+        }
+        (res.0 * self.phase
+            + res.1 / 1.5 * self.scale_factor / f64::from(SCALE_NORMAL) * (128.0 - self.phase))
+            / 128.0
+    }
+
+    //Terms are in the same order as EvaluationBreakdown::terms
+    pub fn evaluate_terms(&self, params: &Parameters) -> [(f64, f64); 7] {
         //PSQT Evaluation
         let mut psqt_res = (0., 0.);
         evaluate_psqt(&mut psqt_res, &self.psqt_pawn, &params.psqt_pawn);
@@ -346,34 +365,15 @@ impl Trace {
         let mut tempo_bonus = (0., 0.);
         evaluate_single(&mut tempo_bonus, self.tempo_bonus, &params.tempo_bonus);
 
-        let res = (
-            psqt_res.0
-                + knight_res.0
-                + piecewise_res.0
-                + king_res.0
-                + pawn_res.0
-                + piecevalue_res.0
-                + tempo_bonus.0,
-            psqt_res.1
-                + knight_res.1
-                + piecewise_res.1
-                + king_res.1
-                + pawn_res.1
-                + piecevalue_res.1
-                + tempo_bonus.1,
-        );
-        #[cfg(feature = "display-eval")]
-        {
-            println!("PSQT: {},{}", psqt_res.0, psqt_res.1);
-            println!("KNIGHT:{},{}", knight_res.0, knight_res.1);
-            println!("PIECEWISE:{},{}", piecewise_res.0, piecewise_res.1);
-            println!("KING:{},{}", king_res.0, king_res.1);
-            println!("PAWNS:{},{}", pawn_res.0, pawn_res.1);
-            println!("PieceValue:{},{}", piecevalue_res.0, piecevalue_res.1);
-            println!("Tempo:{},{}", tempo_bonus.0, tempo_bonus.1);
-            println!("Res:{},{}", res.0, res.1);
-        }
-        (res.0 * self.phase + res.1 / 1.5 * (128.0 - self.phase)) / 128.0
+        [
+            tempo_bonus,
+            psqt_res,
+            piecevalue_res,
+            pawn_res,
+            knight_res,
+            piecewise_res,
+            king_res,
+        ]
     }
 
     pub fn default() -> Self {
@@ -433,10 +433,56 @@ impl Trace {
             psqt_queen: [[0; 8]; 8],
             psqt_king: [[0; 8]; 8],
             phase: 0.,
+            scale_factor: f64::from(SCALE_NORMAL),
         }
     }
 }
 
+//Compares the traced evaluation with the real evaluation of the position.
+//Returns a per-term breakdown if they diverge by more than rounding errors can explain
+#[cfg(feature = "texel-tuning")]
+pub fn trace_mismatch(game_state: &GameState, params: &Parameters) -> Option<String> {
+    let breakdown = eval_breakdown(game_state);
+    if breakdown.specialized.is_some() {
+        return None;
+    }
+    let trace_eval = breakdown.trace.evaluate(params) as i16;
+    //Rounding erros can make up for max 2 error (only 2 place where rounding can make a difference )
+    if (breakdown.final_eval - trace_eval).abs() <= 2 {
+        return None;
+    }
+    let trace_terms = breakdown.trace.evaluate_terms(params);
+    let mut res_str = String::new();
+    res_str.push_str(&format!("FEN: {}\n", game_state.to_fen()));
+    res_str.push_str(&format!(
+        "Eval: {} Trace: {} Phase: {} Scale: {}\n",
+        breakdown.final_eval, trace_eval, breakdown.phase, breakdown.scale_factor
+    ));
+    for (i, (term, sides)) in breakdown.terms().iter().enumerate() {
+        let eval_term = sides[WHITE] - sides[BLACK];
+        let diff = (
+            f64::from(eval_term.0) - trace_terms[i].0,
+            f64::from(eval_term.1) - trace_terms[i].1,
+        );
+        res_str.push_str(&format!(
+            "{:<14} Eval: {:>6} , {:>6}   Trace: {:>8.2} , {:>8.2}   Diff: {:>8.2} , {:>8.2}{}\n",
+            term,
+            eval_term.0,
+            eval_term.1,
+            trace_terms[i].0,
+            trace_terms[i].1,
+            diff.0,
+            diff.1,
+            if diff.0.abs() >= 1. || diff.1.abs() >= 1. {
+                "   <---"
+            } else {
+                ""
+            }
+        ));
+    }
+    Some(res_str)
+}
+
 #[cfg(test)]
 mod tests {
     #[cfg(feature = "texel-tuning")]
@@ -445,6 +491,8 @@ mod tests {
     use crate::board_representation::game_state::GameState;
     #[cfg(feature = "texel-tuning")]
     use crate::evaluation::eval_game_state_from_null;
+    #[cfg(feature = "texel-tuning")]
+    use std::fs;
 
     #[test]
     #[ignore]
@@ -563,4 +611,45 @@ r3k2r/1pqb2p1/p4p2/P2npP2/2pB2Bp/2P4P/2P1Q1P1/R4RK1 w kq - 0 21
             }
         }
     }
+
+    #[test]
+    #[ignore]
+    pub fn trace_coverage() {
+        if !cfg!(feature = "texel-tuning") {
+            panic!("Feature texel-tuning has to be enabled");
+        }
+        #[cfg(feature = "texel-tuning")]
+        {
+            let mut positions: Vec<GameState> = Vec::new();
+            let benchmarking_positions = fs::read_to_string("benchmarking_positions.txt")
+                .expect("Unable to read benchmarking positions");
+            for line in benchmarking_positions.lines() {
+                if !line.trim().is_empty() {
+                    positions.push(GameState::from_fen(line.trim()));
+                }
+            }
+            let sts = fs::read_to_string("testsuites/sts.epd").expect("Unable to read sts suite");
+            for line in sts.lines() {
+                let fen = line.split(" bm ").collect::<Vec<&str>>()[0].trim();
+                if !fen.is_empty() {
+                    positions.push(GameState::from_fen(fen));
+                }
+            }
+
+            let params = Parameters::default();
+            let mut mismatches = 0;
+            for position in positions.iter() {
+                if let Some(report) = super::trace_mismatch(position, &params) {
+                    println!("{}", report);
+                    mismatches += 1;
+                }
+            }
+            println!(
+                "Checked {} positions, {} mismatches",
+                positions.len(),
+                mismatches
+            );
+            assert_eq!(mismatches, 0);
+        }
+    }
 }
@@ -3,6 +3,9 @@ extern crate rand;
 
 use core::board_representation::game_state::{BLACK, WHITE};
 #[cfg(feature = "texel-tuning")]
+use core::evaluation::endgame::evaluate_specialized;
+use core::evaluation::endgame::SCALE_NORMAL;
+#[cfg(feature = "texel-tuning")]
 use core::evaluation::eval_game_state_from_null;
 use core::evaluation::{EG, MG};
 #[cfg(feature = "texel-tuning")]
@@ -43,6 +46,9 @@ const BATCH_SIZE: usize = 100_000;
 const START_LEARNING_RATE: f64 = 10.;
 const L1_REGULARIZATION: f64 = 0.;
 const L2_REGULARIZATION: f64 = 0.;
+//Weight of the game result in the training target. Positions which carry a search score
+//are trained on LABEL_LAMBDA * result + (1 - LABEL_LAMBDA) * sigmoid(score)
+const LABEL_LAMBDA: f64 = 1.0;
 
 pub fn main() {
     if !cfg!(feature = "texel-tuning") {
@@ -88,9 +94,14 @@ pub fn main() {
 pub fn init_texel_states(labelledstates: Vec<LabelledGameState>) -> Vec<TexelState> {
     let mut res: Vec<TexelState> = Vec::with_capacity(1);
     for state in labelledstates {
+        //Specialized endgame evaluators don't depend on the parameters
+        if evaluate_specialized(&state.game_state).is_some() {
+            continue;
+        }
         let eval = eval_game_state_from_null(&state.game_state);
         res.push(TexelState {
             label: state.label,
+            search_score: state.search_score.map(f64::from),
             eval: eval.final_eval as f64,
             trace: eval.trace,
         });
@@ -100,10 +111,27 @@ pub fn init_texel_states(labelledstates: Vec<LabelledGameState>) -> Vec<TexelSta
 
 pub struct TexelState {
     pub label: f64,
+    pub search_score: Option<f64>,
     pub eval: f64,
     pub trace: Trace,
 }
 
+impl TexelState {
+    pub fn target(&self, k: f64) -> f64 {
+        match self.search_score {
+            Some(score) => LABEL_LAMBDA * self.label + (1. - LABEL_LAMBDA) * sigmoid(k, score),
+            None => self.label,
+        }
+    }
+
+    pub fn dtargetdk(&self, k: f64) -> f64 {
+        match self.search_score {
+            Some(score) => (1. - LABEL_LAMBDA) * dsigmoiddk(k, score),
+            None => 0.,
+        }
+    }
+}
+
 pub struct Tuner {
     pub k: f64,
     pub positions: Vec<TexelState>,
@@ -126,10 +154,9 @@ pub fn add_gradient(
     gradient: &mut [f64; 2],
     trace: i8,
     start_of_gradient: f64,
-    phase: f64,
+    devaldmg: f64,
+    devaldeg: f64,
 ) {
-    let devaldmg = phase / 128.0;
-    let devaldeg = (1. - phase / 128.0) / 1.5;
     let x = f64::from(trace);
     gradient[MG] += start_of_gradient * devaldmg * x - portion * regularization(params[MG]);
     gradient[EG] += start_of_gradient * devaldeg * x - portion * regularization(params[EG]);
@@ -148,10 +175,10 @@ pub fn calculate_gradient(tuner: &mut Tuner, from: usize, to: usize) -> Paramete
         //Step 2. Calculate first half of gradient
         let s = sigmoid(tuner.k, pos.eval);
         let portion = 1. / (to - from) as f64;
-        let start_of_gradient = 2. * portion * (pos.label - s) * s * (1. - s);
-        let phase = pos.trace.phase;
+        let start_of_gradient = 2. * portion * (pos.target(tuner.k) - s) * s * (1. - s);
         let devaldmg = pos.trace.phase / 128.0;
-        let devaldeg = (1. - pos.trace.phase / 128.0) / 1.5;
+        let devaldeg =
+            (1. - pos.trace.phase / 128.0) / 1.5 * pos.trace.scale_factor / f64::from(SCALE_NORMAL);
         //Tempo-bonus
         if TUNE_TEMPO_BONUS {
             add_gradient(
@@ -160,7 +187,8 @@ pub fn calculate_gradient(tuner: &mut Tuner, from: usize, to: usize) -> Paramete
                 &mut gradient.tempo_bonus,
                 pos.trace.tempo_bonus,
                 start_of_gradient,
-                phase,
+                devaldmg,
+                devaldeg,
             );
         }
         //Shielding pawns
@@ -186,7 +214,8 @@ pub fn calculate_gradient(tuner: &mut Tuner, from: usize, to: usize) -> Paramete
                 &mut gradient.pawn_doubled,
                 pos.trace.pawn_doubled,
                 start_of_gradient,
-                phase,
+                devaldmg,
+                devaldeg,
             );
             add_gradient(
                 &tuner.params.pawn_isolated,
@@ -194,7 +223,8 @@ pub fn calculate_gradient(tuner: &mut Tuner, from: usize, to: usize) -> Paramete
                 &mut gradient.pawn_isolated,
                 pos.trace.pawn_isolated,
                 start_of_gradient,
-                phase,
+                devaldmg,
+                devaldeg,
             );
             add_gradient(
                 &tuner.params.pawn_backward,
@@ -202,7 +232,8 @@ pub fn calculate_gradient(tuner: &mut Tuner, from: usize, to: usize) -> Paramete
                 &mut gradient.pawn_backward,
                 pos.trace.pawn_backward,
                 start_of_gradient,
-                phase,
+                devaldmg,
+                devaldeg,
             );
             add_gradient(
                 &tuner.params.pawn_attack_center,
@@ -210,7 +241,8 @@ pub fn calculate_gradient(tuner: &mut Tuner, from: usize, to: usize) -> Paramete
                 &mut gradient.pawn_attack_center,
                 pos.trace.pawn_attack_center,
                 start_of_gradient,
-                phase,
+                devaldmg,
+                devaldeg,
             );
             add_gradient(
                 &tuner.params.pawn_attack_center,
@@ -218,7 +250,8 @@ pub fn calculate_gradient(tuner: &mut Tuner, from: usize, to: usize) -> Paramete
                 &mut gradient.pawn_mobility,
                 pos.trace.pawn_mobility,
                 start_of_gradient,
-                phase,
+                devaldmg,
+                devaldeg,
             );
         }
         //Passed pawns
@@ -229,7 +262,8 @@ pub fn calculate_gradient(tuner: &mut Tuner, from: usize, to: usize) -> Paramete
                 &mut gradient.rook_behind_support_passer,
                 pos.trace.rook_behind_support_passer,
                 start_of_gradient,
-                phase,
+                devaldmg,
+                devaldeg,
             );
             add_gradient(
                 &tuner.params.rook_behind_enemy_passer,
@@ -237,7 +271,8 @@ pub fn calculate_gradient(tuner: &mut Tuner, from: usize, to: usize) -> Paramete
                 &mut gradient.rook_behind_enemy_passer,
                 pos.trace.rook_behind_enemy_passer,
                 start_of_gradient,
-                phase,
+                devaldmg,
+                devaldeg,
             );
             add_gradient(
                 &tuner.params.pawn_passed_weak,
@@ -245,7 +280,8 @@ pub fn calculate_gradient(tuner: &mut Tuner, from: usize, to: usize) -> Paramete
                 &mut gradient.pawn_passed_weak,
                 pos.trace.pawn_passed_weak,
                 start_of_gradient,
-                phase,
+                devaldmg,
+                devaldeg,
             );
             for i in 0..7 {
                 let x = f64::from(pos.trace.pawn_passed[i]);
@@ -291,7 +327,8 @@ pub fn calculate_gradient(tuner: &mut Tuner, from: usize, to: usize) -> Paramete
                 &mut gradient.knight_supported,
                 pos.trace.knight_supported,
                 start_of_gradient,
-                phase,
+                devaldmg,
+                devaldeg,
             );
         }
         //All PST
@@ -362,7 +399,8 @@ pub fn calculate_gradient(tuner: &mut Tuner, from: usize, to: usize) -> Paramete
                 &mut gradient.rook_on_open,
                 pos.trace.rook_on_open,
                 start_of_gradient,
-                phase,
+                devaldmg,
+                devaldeg,
             );
             add_gradient(
                 &tuner.params.rook_on_semi_open,
@@ -370,7 +408,8 @@ pub fn calculate_gradient(tuner: &mut Tuner, from: usize, to: usize) -> Paramete
                 &mut gradient.rook_on_semi_open,
                 pos.trace.rook_on_open,
                 start_of_gradient,
-                phase,
+                devaldmg,
+                devaldeg,
             );
             add_gradient(
                 &tuner.params.queen_on_open,
@@ -378,7 +417,8 @@ pub fn calculate_gradient(tuner: &mut Tuner, from: usize, to: usize) -> Paramete
                 &mut gradient.queen_on_open,
                 pos.trace.queen_on_open,
                 start_of_gradient,
-                phase,
+                devaldmg,
+                devaldeg,
             );
             add_gradient(
                 &tuner.params.queen_on_semi_open,
@@ -386,7 +426,8 @@ pub fn calculate_gradient(tuner: &mut Tuner, from: usize, to: usize) -> Paramete
                 &mut gradient.queen_on_semi_open,
                 pos.trace.queen_on_semi_open,
                 start_of_gradient,
-                phase,
+                devaldmg,
+                devaldeg,
             );
             add_gradient(
                 &tuner.params.rook_on_seventh,
@@ -394,7 +435,8 @@ pub fn calculate_gradient(tuner: &mut Tuner, from: usize, to: usize) -> Paramete
                 &mut gradient.rook_on_seventh,
                 pos.trace.rook_on_seventh,
                 start_of_gradient,
-                phase,
+                devaldmg,
+                devaldeg,
             );
         }
         if TUNE_XRAY || TUNE_ALL {
@@ -404,7 +446,8 @@ pub fn calculate_gradient(tuner: &mut Tuner, from: usize, to: usize) -> Paramete
                 &mut gradient.bishop_xray_king,
                 pos.trace.bishop_xray_king,
                 start_of_gradient,
-                phase,
+                devaldmg,
+                devaldeg,
             );
             add_gradient(
                 &tuner.params.rook_xray_king,
@@ -412,7 +455,8 @@ pub fn calculate_gradient(tuner: &mut Tuner, from: usize, to: usize) -> Paramete
                 &mut gradient.rook_xray_king,
                 pos.trace.rook_xray_king,
                 start_of_gradient,
-                phase,
+                devaldmg,
+                devaldeg,
             );
             add_gradient(
                 &tuner.params.queen_xray_king,
@@ -420,7 +464,8 @@ pub fn calculate_gradient(tuner: &mut Tuner, from: usize, to: usize) -> Paramete
                 &mut gradient.queen_xray_king,
                 pos.trace.queen_xray_king,
                 start_of_gradient,
-                phase,
+                devaldmg,
+                devaldeg,
             );
         }
         //Piece values
@@ -431,7 +476,8 @@ pub fn calculate_gradient(tuner: &mut Tuner, from: usize, to: usize) -> Paramete
                 &mut gradient.pawn_piece_value,
                 pos.trace.pawns,
                 start_of_gradient,
-                phase,
+                devaldmg,
+                devaldeg,
             );
             add_gradient(
                 &tuner.params.knight_piece_value,
@@ -439,7 +485,8 @@ pub fn calculate_gradient(tuner: &mut Tuner, from: usize, to: usize) -> Paramete
                 &mut gradient.knight_piece_value,
                 pos.trace.knights,
                 start_of_gradient,
-                phase,
+                devaldmg,
+                devaldeg,
             );
             let knights = f64::from(pos.trace.knights);
             gradient.knight_value_with_pawns[pos.trace.knight_value_with_pawns as usize] +=
@@ -456,7 +503,8 @@ pub fn calculate_gradient(tuner: &mut Tuner, from: usize, to: usize) -> Paramete
                 &mut gradient.bishop_piece_value,
                 pos.trace.bishops,
                 start_of_gradient,
-                phase,
+                devaldmg,
+                devaldeg,
             );
             add_gradient(
                 &tuner.params.bishop_pair,
@@ -464,7 +512,8 @@ pub fn calculate_gradient(tuner: &mut Tuner, from: usize, to: usize) -> Paramete
                 &mut gradient.bishop_pair,
                 pos.trace.bishop_bonus,
                 start_of_gradient,
-                phase,
+                devaldmg,
+                devaldeg,
             );
             add_gradient(
                 &tuner.params.rook_piece_value,
@@ -472,7 +521,8 @@ pub fn calculate_gradient(tuner: &mut Tuner, from: usize, to: usize) -> Paramete
                 &mut gradient.rook_piece_value,
                 pos.trace.rooks,
                 start_of_gradient,
-                phase,
+                devaldmg,
+                devaldeg,
             );
             add_gradient(
                 &tuner.params.queen_piece_value,
@@ -480,7 +530,8 @@ pub fn calculate_gradient(tuner: &mut Tuner, from: usize, to: usize) -> Paramete
                 &mut gradient.queen_piece_value,
                 pos.trace.queens,
                 start_of_gradient,
-                phase,
+                devaldmg,
+                devaldeg,
             );
         }
         //Diagonally adjacent
@@ -897,7 +948,7 @@ pub fn texel_tuning(tuner: &mut Tuner) {
 pub fn average_evaluation_error(tuner: &Tuner) -> f64 {
     let mut res = 0.;
     for pos in &tuner.positions {
-        res += (pos.label - sigmoid(tuner.k, pos.eval)).powf(2.0);
+        res += (pos.target(tuner.k) - sigmoid(tuner.k, pos.eval)).powf(2.0);
     }
     res / tuner.positions.len() as f64
 }
@@ -922,7 +973,8 @@ pub fn minimize_evaluation_error_fork(tuner: &mut Tuner) -> f64 {
             let mut dedk = 0.;
             for pos in &tuner.positions[from..to] {
                 let eval = pos.eval;
-                dedk += (pos.label - sigmoid(tuner.k, eval)) * dsigmoiddk(tuner.k, eval);
+                dedk += (pos.target(tuner.k) - sigmoid(tuner.k, eval))
+                    * (dsigmoiddk(tuner.k, eval) - pos.dtargetdk(tuner.k));
             }
             dedk *= -2.0 / (to - from) as f64;
             tuner.k += -lr * dedk;
@@ -1,15 +1,19 @@
 use crate::board_representation::game_state::GameState;
 use crate::search::cache::DEFAULT_HASH_SIZE;
-use crate::search::searcher::{DEFAULT_SKIP_RATIO, DEFAULT_THREADS};
+use crate::search::params::SearchParams;
+use crate::search::searcher::DEFAULT_THREADS;
 use crate::search::timecontrol::DEFAULT_MOVE_OVERHEAD;
 
-#[derive(Copy, Clone)]
+#[derive(Clone)]
 pub struct UCIOptions {
     pub hash_size: usize,
     pub threads: usize,
     pub move_overhead: u64,
     pub debug_print: bool,
-    pub skip_ratio: usize,
+    pub hash_file: String,
+    pub never_clear_hash: bool,
+    pub hash_stats_print: bool,
+    pub search_params: SearchParams,
 }
 impl Default for UCIOptions {
     fn default() -> Self {
@@ -18,7 +22,10 @@ impl Default for UCIOptions {
             threads: DEFAULT_THREADS,
             move_overhead: DEFAULT_MOVE_OVERHEAD,
             debug_print: false,
-            skip_ratio: DEFAULT_SKIP_RATIO,
+            hash_file: String::new(),
+            never_clear_hash: false,
+            hash_stats_print: false,
+            search_params: SearchParams::default(),
         }
     }
 }
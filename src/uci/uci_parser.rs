@@ -4,16 +4,21 @@ use crate::board_representation::game_state_attack_container::GameStateAttackCon
 use crate::move_generation::makemove::make_move;
 use crate::move_generation::movegen;
 use crate::search::cache::{Cache, MAX_HASH_SIZE, MIN_HASH_SIZE};
+use crate::search::mate_search::{
+    find_mate, MateResult, MATE_SEARCH_NODE_SIZE, MIN_MATE_SEARCH_NODES,
+};
+use crate::search::params::tunable_parameter;
 use crate::search::searcher::{
-    search_move, InterThreadCommunicationSystem, MAX_SKIP_RATIO, MAX_THREADS, MIN_SKIP_RATIO,
-    MIN_THREADS,
+    search_move, InterThreadCommunicationSystem, MAX_THREADS, MIN_THREADS,
+};
+use crate::search::timecontrol::{
+    TimeControl, TimeControlInformation, MAX_MOVE_OVERHEAD, MIN_MOVE_OVERHEAD,
 };
-use crate::search::timecontrol::{TimeControl, MAX_MOVE_OVERHEAD, MIN_MOVE_OVERHEAD};
 use crate::search::MAX_SEARCH_DEPTH;
 use std::io;
 use std::sync::{atomic::Ordering, Arc};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use std::u64;
 
 pub fn parse_loop() {
@@ -22,7 +27,7 @@ pub fn parse_loop() {
     let mut us = UCIEngine::standard();
 
     let itcs = Arc::new(InterThreadCommunicationSystem::new());
-    *itcs.cache() = Cache::with_size(itcs.uci_options().hash_size);
+    itcs.replace_cache(Cache::with_size(itcs.uci_options().hash_size));
     let mut movelist = movegen::MoveList::default();
     let mut attack_container = GameStateAttackContainer::default();
 
@@ -45,7 +50,10 @@ pub fn parse_loop() {
 
             "ucinewgame" | "newgame" => {
                 newgame(&mut us);
-                itcs.cache().clear();
+                if !itcs.uci_options().never_clear_hash {
+                    itcs.cache().clear();
+                    itcs.reset_cache_statistics();
+                }
                 itcs.saved_time.store(0, Ordering::Relaxed);
             }
             "isready" => isready(&itcs, true),
@@ -54,7 +62,7 @@ pub fn parse_loop() {
             }
             "go" => {
                 isready(&itcs, false);
-                let (tc, depth) = go(&us, &arg[1..]);
+                let (tc, depth, mate) = go(&us, &arg[1..]);
                 let mut new_history = vec![];
                 for gs in &history {
                     new_history.push(gs.clone());
@@ -63,8 +71,11 @@ pub fn parse_loop() {
                 let itcs = Arc::clone(&itcs);
                 thread::Builder::new()
                     .stack_size(2 * 1024 * 1024)
-                    .spawn(move || {
-                        search_move(itcs, depth as i16, new_state, new_history, tc);
+                    .spawn(move || match mate {
+                        Some(moves) => go_mate(&itcs, &new_state, moves, depth, tc),
+                        None => {
+                            search_move(itcs, depth as i16, new_state, new_history, tc);
+                        }
                     })
                     .expect("Couldn't start thread");
             }
@@ -85,6 +96,10 @@ pub fn parse_loop() {
                     crate::evaluation::eval_game_state_from_null(&us.internal_state).final_eval
                 );
             }
+            "hashstats" => hashstats(&us, &itcs),
+            "eval" => {
+                println!("{}", crate::evaluation::eval_breakdown(&us.internal_state));
+            }
             _ => {
                 println!("Unknown command {}", line);
             }
@@ -92,6 +107,76 @@ pub fn parse_loop() {
     }
 }
 
+pub fn hashstats(engine: &UCIEngine, itcs: &InterThreadCommunicationSystem) {
+    let state = &engine.internal_state;
+    let root_plies_played = (state.full_moves - 1) * 2 + state.color_to_move;
+    println!("Pages: {}", itcs.cache().cache.pages);
+    print!("{}", itcs.cache().table_statistics(root_plies_played));
+    println!(
+        "Searches since the last clear: {}",
+        itcs.cache_statistics.lock().unwrap()
+    );
+}
+
+//The mate search may use as much memory as the hash table
+pub fn go_mate(
+    itcs: &InterThreadCommunicationSystem,
+    state: &GameState,
+    moves: usize,
+    depth: usize,
+    tc: TimeControl,
+) {
+    //Started and stopped like the normal search
+    *itcs.timeout_flag.write().unwrap() = false;
+    let _search = itcs.search_lock.lock().unwrap();
+    let start = Instant::now();
+    let mut max_nodes = (itcs.uci_options().hash_size * 1024 * 1024 / MATE_SEARCH_NODE_SIZE)
+        .max(MIN_MATE_SEARCH_NODES);
+    if let TimeControl::Nodes(nodes) = tc {
+        max_nodes = max_nodes.min(nodes as usize);
+    }
+    //A mate in n moves needs a depth of 2n - 1 plies
+    let moves = moves.min(depth.div_ceil(2));
+    let tc_information = TimeControlInformation::new(itcs.saved_time.load(Ordering::Relaxed));
+    let (result, nodes) = find_mate(state, moves, max_nodes, &|| {
+        *itcs.timeout_flag.read().unwrap()
+            || tc.time_over(
+                start.elapsed().as_millis() as u64,
+                &tc_information,
+                itcs.uci_options().move_overhead,
+            )
+    });
+    let time = start.elapsed().as_millis();
+    match result {
+        MateResult::Mate(line) => {
+            let mut pv = String::new();
+            for mv in line.iter() {
+                pv.push_str(&format!("{:?} ", mv));
+            }
+            println!(
+                "info depth {} score mate {} nodes {} time {} pv {}",
+                line.len(),
+                line.len().div_ceil(2),
+                nodes,
+                time,
+                pv
+            );
+            println!("bestmove {:?}", line[0]);
+        }
+        MateResult::NoMate => {
+            println!("info String No mate in {} exists", moves);
+            println!("bestmove 0000");
+        }
+        MateResult::Unknown => {
+            println!(
+                "info String No mate in {} found after {} nodes",
+                moves, nodes
+            );
+            println!("bestmove 0000");
+        }
+    }
+}
+
 pub fn perft(game_state: &GameState, cmd: &[&str]) {
     let depth = cmd[0].parse::<usize>().unwrap();
     crate::perft_div(&game_state, depth);
@@ -101,22 +186,35 @@ pub fn print_internal_state(engine: &UCIEngine) {
     println!("{}", engine.internal_state);
 }
 
-pub fn go(engine: &UCIEngine, cmd: &[&str]) -> (TimeControl, usize) {
+//Returns the time control, the maximum depth and the amount of moves for go mate
+pub fn go(engine: &UCIEngine, cmd: &[&str]) -> (TimeControl, usize, Option<usize>) {
     let mut wtime: u64 = 0;
     let mut btime: u64 = 0;
     let mut winc: u64 = 0;
     let mut binc: u64 = 0;
     let mut depth = MAX_SEARCH_DEPTH;
-    if cmd[0].to_lowercase() == "infinite" {
-        return (TimeControl::Infinite, depth);
-    } else if cmd[0].to_lowercase() == "depth" {
-        depth = cmd[1].parse::<usize>().unwrap();
-        return (TimeControl::Infinite, depth);
-    }
+    let mut mate = None;
+    let mut tc = None;
     let mut index = 0;
     let mut movestogo: Option<usize> = None;
     while index < cmd.len() {
-        match cmd[index] {
+        match cmd[index].to_lowercase().as_str() {
+            "infinite" => {
+                tc = Some(TimeControl::Infinite);
+                index += 1;
+                continue;
+            }
+            "depth" => {
+                depth = cmd[index + 1].parse::<usize>().unwrap();
+                tc = Some(TimeControl::Infinite);
+            }
+            "mate" => {
+                mate = Some(
+                    cmd[index + 1]
+                        .parse::<usize>()
+                        .expect("Invalid mate value!"),
+                );
+            }
             "wtime" => {
                 wtime = cmd[index + 1].parse::<u64>().unwrap_or(0);
             }
@@ -131,27 +229,34 @@ pub fn go(engine: &UCIEngine, cmd: &[&str]) -> (TimeControl, usize) {
             }
             "movetime" => {
                 let mvtime = cmd[index + 1].parse::<u64>().unwrap_or(0);
-                return (TimeControl::MoveTime(mvtime), depth);
+                tc = Some(TimeControl::MoveTime(mvtime));
+            }
+            "nodes" => {
+                let nodes = cmd[index + 1].parse::<u64>().unwrap_or(0);
+                tc = Some(TimeControl::Nodes(nodes));
             }
             "movestogo" => movestogo = Some(cmd[index + 1].parse::<usize>().unwrap_or(1)),
             _ => println!("Some parts of the go command weren't recognized well."),
         };
         index += 2;
     }
+    if let Some(tc) = tc {
+        return (tc, depth, mate);
+    }
     if movestogo.is_none() {
         if engine.internal_state.color_to_move == 0 {
-            (TimeControl::Incremental(wtime, winc), depth)
+            (TimeControl::Incremental(wtime, winc), depth, mate)
         } else {
-            (TimeControl::Incremental(btime, binc), depth)
+            (TimeControl::Incremental(btime, binc), depth, mate)
         }
     } else if let Some(mvs) = movestogo {
         if mvs == 0 {
             panic!("movestogo = 0");
         }
         if engine.internal_state.color_to_move == 0 {
-            (TimeControl::Tournament(wtime, winc, mvs), depth)
+            (TimeControl::Tournament(wtime, winc, mvs), depth, mate)
         } else {
-            (TimeControl::Tournament(btime, binc, mvs), depth)
+            (TimeControl::Tournament(btime, binc, mvs), depth, mate)
         }
     } else {
         panic!("Something went wrong in go!");
@@ -256,6 +361,17 @@ pub fn uci(engine: &UCIEngine, itcs: &InterThreadCommunicationSystem) {
         MAX_HASH_SIZE
     );
     println!("option name ClearHash type button");
+    println!(
+        "option name NeverClearHash type check default {}",
+        itcs.uci_options().never_clear_hash
+    );
+    println!("option name HashFile type string default <empty>");
+    println!(
+        "option name HashStatsPrint type check default {}",
+        itcs.uci_options().hash_stats_print
+    );
+    println!("option name SaveHash type button");
+    println!("option name LoadHash type button");
     println!(
         "option name Threads type spin default {} min {} max {}",
         itcs.uci_options().threads,
@@ -272,12 +388,6 @@ pub fn uci(engine: &UCIEngine, itcs: &InterThreadCommunicationSystem) {
         "option name DebugSMPPrint type check default {}",
         itcs.uci_options().debug_print
     );
-    println!(
-        "option name SMPSkipRatio type spin default {} min {} max {}",
-        itcs.uci_options().skip_ratio,
-        MIN_SKIP_RATIO,
-        MAX_SKIP_RATIO
-    );
     println!("uciok");
 }
 
@@ -291,15 +401,76 @@ pub fn setoption(cmd: &[&str], itcs: &Arc<InterThreadCommunicationSystem>) {
                     .parse::<usize>()
                     .expect("Invalid Hash value!");
                 itcs.uci_options().hash_size = num;
-                *itcs.cache() = Cache::with_size(num);
-                println!("info String Succesfully set Hash to {}", num);
+                itcs.replace_cache(Cache::with_size(num));
+                itcs.reset_cache_statistics();
+                println!(
+                    "info String Succesfully set Hash to {} using {}",
+                    num,
+                    itcs.cache().cache.pages
+                );
                 return;
             }
             "clearhash" => {
                 itcs.cache().clear();
+                itcs.reset_cache_statistics();
                 println!("info String Succesfully cleared hash!");
                 return;
             }
+            "neverclearhash" => {
+                let val = cmd[index + 2]
+                    .parse::<bool>()
+                    .expect("Invalid NeverClearHash value!");
+                itcs.uci_options().never_clear_hash = val;
+                println!("info String Succesfully set NeverClearHash to {}", val);
+                return;
+            }
+            "hashstatsprint" => {
+                let val = cmd[index + 2]
+                    .parse::<bool>()
+                    .expect("Invalid HashStatsPrint value!");
+                itcs.uci_options().hash_stats_print = val;
+                println!("info String Succesfully set HashStatsPrint to {}", val);
+                return;
+            }
+            "hashfile" => {
+                let path = cmd[index + 2..].join(" ");
+                itcs.uci_options().hash_file = if path == "<empty>" {
+                    String::new()
+                } else {
+                    path
+                };
+                println!(
+                    "info String Succesfully set HashFile to {}",
+                    itcs.uci_options().hash_file
+                );
+                return;
+            }
+            "savehash" => {
+                let path = itcs.uci_options().hash_file.clone();
+                match itcs.cache().save(&path) {
+                    Ok(_) => println!("info String Succesfully saved hash to {}", path),
+                    Err(e) => println!("info String Couldn't save hash to {}: {}", path, e),
+                }
+                return;
+            }
+            "loadhash" => {
+                let path = itcs.uci_options().hash_file.clone();
+                match Cache::load(&path) {
+                    Ok(cache) => {
+                        itcs.uci_options().hash_size = cache.mb_size();
+                        itcs.replace_cache(cache);
+                        itcs.reset_cache_statistics();
+                        println!(
+                            "info String Succesfully loaded hash from {}, Hash is now {} using {}",
+                            path,
+                            itcs.uci_options().hash_size,
+                            itcs.cache().cache.pages
+                        );
+                    }
+                    Err(e) => println!("info String Couldn't load hash from {}: {}", path, e),
+                }
+                return;
+            }
             "threads" => {
                 let num = cmd[index + 2]
                     .parse::<usize>()
@@ -324,15 +495,17 @@ pub fn setoption(cmd: &[&str], itcs: &Arc<InterThreadCommunicationSystem>) {
                 println!("info String Succesfully set DebugSMPPrint to {}", val);
                 return;
             }
-            "smpskipratio" => {
-                let num = cmd[index + 2]
-                    .parse::<usize>()
-                    .expect("Invalid SMPSkipRatio value!");
-                itcs.uci_options().skip_ratio = num;
-                println!("info String Succesfully set SMPSkipRatio to {}", num);
-                return;
-            }
             _ => {
+                //Hidden options for tuning the search
+                if let Some(parameter) = tunable_parameter(arg) {
+                    let num = cmd[index + 2]
+                        .parse::<i16>()
+                        .expect("Invalid search parameter value!")
+                        .clamp(parameter.min, parameter.max);
+                    *(parameter.field)(&mut itcs.uci_options().search_params) = num;
+                    println!("info String Succesfully set {} to {}", parameter.name, num);
+                    return;
+                }
                 index += 1;
             }
         }